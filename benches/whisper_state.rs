@@ -0,0 +1,82 @@
+//! CPU benchmark for reusing a `WhisperState` across passes.
+//!
+//! No model ships with the repository: point `OSWISPA_BENCH_MODEL` at a small
+//! model you have (e.g. `ggml-tiny.en.bin`) and run
+//! `cargo bench --bench whisper_state`. Without it the benchmark measures nothing. The buffers whisper.cpp allocates for a
+//! state live outside the Rust allocator, so the cost of allocating them is
+//! measured as the time `create_state` takes.
+
+use std::time::{Duration, Instant};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+const SAMPLE_RATE: usize = 16_000;
+const PASSES: usize = 5;
+
+/// Two seconds of a gliding tone, enough for the encoder to run a full window.
+fn fixture_samples() -> Vec<f32> {
+    (0..SAMPLE_RATE * 2)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            0.2 * (2.0 * std::f32::consts::PI * (220.0 + 80.0 * t) * t).sin()
+        })
+        .collect()
+}
+
+fn params() -> FullParams<'static, 'static> {
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_n_threads((num_cpus::get() / 2).max(1) as i32);
+    params.set_no_context(true);
+    params.set_single_segment(true);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_special(false);
+    params.set_print_timestamps(false);
+    params
+}
+
+fn mean(times: &[Duration]) -> Duration {
+    times.iter().sum::<Duration>() / times.len().max(1) as u32
+}
+
+fn main() {
+    let Ok(model) = std::env::var("OSWISPA_BENCH_MODEL") else {
+        eprintln!("OSWISPA_BENCH_MODEL is not set; skipping the WhisperState benchmark");
+        return;
+    };
+    let mut context_params = WhisperContextParameters::default();
+    context_params.use_gpu(false);
+    let ctx = WhisperContext::new_with_params(&model, context_params)
+        .expect("failed to load the benchmark model");
+    let samples = fixture_samples();
+
+    let mut create_times = Vec::with_capacity(PASSES);
+    let mut fresh_times = Vec::with_capacity(PASSES);
+    for _ in 0..PASSES {
+        let started = Instant::now();
+        let mut state = ctx.create_state().expect("create_state failed");
+        create_times.push(started.elapsed());
+        state
+            .full(params(), &samples)
+            .expect("transcription failed");
+        fresh_times.push(started.elapsed());
+    }
+
+    let mut state = ctx.create_state().expect("create_state failed");
+    state.full(params(), &samples).expect("warm-up failed");
+    let mut reused_times = Vec::with_capacity(PASSES);
+    for _ in 0..PASSES {
+        let started = Instant::now();
+        state
+            .full(params(), &samples)
+            .expect("transcription failed");
+        reused_times.push(started.elapsed());
+    }
+
+    println!("model: {}", model);
+    println!(
+        "state allocation (create_state): {:?} per pass",
+        mean(&create_times)
+    );
+    println!("fresh state per pass:            {:?}", mean(&fresh_times));
+    println!("reused state per pass:           {:?}", mean(&reused_times));
+}
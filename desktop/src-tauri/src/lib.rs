@@ -13,6 +13,8 @@ impl UiLifecycle {
     pub const fn from_phase(phase: EnginePhase) -> Self {
         let state = match phase {
             EnginePhase::Booting => "booting",
+            EnginePhase::NeedsModel => "needs_model",
+            EnginePhase::InstallingModel => "installing_model",
             EnginePhase::Ready => "ready",
             EnginePhase::Arming => "arming",
             EnginePhase::Listening => "listening",
@@ -123,9 +125,13 @@ mod desktop {
                         let events = engine.events();
                         thread_state.engine().replace(engine);
 
+                        // The webview has no model onboarding yet, so the engine
+                        // starts with `embedded()` and fails fast without a
+                        // model; only lifecycle phases cross over.
                         for event in events {
-                            let EngineEvent::PhaseChanged(phase) = event;
-                            publish_lifecycle(&thread_app, &thread_state, phase);
+                            if let EngineEvent::PhaseChanged { phase } = event {
+                                publish_lifecycle(&thread_app, &thread_state, phase);
+                            }
                         }
 
                         if let Some(engine) = thread_state.engine().take() {
@@ -291,6 +297,8 @@ mod tests {
     fn lifecycle_projection_is_transcript_free_and_bounded() {
         let cases = [
             (EnginePhase::Booting, "booting"),
+            (EnginePhase::NeedsModel, "needs_model"),
+            (EnginePhase::InstallingModel, "installing_model"),
             (EnginePhase::Arming, "arming"),
             (EnginePhase::Listening, "listening"),
             (EnginePhase::Processing, "processing"),
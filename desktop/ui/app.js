@@ -10,6 +10,8 @@
 
   const SIGNAL_STATES = Object.freeze({
     booting: Object.freeze({ label: "Starting", detail: "Checking local setup", receipt: "Local" }),
+    needs_model: Object.freeze({ label: "Needs a model", detail: "Choose a speech model to finish setup", receipt: "Setup" }),
+    installing_model: Object.freeze({ label: "Installing", detail: "Downloading the speech model", receipt: "Setup" }),
     ready: Object.freeze({ label: "Ready", detail: "Hold Ctrl + Super to speak", receipt: "Local" }),
     arming: Object.freeze({ label: "Arming", detail: "Preparing the microphone", receipt: "Input" }),
     listening: Object.freeze({ label: "Listening", detail: "Release the shortcut to transcribe", receipt: "Local" }),
@@ -13,6 +13,8 @@ TEXT_ASSETS = tuple(UI_ROOT.glob("*.html")) + tuple(UI_ROOT.glob("*.css")) + tup
 
 EXPECTED_SIGNAL_STATES = {
     "booting",
+    "needs_model",
+    "installing_model",
     "ready",
     "arming",
     "listening",
@@ -0,0 +1,51 @@
+#!/usr/bin/env python3
+"""Print the published SHA-256 digest and size of every catalogue model.
+
+Reads the file names from `AVAILABLE_MODELS` in src/models/mod.rs and looks
+them up in the Hugging Face LFS metadata of ggerganov/whisper.cpp. Paste the
+digests into the matching `sha256` fields after checking them.
+"""
+
+from __future__ import annotations
+
+import json
+import re
+import sys
+import urllib.request
+from pathlib import Path
+
+
+ROOT = Path(__file__).resolve().parents[1]
+CATALOGUE = ROOT / "src" / "models" / "mod.rs"
+TREE_URL = "https://huggingface.co/api/models/ggerganov/whisper.cpp/tree/main"
+
+
+def catalogue_files() -> list[str]:
+    source = CATALOGUE.read_text(encoding="utf-8")
+    return re.findall(r'filename: "([^"]+)"', source)
+
+
+def published_files() -> dict[str, dict]:
+    with urllib.request.urlopen(TREE_URL, timeout=30) as response:
+        entries = json.load(response)
+    return {entry["path"]: entry for entry in entries if entry.get("type") == "file"}
+
+
+def main() -> int:
+    published = published_files()
+    missing = 0
+    for filename in catalogue_files():
+        entry = published.get(filename)
+        lfs = (entry or {}).get("lfs") or {}
+        digest = lfs.get("oid") or lfs.get("sha256")
+        if not digest:
+            print(f"{filename}: not found in the published tree", file=sys.stderr)
+            missing += 1
+            continue
+        size_mb = lfs.get("size", entry.get("size", 0)) / (1024 * 1024)
+        print(f'{filename}: sha256: Some("{digest}"), ~{size_mb:.0f} MiB')
+    return 1 if missing else 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
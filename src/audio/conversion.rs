@@ -77,8 +77,8 @@ impl MonoPcm16Resampler {
         }
     }
 
-    #[cfg(test)]
-    fn process_interleaved(
+    /// Downmix interleaved frames and resample them in one pass.
+    pub(crate) fn process_interleaved(
         &mut self,
         samples: &[f32],
         channels: usize,
@@ -5,7 +5,7 @@
 //!
 //! arecord is part of alsa-utils and is universally available on Linux.
 
-use crate::{AppEvent, Config, RecordCommand, StreamingAudioMessage, TranscriptionBackend};
+use crate::{AppEvent, Config, RecordCommand, StreamingAudioMessage};
 use anyhow::{Context, Result};
 use crossbeam_channel::{Receiver, Sender};
 use std::fs::File;
@@ -172,8 +172,7 @@ fn run_arecord_session(
         .to_string();
     let _ = status_tx.send(AppEvent::CaptureStarted { device_name });
 
-    let streaming_active =
-        config.backend == TranscriptionBackend::Local && config.streaming.enabled;
+    let streaming_active = config.live_streaming_active();
     let chunk_duration_ms = config.streaming.chunk_duration_ms.clamp(250, 1000);
     let chunk_bytes = chunk_duration_ms as usize * 16000 * 2 / 1000;
     let mut stream_offset = 44_u64;
@@ -0,0 +1,341 @@
+//! LAN discovery of `oswispa serve` instances over DNS-SD (mDNS).
+//!
+//! Servers announce `_oswispa._tcp` with their model, request dialect and a
+//! short server ID derived from a private server identity. DNS-SD and the
+//! server's `/health` reply are unauthenticated, so the ID only tells servers
+//! apart; it does not prove who is answering. Discovery only lists candidates;
+//! adding one as a remote endpoint is always an explicit choice. The endpoint
+//! keeps the ID and refuses to upload once the server reports a different one,
+//! which catches a replaced or re-addressed server but not a deliberate impostor.
+
+use crate::{RemoteDialect, RemoteEndpointConfig};
+use anyhow::Result;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+pub(crate) const SERVICE_TYPE: &str = "_oswispa._tcp.local.";
+const DEFAULT_PATH: &str = "/v1/audio/transcriptions";
+
+/// A transcription server seen on the local network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredServer {
+    /// Instance name, e.g. "MorpheOS Voice on workstation"
+    pub name: String,
+    pub host: String,
+    /// Resolved addresses, IPv4 first
+    pub addresses: Vec<IpAddr>,
+    pub port: u16,
+    pub path: String,
+    pub dialect: RemoteDialect,
+    /// Model file the server runs
+    pub model: String,
+    /// ID to compare against the one `oswispa serve` prints; not authenticated
+    pub server_id: String,
+}
+
+impl DiscoveredServer {
+    /// Transcription URL on the preferred address.
+    pub fn endpoint_url(&self) -> Option<String> {
+        Some(format!("{}{}", self.base_url()?, self.path))
+    }
+
+    /// Health check URL on the preferred address.
+    fn health_url(&self) -> Option<String> {
+        Some(format!("{}/health", self.base_url()?))
+    }
+
+    fn base_url(&self) -> Option<String> {
+        let address = self.addresses.first()?;
+        Some(format!("http://{}", SocketAddr::new(*address, self.port)))
+    }
+
+    /// Endpoint settings for a server the user chose to add.
+    ///
+    /// LAN servers speak plain HTTP, so the endpoint opts into it; the shared
+    /// stored API key is never sent to a discovered server.
+    pub fn to_endpoint(&self) -> Option<RemoteEndpointConfig> {
+        Some(RemoteEndpointConfig {
+            endpoint: self.endpoint_url()?,
+            allow_insecure_http: true,
+            use_stored_api_key: false,
+            dialect: self.dialect,
+            discovered_server_id: Some(self.server_id.clone()),
+            ..RemoteEndpointConfig::default()
+        })
+    }
+
+    /// Ask the server at the announced address for its ID and compare it with the announcement.
+    ///
+    /// This catches stale records and address mix-ups. It is not authentication:
+    /// an impostor on the network can repeat any ID. Blocks for up to three seconds.
+    pub fn check_server_id(&self) -> Result<()> {
+        let health = self
+            .health_url()
+            .ok_or_else(|| anyhow::anyhow!("Server has no usable address"))?;
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(3))
+            .build()?;
+        require_server_id(&client, &health, &self.server_id)
+    }
+}
+
+/// Ask the `/health` endpoint at `health_url` for its server ID and compare it with `expected`.
+pub(crate) fn require_server_id(
+    client: &reqwest::blocking::Client,
+    health_url: &str,
+    expected: &str,
+) -> Result<()> {
+    let body: serde_json::Value = client.get(health_url).send()?.error_for_status()?.json()?;
+    match body.get("server_id").and_then(|value| value.as_str()) {
+        Some(server_id) if server_id == expected => Ok(()),
+        Some(server_id) => anyhow::bail!("Server reports ID {} instead of {}", server_id, expected),
+        None => anyhow::bail!("Server did not report an ID"),
+    }
+}
+
+/// Short, human-comparable ID of a server identity.
+pub(crate) fn server_id(identity: &str) -> String {
+    let digest = Sha256::digest(identity.as_bytes());
+    digest[..10]
+        .chunks(2)
+        .map(|pair| format!("{:02X}{:02X}", pair[0], pair[1]))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Browse the LAN for transcription servers for up to `timeout`.
+pub fn discover_servers(timeout: Duration) -> Result<Vec<DiscoveredServer>> {
+    let daemon = ServiceDaemon::new()?;
+    let events = daemon.browse(SERVICE_TYPE)?;
+    let deadline = Instant::now() + timeout;
+    let mut servers: Vec<DiscoveredServer> = Vec::new();
+
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        let Ok(event) = events.recv_timeout(remaining) else {
+            break;
+        };
+        if let ServiceEvent::ServiceResolved(info) = event {
+            let properties: HashMap<String, String> = info
+                .get_properties()
+                .iter()
+                .map(|property| (property.key().to_string(), property.val_str().to_string()))
+                .collect();
+            let Some(server) = parse_service(
+                info.get_fullname(),
+                info.get_hostname(),
+                info.get_addresses().iter().copied().collect(),
+                info.get_port(),
+                &properties,
+            ) else {
+                debug!("Ignoring incomplete DNS-SD record {}", info.get_fullname());
+                continue;
+            };
+            servers.retain(|known| known.name != server.name);
+            servers.push(server);
+        }
+    }
+
+    let _ = daemon.stop_browse(SERVICE_TYPE);
+    let _ = daemon.shutdown();
+    servers.sort_by(|left, right| left.name.cmp(&right.name));
+    Ok(servers)
+}
+
+fn parse_service(
+    fullname: &str,
+    hostname: &str,
+    mut addresses: Vec<IpAddr>,
+    port: u16,
+    properties: &HashMap<String, String>,
+) -> Option<DiscoveredServer> {
+    let server_id = properties.get("id").filter(|id| !id.is_empty())?.clone();
+    if addresses.is_empty() || port == 0 {
+        return None;
+    }
+    addresses.sort_by_key(|address| (address.is_ipv6(), *address));
+
+    let path = properties
+        .get("path")
+        .filter(|path| path.starts_with('/'))
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_PATH.to_string());
+    let dialect = match properties.get("dialect").map(String::as_str) {
+        Some("whisper_cpp") => RemoteDialect::WhisperCpp,
+        _ => RemoteDialect::OpenAi,
+    };
+
+    Some(DiscoveredServer {
+        name: fullname
+            .strip_suffix(&format!(".{}", SERVICE_TYPE))
+            .unwrap_or(fullname)
+            .to_string(),
+        host: hostname.trim_end_matches('.').to_string(),
+        addresses,
+        port,
+        path,
+        dialect,
+        model: properties.get("model").cloned().unwrap_or_default(),
+        server_id,
+    })
+}
+
+/// Keeps a server announced on the LAN until dropped.
+pub(crate) struct Advertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+/// Announce `oswispa serve` on the LAN; loopback listeners are not advertised.
+pub(crate) fn advertise(
+    listen: SocketAddr,
+    model: &str,
+    server_id: &str,
+) -> Result<Option<Advertisement>> {
+    if listen.ip().is_loopback() {
+        info!("Not advertising a loopback-only server on the LAN");
+        return Ok(None);
+    }
+
+    let host = local_hostname();
+    let properties = [
+        ("path", DEFAULT_PATH),
+        ("dialect", "openai"),
+        ("model", model),
+        ("id", server_id),
+        ("version", env!("CARGO_PKG_VERSION")),
+    ];
+    let instance = format!("{} on {}", crate::PRODUCT_NAME, host);
+    let host_name = format!("{}.local.", host);
+    let service = if listen.ip().is_unspecified() {
+        ServiceInfo::new(
+            SERVICE_TYPE,
+            &instance,
+            &host_name,
+            "",
+            listen.port(),
+            &properties[..],
+        )?
+        .enable_addr_auto()
+    } else {
+        ServiceInfo::new(
+            SERVICE_TYPE,
+            &instance,
+            &host_name,
+            listen.ip(),
+            listen.port(),
+            &properties[..],
+        )?
+    };
+
+    let daemon = ServiceDaemon::new()?;
+    let fullname = service.get_fullname().to_string();
+    daemon.register(service)?;
+    info!("Advertising {} on the LAN", fullname);
+    Ok(Some(Advertisement { daemon, fullname }))
+}
+
+impl Drop for Advertisement {
+    fn drop(&mut self) {
+        if let Err(err) = self.daemon.unregister(&self.fullname) {
+            warn!("Failed to withdraw DNS-SD announcement: {}", err);
+        }
+        let _ = self.daemon.shutdown();
+    }
+}
+
+fn local_hostname() -> String {
+    #[cfg(unix)]
+    {
+        let mut buffer = [0u8; 256];
+        let result =
+            unsafe { libc::gethostname(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len()) };
+        if result == 0 {
+            let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+            if let Ok(name) = std::str::from_utf8(&buffer[..end]) {
+                let name = name.split('.').next().unwrap_or_default();
+                if !name.is_empty() {
+                    return name.to_string();
+                }
+            }
+        }
+    }
+
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "oswispa".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn server_id_is_stable_and_grouped() {
+        let first = server_id("identity");
+        assert_eq!(first, server_id("identity"));
+        assert_ne!(first, server_id("other identity"));
+        assert_eq!(first.len(), 24);
+        assert_eq!(first.split('-').count(), 5);
+    }
+
+    #[test]
+    fn resolved_services_become_endpoints() {
+        let server = parse_service(
+            "MorpheOS Voice on gpu-box._oswispa._tcp.local.",
+            "gpu-box.local.",
+            vec!["fe80::1".parse().unwrap(), "192.168.1.20".parse().unwrap()],
+            8765,
+            &properties(&[("id", "ABCD-EF01"), ("model", "ggml-large-v3.bin")]),
+        )
+        .unwrap();
+
+        assert_eq!(server.name, "MorpheOS Voice on gpu-box");
+        assert_eq!(server.host, "gpu-box.local");
+        assert_eq!(
+            server.endpoint_url().as_deref(),
+            Some("http://192.168.1.20:8765/v1/audio/transcriptions")
+        );
+        assert_eq!(
+            server.health_url().as_deref(),
+            Some("http://192.168.1.20:8765/health")
+        );
+
+        let endpoint = server.to_endpoint().unwrap();
+        assert!(endpoint.allow_insecure_http);
+        assert!(!endpoint.use_stored_api_key);
+        assert_eq!(endpoint.discovered_server_id.as_deref(), Some("ABCD-EF01"));
+    }
+
+    #[test]
+    fn health_url_ignores_the_announced_path() {
+        let server = parse_service(
+            "a",
+            "h",
+            vec!["192.168.1.20".parse().unwrap()],
+            8765,
+            &properties(&[("id", "X"), ("path", "/health/v1/audio/transcriptions")]),
+        )
+        .unwrap();
+        assert_eq!(
+            server.health_url().as_deref(),
+            Some("http://192.168.1.20:8765/health")
+        );
+    }
+
+    #[test]
+    fn services_without_id_or_address_are_ignored() {
+        let address = vec!["192.168.1.20".parse().unwrap()];
+        assert!(parse_service("a", "h", address.clone(), 8765, &properties(&[])).is_none());
+        assert!(parse_service("a", "h", Vec::new(), 8765, &properties(&[("id", "X")])).is_none());
+        assert!(parse_service("a", "h", address, 0, &properties(&[("id", "X")])).is_none());
+    }
+}
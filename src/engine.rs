@@ -7,7 +7,9 @@ use serde::{Deserialize, Serialize};
 use std::thread::JoinHandle;
 
 /// Commands accepted by a running MorpheOS Voice engine.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Models are identified by their catalogue file name, e.g. `ggml-base.en.bin`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum EngineCommand {
     Start,
@@ -15,6 +17,16 @@ pub enum EngineCommand {
     Cancel,
     Reload,
     Shutdown,
+    /// Report the model catalogue as [`EngineEvent::ModelsListed`].
+    ListModels,
+    /// Download a catalogue model in the background, reporting progress.
+    DownloadModel(String),
+    /// Make an installed catalogue model the active local model.
+    ActivateModel(String),
+    /// Use this Whisper language code for the next dictation only; `None` unpins.
+    PinLanguage(Option<String>),
+    /// Switch to a configured dictation profile; `None` uses the base settings.
+    SwitchProfile(Option<String>),
 }
 
 /// Transcript-free lifecycle phases safe to expose in routine desktop state.
@@ -22,6 +34,10 @@ pub enum EngineCommand {
 #[serde(rename_all = "snake_case")]
 pub enum EnginePhase {
     Booting,
+    /// No usable local model is configured; waiting for one to be activated.
+    NeedsModel,
+    /// A model download started from [`EnginePhase::NeedsModel`] is running.
+    InstallingModel,
     Ready,
     Arming,
     Listening,
@@ -49,14 +65,71 @@ impl From<&AppPhase> for EnginePhase {
     }
 }
 
+/// A catalogue model as reported to an observing shell.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EngineModel {
+    /// Catalogue file name, used as the id in model commands
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub size_mb: u32,
+    pub installed: bool,
+    pub active: bool,
+    pub downloading: bool,
+    /// The model the first-run wizard would suggest for this machine
+    pub recommended: bool,
+}
+
+/// Why a model command failed, without the underlying error text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelFailure {
+    UnknownModel,
+    AlreadyDownloading,
+    InsufficientSpace,
+    DownloadFailed,
+    NotInstalled,
+    InvalidModel,
+    ConfigNotSaved,
+}
+
 /// Lifecycle facts emitted to an observing shell.
 ///
 /// These events intentionally contain no transcript text, audio, clipboard
-/// contents, device names, or raw error messages.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(tag = "event", content = "phase", rename_all = "snake_case")]
+/// contents, device names, or raw error messages. Every event is a flat JSON
+/// object tagged by `event`, e.g. `{"event":"phase_changed","phase":"ready"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
 pub enum EngineEvent {
-    PhaseChanged(EnginePhase),
+    PhaseChanged {
+        phase: EnginePhase,
+    },
+    ModelsListed {
+        models: Vec<EngineModel>,
+    },
+    ModelDownloadProgress {
+        model: String,
+        downloaded_bytes: u64,
+        total_bytes: u64,
+    },
+    ModelInstalled {
+        model: String,
+    },
+    ModelInstallFailed {
+        model: String,
+        reason: ModelFailure,
+    },
+    ModelActivated {
+        model: String,
+    },
+    ModelActivationFailed {
+        model: String,
+        reason: ModelFailure,
+    },
+    /// The active dictation profile, reported at startup and on every switch.
+    ProfileChanged {
+        profile: Option<String>,
+    },
 }
 
 /// Compatibility services started alongside the engine runtime.
@@ -68,9 +141,14 @@ pub struct EngineOptions {
     pub launch_ipc: bool,
     /// Permit the terminal first-run model wizard to read from stdin.
     ///
-    /// Embedding GUI shells must disable this and provide their own onboarding
-    /// before restarting the engine with a valid local model.
+    /// Embedding GUI shells must disable this and either provide onboarding
+    /// through model commands or restart the engine with a valid local model.
     pub interactive_setup: bool,
+    /// Without a valid local model and without the terminal wizard, wait in
+    /// [`EnginePhase::NeedsModel`] for model commands instead of failing.
+    ///
+    /// Only shells that list, download and activate models should enable this.
+    pub model_onboarding: bool,
 }
 
 impl Default for EngineOptions {
@@ -79,17 +157,22 @@ impl Default for EngineOptions {
             launch_tray: true,
             launch_ipc: true,
             interactive_setup: true,
+            model_onboarding: false,
         }
     }
 }
 
 impl EngineOptions {
     /// Options for an embedding desktop shell that owns its tray and IPC.
+    ///
+    /// Start-up fails fast without a usable model; set `model_onboarding`
+    /// when the shell can install one.
     pub const fn embedded() -> Self {
         Self {
             launch_tray: false,
             launch_ipc: false,
             interactive_setup: false,
+            model_onboarding: false,
         }
     }
 }
@@ -121,10 +204,13 @@ impl EngineHandle {
             .spawn(move || {
                 let result = worker(command_rx, event_tx);
                 if result.is_err() {
-                    let _ = terminal_event_tx
-                        .send(EngineEvent::PhaseChanged(EnginePhase::NeedsAttention));
+                    let _ = terminal_event_tx.send(EngineEvent::PhaseChanged {
+                        phase: EnginePhase::NeedsAttention,
+                    });
                 }
-                let _ = terminal_event_tx.send(EngineEvent::PhaseChanged(EnginePhase::Stopped));
+                let _ = terminal_event_tx.send(EngineEvent::PhaseChanged {
+                    phase: EnginePhase::Stopped,
+                });
                 result
             })
             .context("Failed to start the MorpheOS Voice engine thread")?;
@@ -170,6 +256,26 @@ impl EngineHandle {
         self.command(EngineCommand::Reload)
     }
 
+    pub fn list_models(&self) -> Result<()> {
+        self.command(EngineCommand::ListModels)
+    }
+
+    pub fn download_model(&self, id: &str) -> Result<()> {
+        self.command(EngineCommand::DownloadModel(id.to_string()))
+    }
+
+    pub fn activate_model(&self, id: &str) -> Result<()> {
+        self.command(EngineCommand::ActivateModel(id.to_string()))
+    }
+
+    pub fn pin_language(&self, language: Option<&str>) -> Result<()> {
+        self.command(EngineCommand::PinLanguage(language.map(str::to_string)))
+    }
+
+    pub fn switch_profile(&self, profile: Option<&str>) -> Result<()> {
+        self.command(EngineCommand::SwitchProfile(profile.map(str::to_string)))
+    }
+
     /// Ask the engine to stop and wait for its owning runtime thread.
     pub fn shutdown(mut self) -> Result<()> {
         // If the command side has already closed, joining still returns the
@@ -209,10 +315,13 @@ mod tests {
 
     fn test_handle(observed_tx: Sender<EngineCommand>) -> EngineHandle {
         EngineHandle::spawn_worker(move |command_rx, event_tx| {
-            event_tx.send(EngineEvent::PhaseChanged(EnginePhase::Ready))?;
+            event_tx.send(EngineEvent::PhaseChanged {
+                phase: EnginePhase::Ready,
+            })?;
             for command in command_rx {
+                let shutdown = command == EngineCommand::Shutdown;
                 observed_tx.send(command)?;
-                if command == EngineCommand::Shutdown {
+                if shutdown {
                     break;
                 }
             }
@@ -230,11 +339,21 @@ mod tests {
         handle.stop_recording().unwrap();
         handle.cancel_recording().unwrap();
         handle.reload_config().unwrap();
+        handle.download_model("ggml-base.en.bin").unwrap();
+        handle.switch_profile(Some("email")).unwrap();
 
         assert_eq!(observed_rx.recv().unwrap(), EngineCommand::Start);
         assert_eq!(observed_rx.recv().unwrap(), EngineCommand::Stop);
         assert_eq!(observed_rx.recv().unwrap(), EngineCommand::Cancel);
         assert_eq!(observed_rx.recv().unwrap(), EngineCommand::Reload);
+        assert_eq!(
+            observed_rx.recv().unwrap(),
+            EngineCommand::DownloadModel("ggml-base.en.bin".to_string())
+        );
+        assert_eq!(
+            observed_rx.recv().unwrap(),
+            EngineCommand::SwitchProfile(Some("email".to_string()))
+        );
         handle.shutdown().unwrap();
         assert_eq!(observed_rx.recv().unwrap(), EngineCommand::Shutdown);
     }
@@ -247,12 +366,16 @@ mod tests {
 
         assert_eq!(
             events.recv_timeout(Duration::from_secs(1)).unwrap(),
-            EngineEvent::PhaseChanged(EnginePhase::Ready)
+            EngineEvent::PhaseChanged {
+                phase: EnginePhase::Ready
+            }
         );
         handle.shutdown().unwrap();
         assert_eq!(
             events.recv_timeout(Duration::from_secs(1)).unwrap(),
-            EngineEvent::PhaseChanged(EnginePhase::Stopped)
+            EngineEvent::PhaseChanged {
+                phase: EnginePhase::Stopped
+            }
         );
     }
 
@@ -261,13 +384,54 @@ mod tests {
         let phase = AppPhase::Listening {
             device_name: "Private microphone label".to_string(),
         };
-        let event = EngineEvent::PhaseChanged(EnginePhase::from(&phase));
+        let event = EngineEvent::PhaseChanged {
+            phase: EnginePhase::from(&phase),
+        };
         let json = serde_json::to_string(&event).unwrap();
 
-        assert_eq!(event, EngineEvent::PhaseChanged(EnginePhase::Listening));
+        assert_eq!(
+            event,
+            EngineEvent::PhaseChanged {
+                phase: EnginePhase::Listening
+            }
+        );
         assert!(!json.contains("Private microphone label"));
     }
 
+    #[test]
+    fn model_failures_carry_no_error_text() {
+        let event = EngineEvent::ModelInstallFailed {
+            model: "ggml-base.en.bin".to_string(),
+            reason: ModelFailure::InsufficientSpace,
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "event": "model_install_failed",
+                "model": "ggml-base.en.bin",
+                "reason": "insufficient_space"
+            })
+        );
+    }
+
+    #[test]
+    fn phase_changed_keeps_its_published_shape() {
+        let event = EngineEvent::PhaseChanged {
+            phase: EnginePhase::Listening,
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({ "event": "phase_changed", "phase": "listening" })
+        );
+        assert_eq!(
+            serde_json::from_str::<EngineEvent>(r#"{"event":"phase_changed","phase":"ready"}"#)
+                .unwrap(),
+            EngineEvent::PhaseChanged {
+                phase: EnginePhase::Ready
+            }
+        );
+    }
+
     #[test]
     fn embedded_mode_disables_terminal_setup_and_compatibility_services() {
         let options = EngineOptions::embedded();
@@ -275,5 +439,6 @@ mod tests {
         assert!(!options.launch_tray);
         assert!(!options.launch_ipc);
         assert!(!options.interactive_setup);
+        assert!(!options.model_onboarding);
     }
 }
@@ -0,0 +1,483 @@
+//! `oswispa eval`: word and character error rates for local configurations.
+//!
+//! Reads a directory of audio clips, each with a `.txt` reference transcript
+//! next to it, and runs every clip through the same path as dictation: local
+//! Whisper with the fallback chain, then the personal dictionary and spoken
+//! punctuation. Configurations are named overlays on `config.json`, so a
+//! model, language or dictionary change can be compared against the current
+//! settings. The JSON report goes to stdout or `--output`.
+
+use crate::personalisation::{self, Personalisation};
+use crate::runtime::{load_config, post_process_transcript};
+use crate::server::audio;
+use crate::transcribe::LocalTranscriber;
+use crate::{models, persistence, Config};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::{info, warn};
+
+const USAGE: &str =
+    "Usage: oswispa eval <clips-dir> [--configs FILE] [--output FILE] [--fail-above-wer RATE]";
+pub const EVAL_SCHEMA_VERSION: u32 = 1;
+
+/// One configuration to evaluate, as listed in a `--configs` file.
+#[derive(Debug, Clone, Deserialize)]
+struct EvalConfiguration {
+    name: String,
+    /// Partial `config.json` merged over the current configuration
+    #[serde(default)]
+    overrides: serde_json::Value,
+    /// Apply the personal dictionary and send its vocabulary prompt
+    #[serde(default = "default_true")]
+    dictionary: bool,
+    /// Personalisation document to use instead of the current one
+    #[serde(default)]
+    personalisation: Option<PathBuf>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl EvalConfiguration {
+    fn current() -> Self {
+        Self {
+            name: "current".to_string(),
+            overrides: serde_json::Value::Null,
+            dictionary: true,
+            personalisation: None,
+        }
+    }
+}
+
+/// Edit counts against a reference, summed over clips for corpus rates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ErrorCounts {
+    pub reference_words: usize,
+    pub word_errors: usize,
+    pub reference_chars: usize,
+    pub char_errors: usize,
+}
+
+impl ErrorCounts {
+    fn add(&mut self, other: &ErrorCounts) {
+        self.reference_words += other.reference_words;
+        self.word_errors += other.word_errors;
+        self.reference_chars += other.reference_chars;
+        self.char_errors += other.char_errors;
+    }
+
+    pub fn wer(&self) -> f64 {
+        rate(self.word_errors, self.reference_words)
+    }
+
+    pub fn cer(&self) -> f64 {
+        rate(self.char_errors, self.reference_chars)
+    }
+}
+
+fn rate(errors: usize, reference: usize) -> f64 {
+    if reference == 0 {
+        if errors == 0 {
+            0.0
+        } else {
+            1.0
+        }
+    } else {
+        errors as f64 / reference as f64
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ClipResult {
+    clip: String,
+    wer: f64,
+    cer: f64,
+    #[serde(flatten)]
+    counts: ErrorCounts,
+    reference: String,
+    hypothesis: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ConfigurationReport {
+    name: String,
+    model: PathBuf,
+    language: String,
+    dictionary: bool,
+    punctuation_commands: bool,
+    wer: f64,
+    cer: f64,
+    #[serde(flatten)]
+    counts: ErrorCounts,
+    failures: usize,
+    seconds: f64,
+    clips: Vec<ClipResult>,
+}
+
+#[derive(Debug, Serialize)]
+struct EvalReport {
+    schema_version: u32,
+    dataset: PathBuf,
+    clip_count: usize,
+    configurations: Vec<ConfigurationReport>,
+}
+
+struct Options {
+    dataset: PathBuf,
+    configs: Option<PathBuf>,
+    output: Option<PathBuf>,
+    fail_above_wer: Option<f64>,
+}
+
+fn parse_arguments(args: &[String]) -> Result<Options> {
+    let mut dataset = None;
+    let mut options = Options {
+        dataset: PathBuf::new(),
+        configs: None,
+        output: None,
+        fail_above_wer: None,
+    };
+    let mut args = args.iter();
+    while let Some(argument) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("{} needs a value\n{}", argument, USAGE))
+        };
+        match argument.as_str() {
+            "--configs" => options.configs = Some(PathBuf::from(value()?)),
+            "--output" => options.output = Some(PathBuf::from(value()?)),
+            "--fail-above-wer" => {
+                options.fail_above_wer = Some(
+                    value()?
+                        .parse()
+                        .with_context(|| format!("Invalid --fail-above-wer\n{}", USAGE))?,
+                )
+            }
+            flag if flag.starts_with("--") => {
+                anyhow::bail!("Unknown eval option: {}\n{}", flag, USAGE)
+            }
+            path if dataset.is_none() => dataset = Some(PathBuf::from(path)),
+            extra => anyhow::bail!("Unexpected argument: {}\n{}", extra, USAGE),
+        }
+    }
+    options.dataset =
+        dataset.ok_or_else(|| anyhow::anyhow!("Missing clips directory\n{}", USAGE))?;
+    Ok(options)
+}
+
+/// Run the evaluation described by `eval` arguments and print or write the report.
+pub(crate) fn run_eval(args: &[String]) -> Result<()> {
+    let options = parse_arguments(args)?;
+    let clips = find_clips(&options.dataset)?;
+    let base = load_config()?;
+    let configurations = match &options.configs {
+        Some(path) => {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {:?}", path))?;
+            serde_json::from_str::<Vec<EvalConfiguration>>(&text)
+                .with_context(|| format!("Invalid configurations file {:?}", path))?
+        }
+        None => vec![EvalConfiguration::current()],
+    };
+    if configurations.is_empty() {
+        anyhow::bail!("The configurations file lists no configurations");
+    }
+
+    let mut reports = Vec::new();
+    for configuration in &configurations {
+        reports.push(evaluate(configuration, &base, &clips)?);
+    }
+    let report = EvalReport {
+        schema_version: EVAL_SCHEMA_VERSION,
+        dataset: options.dataset.clone(),
+        clip_count: clips.len(),
+        configurations: reports,
+    };
+
+    let json = serde_json::to_string_pretty(&report)?;
+    match &options.output {
+        Some(path) => persistence::write_private(path, json.as_bytes())?,
+        None => println!("{}", json),
+    }
+
+    if let Some(limit) = options.fail_above_wer {
+        let over: Vec<String> = report
+            .configurations
+            .iter()
+            .filter(|configuration| configuration.wer > limit || configuration.failures > 0)
+            .map(|configuration| format!("{} ({:.3})", configuration.name, configuration.wer))
+            .collect();
+        if !over.is_empty() {
+            anyhow::bail!(
+                "WER above {} or failed clips in: {}",
+                limit,
+                over.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Audio clips in `dir` paired with their reference transcripts, sorted by name.
+fn find_clips(dir: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let mut clips = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {:?}", dir))? {
+        let path = entry?.path();
+        let is_audio = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| matches!(extension.to_ascii_lowercase().as_str(), "wav" | "flac"))
+            .unwrap_or(false);
+        if !is_audio {
+            continue;
+        }
+        let reference_path = path.with_extension("txt");
+        match std::fs::read_to_string(&reference_path) {
+            Ok(reference) => clips.push((path, reference.trim().to_string())),
+            Err(_) => warn!("Skipping {:?}: no reference transcript", path),
+        }
+    }
+    if clips.is_empty() {
+        anyhow::bail!(
+            "No clips with reference transcripts in {:?}; pair each .wav or .flac with a .txt of the same name",
+            dir
+        );
+    }
+    clips.sort();
+    Ok(clips)
+}
+
+fn evaluate(
+    configuration: &EvalConfiguration,
+    base: &Config,
+    clips: &[(PathBuf, String)],
+) -> Result<ConfigurationReport> {
+    let config = overlay_config(base, &configuration.overrides)
+        .with_context(|| format!("Invalid overrides for {}", configuration.name))?;
+    models::validate_model_path(&config.model_path).with_context(|| {
+        format!(
+            "No usable model for {} at {:?}",
+            configuration.name, config.model_path
+        )
+    })?;
+    let dictionary = match (&configuration.personalisation, configuration.dictionary) {
+        (_, false) => Personalisation::default(),
+        (Some(path), true) => personalisation::import_personalisation(path)?,
+        (None, true) => personalisation::load_personalisation()?,
+    };
+    let prompt = dictionary.vocabulary_prompt();
+
+    info!(
+        "Evaluating {} with {:?} on {} clip(s)",
+        configuration.name,
+        config.model_path,
+        clips.len()
+    );
+    let started = Instant::now();
+    let mut transcriber = LocalTranscriber::new(&config);
+    let mut totals = ErrorCounts::default();
+    let mut failures = 0;
+    let mut results = Vec::new();
+    for (path, reference) in clips {
+        let outcome = transcribe_clip(&mut transcriber, path, &config, prompt.as_deref())
+            .map(|text| post_process_transcript(&text, &config, &dictionary));
+        let (hypothesis, error) = match outcome {
+            Ok(text) => (text, None),
+            Err(err) => {
+                failures += 1;
+                (String::new(), Some(err.to_string()))
+            }
+        };
+        let counts = error_counts(reference, &hypothesis);
+        totals.add(&counts);
+        results.push(ClipResult {
+            clip: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            wer: counts.wer(),
+            cer: counts.cer(),
+            counts,
+            reference: reference.clone(),
+            hypothesis,
+            error,
+        });
+    }
+
+    Ok(ConfigurationReport {
+        name: configuration.name.clone(),
+        model: config.model_path.clone(),
+        language: config.language.clone(),
+        dictionary: configuration.dictionary,
+        punctuation_commands: config.punctuation_commands,
+        wer: totals.wer(),
+        cer: totals.cer(),
+        counts: totals,
+        failures,
+        seconds: started.elapsed().as_secs_f64(),
+        clips: results,
+    })
+}
+
+fn transcribe_clip(
+    transcriber: &mut LocalTranscriber,
+    path: &Path,
+    config: &Config,
+    prompt: Option<&str>,
+) -> Result<String> {
+    let bytes = std::fs::read(path)?;
+    let samples = audio::decode_upload(&bytes, None)?;
+    let wav = crate::audio::private_recording_temp_path()?;
+    audio::write_wav(&wav, &samples)?;
+    transcriber
+        .transcribe_file(&wav, config, prompt)
+        .map(|transcript| transcript.text)
+}
+
+/// `base` with `overrides` merged in, key by key for nested sections.
+fn overlay_config(base: &Config, overrides: &serde_json::Value) -> Result<Config> {
+    let mut merged = serde_json::to_value(base)?;
+    if !overrides.is_null() {
+        merge_json(&mut merged, overrides);
+    }
+    Ok(serde_json::from_value(merged)?)
+}
+
+fn merge_json(target: &mut serde_json::Value, overrides: &serde_json::Value) {
+    match (target, overrides) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge_json(
+                    target.entry(key.clone()).or_insert(serde_json::Value::Null),
+                    value,
+                );
+            }
+        }
+        (target, value) => *target = value.clone(),
+    }
+}
+
+/// Case-folded words without surrounding punctuation, as Whisper evaluations usually score.
+fn normalise_words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Word and character edit counts of `hypothesis` against `reference`.
+pub fn error_counts(reference: &str, hypothesis: &str) -> ErrorCounts {
+    let reference_words = normalise_words(reference);
+    let hypothesis_words = normalise_words(hypothesis);
+    let reference_chars: Vec<char> = reference_words.join(" ").chars().collect();
+    let hypothesis_chars: Vec<char> = hypothesis_words.join(" ").chars().collect();
+    ErrorCounts {
+        reference_words: reference_words.len(),
+        word_errors: edit_distance(&reference_words, &hypothesis_words),
+        reference_chars: reference_chars.len(),
+        char_errors: edit_distance(&reference_chars, &hypothesis_chars),
+    }
+}
+
+/// Levenshtein distance: substitutions, deletions and insertions.
+fn edit_distance<T: PartialEq>(reference: &[T], hypothesis: &[T]) -> usize {
+    let mut previous: Vec<usize> = (0..=hypothesis.len()).collect();
+    let mut current = vec![0; hypothesis.len() + 1];
+    for (i, expected) in reference.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, actual) in hypothesis.iter().enumerate() {
+            let substitution = previous[j] + usize::from(expected != actual);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[hypothesis.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_rates_count_substitutions_deletions_and_insertions() {
+        let exact = error_counts("Send the report, please.", "send the report please");
+        assert_eq!(exact.word_errors, 0);
+        assert_eq!(exact.char_errors, 0);
+
+        let counts = error_counts("the cat sat on the mat", "the cat sat on a mat today");
+        assert_eq!(counts.reference_words, 6);
+        assert_eq!(counts.word_errors, 2);
+        assert!((counts.wer() - 2.0 / 6.0).abs() < 1e-9);
+
+        let dropped = error_counts("one two three", "one three");
+        assert_eq!(dropped.word_errors, 1);
+        assert_eq!(dropped.char_errors, 4);
+
+        assert_eq!(error_counts("", "").wer(), 0.0);
+        assert_eq!(error_counts("", "noise").wer(), 1.0);
+    }
+
+    #[test]
+    fn overrides_merge_into_nested_sections() {
+        let base = Config {
+            fallback_model_path: Some(PathBuf::from("/models/ggml-tiny.en.bin")),
+            ..Config::default()
+        };
+        let config = overlay_config(
+            &base,
+            &serde_json::json!({
+                "language": "de",
+                "long_dictation": {"workers": 2},
+                "fallback_model_path": null
+            }),
+        )
+        .unwrap();
+        assert_eq!(config.language, "de");
+        assert_eq!(config.long_dictation.workers, Some(2));
+        assert_eq!(
+            config.long_dictation.threshold_seconds,
+            base.long_dictation.threshold_seconds
+        );
+        assert_eq!(config.model_path, base.model_path);
+        assert_eq!(config.fallback_model_path, None);
+        assert!(overlay_config(&base, &serde_json::json!({"language": 3})).is_err());
+    }
+
+    #[test]
+    fn clips_pair_audio_with_reference_transcripts() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.wav"), b"").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "second clip\n").unwrap();
+        std::fs::write(dir.path().join("a.flac"), b"").unwrap();
+        std::fs::write(dir.path().join("a.txt"), "first clip").unwrap();
+        std::fs::write(dir.path().join("orphan.wav"), b"").unwrap();
+        std::fs::write(dir.path().join("notes.md"), "ignored").unwrap();
+
+        let clips = find_clips(dir.path()).unwrap();
+        let names: Vec<_> = clips
+            .iter()
+            .map(|(path, reference)| {
+                (
+                    path.file_name().unwrap().to_str().unwrap().to_string(),
+                    reference.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("a.flac".to_string(), "first clip"),
+                ("b.wav".to_string(), "second clip")
+            ]
+        );
+        assert!(find_clips(tempfile::tempdir().unwrap().path()).is_err());
+    }
+}
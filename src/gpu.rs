@@ -1,23 +1,284 @@
 //! Shared GPU inventory helpers.
+//!
+//! Transcription asks for free VRAM on every recording and streaming chunk, so
+//! the inventory is sampled by a background thread and read from a cache
+//! instead of spawning `rocm-smi` or `nvidia-smi` on the hot path. The thread
+//! stops once nobody has read the inventory for a while; the next read gets the
+//! last snapshot at once and restarts the thread to refresh it. Only the first
+//! read in a process waits for a probe.
+//!
+//! Devices are keyed by compute ordinal, the index whisper receives as
+//! `gpu_device`, not by DRM card number.
 
 use std::collections::BTreeMap;
+use std::path::Path;
 use std::process::Command;
-use tracing::debug;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// How often the background monitor refreshes the inventory.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The monitor stops after this long without a reader.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct VramInfo {
-    index: usize,
-    total_bytes: u64,
-    used_bytes: u64,
+pub(crate) struct VramInfo {
+    /// CUDA/HIP device ordinal
+    pub index: usize,
+    pub total_bytes: u64,
+    pub used_bytes: u64,
 }
 
 impl VramInfo {
-    fn available_bytes(self) -> u64 {
+    pub(crate) fn available_bytes(self) -> u64 {
         self.total_bytes.saturating_sub(self.used_bytes)
     }
 }
 
-fn parse_rocm_smi_vram(output: &str, preferred_index: Option<usize>) -> Option<VramInfo> {
+/// Tool or interface the inventory was read from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum InventorySource {
+    #[default]
+    Unavailable,
+    AmdSysfs,
+    RocmSmi,
+    NvidiaSmi,
+}
+
+/// Snapshot of every GPU the monitor can see.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct GpuInventory {
+    pub source: InventorySource,
+    pub devices: Vec<VramInfo>,
+}
+
+impl GpuInventory {
+    /// `preferred`, or the largest device when no index is chosen.
+    pub(crate) fn device(&self, preferred_index: Option<usize>) -> Option<VramInfo> {
+        select_device(self.devices.iter().copied(), preferred_index)
+    }
+
+    /// Free VRAM on `preferred`, or on the largest device when no index is chosen.
+    pub(crate) fn available_bytes(&self, preferred_index: Option<usize>) -> Option<u64> {
+        self.device(preferred_index).map(VramInfo::available_bytes)
+    }
+}
+
+fn select_device(
+    devices: impl Iterator<Item = VramInfo>,
+    preferred_index: Option<usize>,
+) -> Option<VramInfo> {
+    let mut devices = devices;
+    match preferred_index {
+        Some(preferred) => devices.find(|device| device.index == preferred),
+        None => devices.max_by_key(|device| device.total_bytes),
+    }
+}
+
+#[derive(Default)]
+struct MonitorState {
+    /// Last snapshot; `None` until the first probe
+    inventory: Option<GpuInventory>,
+    last_read: Option<Instant>,
+    running: bool,
+}
+
+static MONITOR: OnceLock<Mutex<MonitorState>> = OnceLock::new();
+
+/// Latest cached inventory, keeping the background monitor running.
+///
+/// Only the first call probes synchronously; after the monitor went idle the
+/// previous snapshot is returned while the restarted monitor refreshes it.
+pub(crate) fn cached_inventory() -> GpuInventory {
+    let Ok(mut state) = MONITOR.get_or_init(Mutex::default).lock() else {
+        return GpuInventory::default();
+    };
+    state.last_read = Some(Instant::now());
+    let stale = state.inventory.is_some();
+    if !stale {
+        let inventory = probe_inventory();
+        info!(
+            "GPU inventory from {:?}: {} device(s); refreshing every {}s while in use",
+            inventory.source,
+            inventory.devices.len(),
+            REFRESH_INTERVAL.as_secs()
+        );
+        state.inventory = Some(inventory);
+    }
+    if !state.running {
+        match std::thread::Builder::new()
+            .name("gpu-monitor".to_string())
+            .spawn(move || monitor_loop(stale))
+        {
+            Ok(_) => state.running = true,
+            Err(err) => warn!("GPU monitor thread failed to start: {}", err),
+        }
+    }
+    state.inventory.clone().unwrap_or_default()
+}
+
+/// Refresh the inventory until it goes unread; `refresh_now` skips the first wait.
+fn monitor_loop(refresh_now: bool) {
+    let Some(monitor) = MONITOR.get() else {
+        return;
+    };
+    let mut refresh_now = refresh_now;
+    loop {
+        if !std::mem::take(&mut refresh_now) {
+            std::thread::sleep(REFRESH_INTERVAL);
+        }
+        {
+            let Ok(mut state) = monitor.lock() else {
+                return;
+            };
+            if is_idle(state.last_read, Instant::now()) {
+                debug!("GPU inventory unused; stopping the monitor until the next read");
+                state.running = false;
+                return;
+            }
+        }
+        // Probe outside the lock so readers never wait on rocm-smi or nvidia-smi.
+        let inventory = probe_inventory();
+        if let Ok(mut state) = monitor.lock() {
+            state.inventory = Some(inventory);
+        }
+    }
+}
+
+fn is_idle(last_read: Option<Instant>, now: Instant) -> bool {
+    last_read.is_none_or(|last_read| now.saturating_duration_since(last_read) >= IDLE_TIMEOUT)
+}
+
+/// Read the inventory once: selected ROCm device → AMD sysfs → rocm-smi → nvidia-smi.
+fn probe_inventory() -> GpuInventory {
+    // A configured ROCm device must win over physical sysfs enumeration.
+    let rocm_selected = rocm_visible_device_index().is_some();
+    if rocm_selected {
+        if let Some(devices) = run_rocm_smi() {
+            return GpuInventory {
+                source: InventorySource::RocmSmi,
+                devices,
+            };
+        }
+    }
+
+    let sysfs = read_amd_sysfs(Path::new("/sys/class/drm"));
+    if !sysfs.is_empty() {
+        return GpuInventory {
+            source: InventorySource::AmdSysfs,
+            devices: sysfs,
+        };
+    }
+
+    if !rocm_selected {
+        if let Some(devices) = run_rocm_smi() {
+            return GpuInventory {
+                source: InventorySource::RocmSmi,
+                devices,
+            };
+        }
+    }
+
+    if let Some(devices) = run_nvidia_smi() {
+        return GpuInventory {
+            source: InventorySource::NvidiaSmi,
+            devices,
+        };
+    }
+
+    GpuInventory::default()
+}
+
+fn run_rocm_smi() -> Option<Vec<VramInfo>> {
+    let output = Command::new("rocm-smi")
+        .args(["--showmeminfo", "vram"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let devices = parse_rocm_smi_devices(&String::from_utf8(output.stdout).ok()?);
+    (!devices.is_empty()).then_some(devices)
+}
+
+fn run_nvidia_smi() -> Option<Vec<VramInfo>> {
+    let output = Command::new("nvidia-smi")
+        .args([
+            "--query-gpu=index,memory.total,memory.used",
+            "--format=csv,noheader,nounits",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let devices = parse_nvidia_smi_devices(&String::from_utf8(output.stdout).ok()?);
+    (!devices.is_empty()).then_some(devices)
+}
+
+/// Parse `nvidia-smi --query-gpu=index,memory.total,memory.used` CSV (MiB, no header).
+fn parse_nvidia_smi_devices(output: &str) -> Vec<VramInfo> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(',').map(str::trim);
+            let index = fields.next()?.parse().ok()?;
+            let total_mib: u64 = fields.next()?.parse().ok()?;
+            let used_mib: u64 = fields.next()?.parse().ok()?;
+            Some(VramInfo {
+                index,
+                total_bytes: total_mib * 1024 * 1024,
+                used_bytes: used_mib * 1024 * 1024,
+            })
+        })
+        .collect()
+}
+
+/// Read `cardN/device/mem_info_vram_{total,used}` under a DRM class directory.
+///
+/// DRM numbering often starts at `card1` and includes display-only devices, so
+/// cards that report VRAM are numbered 0, 1, … in card order, which is how HIP
+/// enumerates them.
+fn read_amd_sysfs(drm_root: &Path) -> Vec<VramInfo> {
+    let Ok(entries) = std::fs::read_dir(drm_root) else {
+        return Vec::new();
+    };
+    let mut cards: Vec<(usize, u64, u64)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let card = name.strip_prefix("card")?;
+            if card.is_empty() || !card.chars().all(|character| character.is_ascii_digit()) {
+                return None;
+            }
+            let device = entry.path().join("device");
+            let read = |file: &str| {
+                std::fs::read_to_string(device.join(file))
+                    .ok()
+                    .and_then(|value| value.trim().parse::<u64>().ok())
+            };
+            Some((
+                card.parse().ok()?,
+                read("mem_info_vram_total")?,
+                read("mem_info_vram_used")?,
+            ))
+        })
+        .collect();
+    cards.sort_by_key(|(card, _, _)| *card);
+    cards
+        .into_iter()
+        .enumerate()
+        .map(|(index, (_, total_bytes, used_bytes))| VramInfo {
+            index,
+            total_bytes,
+            used_bytes,
+        })
+        .collect()
+}
+
+fn parse_rocm_smi_devices(output: &str) -> Vec<VramInfo> {
     let mut devices: BTreeMap<usize, (Option<u64>, Option<u64>)> = BTreeMap::new();
 
     for line in output.lines() {
@@ -46,21 +307,16 @@ fn parse_rocm_smi_vram(output: &str, preferred_index: Option<usize>) -> Option<V
         }
     }
 
-    let complete = devices.into_iter().filter_map(|(index, (total, used))| {
-        Some(VramInfo {
-            index,
-            total_bytes: total?,
-            used_bytes: used?,
+    devices
+        .into_iter()
+        .filter_map(|(index, (total, used))| {
+            Some(VramInfo {
+                index,
+                total_bytes: total?,
+                used_bytes: used?,
+            })
         })
-    });
-
-    if let Some(preferred) = preferred_index {
-        complete
-            .into_iter()
-            .find(|device| device.index == preferred)
-    } else {
-        complete.max_by_key(|device| device.total_bytes)
-    }
+        .collect()
 }
 
 pub fn rocm_visible_device_index() -> Option<usize> {
@@ -73,73 +329,14 @@ pub fn rocm_visible_device_index() -> Option<usize> {
         .ok()
 }
 
-pub fn detect_rocm_smi_available_bytes(preferred_index: Option<usize>) -> Option<u64> {
-    let output = Command::new("rocm-smi")
-        .args(["--showmeminfo", "vram"])
-        .output()
-        .ok()?;
-    if !output.status.success() {
-        return None;
-    }
-
-    let stdout = String::from_utf8(output.stdout).ok()?;
-    let info = parse_rocm_smi_vram(&stdout, preferred_index)?;
-    debug!(
-        "rocm-smi GPU {}: total={}, used={}, available={}",
-        info.index,
-        info.total_bytes,
-        info.used_bytes,
-        info.available_bytes()
-    );
-    Some(info.available_bytes())
-}
-
-pub fn detect_amd_sysfs_available_bytes() -> Option<u64> {
-    let entries = std::fs::read_dir("/sys/class/drm").ok()?;
-    let mut best: Option<(u64, u64, String)> = None;
-
-    for entry in entries.flatten() {
-        let name = entry.file_name().to_string_lossy().into_owned();
-        let Some(index) = name.strip_prefix("card") else {
-            continue;
-        };
-        if index.is_empty() || !index.chars().all(|character| character.is_ascii_digit()) {
-            continue;
-        }
-
-        let device = entry.path().join("device");
-        let total = std::fs::read_to_string(device.join("mem_info_vram_total"))
-            .ok()
-            .and_then(|value| value.trim().parse::<u64>().ok());
-        let used = std::fs::read_to_string(device.join("mem_info_vram_used"))
-            .ok()
-            .and_then(|value| value.trim().parse::<u64>().ok());
-        let (Some(total), Some(used)) = (total, used) else {
-            continue;
-        };
-
-        if best
-            .as_ref()
-            .map(|(best_total, _, _)| total > *best_total)
-            .unwrap_or(true)
-        {
-            best = Some((total, used, name));
-        }
-    }
-
-    let (total, used, name) = best?;
-    let available = total.saturating_sub(used);
-    debug!(
-        "AMD sysfs {}: total={}, used={}, available={}",
-        name, total, used, available
-    );
-    Some(available)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn parse_rocm_smi_vram(output: &str, preferred_index: Option<usize>) -> Option<VramInfo> {
+        select_device(parse_rocm_smi_devices(output).into_iter(), preferred_index)
+    }
+
     const MULTI_GPU: &str = r#"
 GPU[0]          : VRAM Total Memory (B): 536870912
 GPU[0]          : VRAM Total Used Memory (B): 1000
@@ -165,4 +362,102 @@ GPU[1]          : VRAM Total Used Memory (B): 2000
     fn rocm_parser_rejects_incomplete_inventory() {
         assert!(parse_rocm_smi_vram("GPU[0]: VRAM Total Memory (B): 1024", None).is_none());
     }
+
+    /// Captured from `nvidia-smi --query-gpu=index,memory.total,memory.used
+    /// --format=csv,noheader,nounits` on a two-GPU workstation.
+    const NVIDIA_TWO_GPU: &str = "0, 24564, 1033\n1, 8192, 7900\n";
+
+    #[test]
+    fn nvidia_parser_reads_every_device_in_mib() {
+        let devices = parse_nvidia_smi_devices(NVIDIA_TWO_GPU);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].total_bytes, 24_564 * 1024 * 1024);
+        assert_eq!(devices[1].available_bytes(), 292 * 1024 * 1024);
+        assert!(parse_nvidia_smi_devices("NVIDIA-SMI has failed").is_empty());
+    }
+
+    #[test]
+    fn inventory_prefers_selected_device_then_largest() {
+        let inventory = GpuInventory {
+            source: InventorySource::NvidiaSmi,
+            devices: parse_nvidia_smi_devices(NVIDIA_TWO_GPU),
+        };
+        assert_eq!(
+            inventory.available_bytes(None),
+            Some((24_564 - 1033) * 1024 * 1024)
+        );
+        assert_eq!(inventory.available_bytes(Some(1)), Some(292 * 1024 * 1024));
+        assert_eq!(inventory.available_bytes(Some(7)), None);
+        assert_eq!(GpuInventory::default().available_bytes(None), None);
+    }
+
+    #[test]
+    fn sysfs_reader_skips_connectors_and_incomplete_cards() {
+        let root = tempfile::tempdir().unwrap();
+        let card = |name: &str, total: Option<&str>, used: Option<&str>| {
+            let device = root.path().join(name).join("device");
+            std::fs::create_dir_all(&device).unwrap();
+            if let Some(total) = total {
+                std::fs::write(device.join("mem_info_vram_total"), total).unwrap();
+            }
+            if let Some(used) = used {
+                std::fs::write(device.join("mem_info_vram_used"), used).unwrap();
+            }
+        };
+        card("card1", Some("17163091968\n"), Some("1073741824\n"));
+        card("card0", Some("536870912\n"), None);
+        card("card1-DP-1", Some("1"), Some("0"));
+
+        let devices = read_amd_sysfs(root.path());
+        assert_eq!(
+            devices,
+            vec![VramInfo {
+                index: 0,
+                total_bytes: 17_163_091_968,
+                used_bytes: 1_073_741_824,
+            }]
+        );
+    }
+
+    #[test]
+    fn sysfs_devices_are_keyed_by_compute_ordinal() {
+        let root = tempfile::tempdir().unwrap();
+        for (name, total) in [("card1", "17163091968"), ("card2", "8589934592")] {
+            let device = root.path().join(name).join("device");
+            std::fs::create_dir_all(&device).unwrap();
+            std::fs::write(device.join("mem_info_vram_total"), total).unwrap();
+            std::fs::write(device.join("mem_info_vram_used"), "0").unwrap();
+        }
+
+        let inventory = GpuInventory {
+            source: InventorySource::AmdSysfs,
+            devices: read_amd_sysfs(root.path()),
+        };
+        assert_eq!(inventory.available_bytes(Some(0)), Some(17_163_091_968));
+        assert_eq!(inventory.available_bytes(Some(1)), Some(8_589_934_592));
+        assert_eq!(inventory.available_bytes(Some(2)), None);
+    }
+
+    #[test]
+    fn card1_only_sysfs_answers_device_zero() {
+        let root = tempfile::tempdir().unwrap();
+        let device = root.path().join("card1").join("device");
+        std::fs::create_dir_all(&device).unwrap();
+        std::fs::write(device.join("mem_info_vram_total"), "17163091968\n").unwrap();
+        std::fs::write(device.join("mem_info_vram_used"), "1073741824\n").unwrap();
+
+        let inventory = GpuInventory {
+            source: InventorySource::AmdSysfs,
+            devices: read_amd_sysfs(root.path()),
+        };
+        assert_eq!(inventory.available_bytes(Some(0)), Some(16_089_350_144));
+    }
+
+    #[test]
+    fn monitor_goes_idle_without_readers() {
+        let now = Instant::now();
+        assert!(is_idle(None, now));
+        assert!(!is_idle(Some(now), now));
+        assert!(is_idle(Some(now), now + IDLE_TIMEOUT));
+    }
 }
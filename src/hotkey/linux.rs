@@ -239,6 +239,20 @@ fn format_hotkey(config: &HotkeyConfig) -> String {
     }
 }
 
+/// The profile whose switch shortcut is pressed, if any
+fn profile_for_keys(pressed_keys: &HashSet<Key>, config: &Config) -> Option<String> {
+    config
+        .profiles
+        .iter()
+        .find(|(_, profile)| {
+            profile
+                .hotkey
+                .as_ref()
+                .is_some_and(|hotkey| is_hotkey_active(pressed_keys, hotkey))
+        })
+        .map(|(name, _)| name.clone())
+}
+
 /// Listen for configured hotkey combination using a single thread with select/poll
 pub fn listen_for_hotkey(
     event_tx: Sender<AppEvent>,
@@ -331,6 +345,16 @@ pub fn listen_for_hotkey(
                                     _ => {}
                                 }
 
+                                if value == 1 && !is_recording {
+                                    if let Some(profile) = profile_for_keys(&pressed_keys, &config)
+                                    {
+                                        info!("Switching to profile {:?} by hotkey", profile);
+                                        let _ =
+                                            event_tx.send(AppEvent::SwitchProfile(Some(profile)));
+                                        continue;
+                                    }
+                                }
+
                                 let combo_active = is_hotkey_active(&pressed_keys, &config.hotkey);
 
                                 if combo_active && !is_recording {
@@ -413,4 +437,33 @@ mod tests {
 
         assert!(!is_hotkey_active(&pressed, &config));
     }
+
+    #[test]
+    fn profile_shortcuts_pick_their_profile() {
+        let mut config = Config::default();
+        config.profiles.insert(
+            "email".to_string(),
+            crate::DictationProfile {
+                hotkey: Some(HotkeyConfig {
+                    ctrl: true,
+                    alt: true,
+                    shift: false,
+                    super_key: false,
+                    trigger_key: Some("f2".to_string()),
+                }),
+                ..Default::default()
+            },
+        );
+        config
+            .profiles
+            .insert("chat".to_string(), crate::DictationProfile::default());
+
+        let mut pressed = HashSet::from([Key::KEY_LEFTCTRL, Key::KEY_LEFTALT]);
+        assert_eq!(profile_for_keys(&pressed, &config), None);
+        pressed.insert(Key::KEY_F2);
+        assert_eq!(
+            profile_for_keys(&pressed, &config).as_deref(),
+            Some("email")
+        );
+    }
 }
@@ -180,6 +180,20 @@ fn format_hotkey(config: &HotkeyConfig) -> String {
     }
 }
 
+/// The profile whose switch shortcut is pressed, if any
+fn profile_for_keys(pressed_keys: &HashSet<Key>, config: &Config) -> Option<String> {
+    config
+        .profiles
+        .iter()
+        .find(|(_, profile)| {
+            profile
+                .hotkey
+                .as_ref()
+                .is_some_and(|hotkey| is_hotkey_active(pressed_keys, hotkey))
+        })
+        .map(|(name, _)| name.clone())
+}
+
 /// Listen for configured hotkey combination using rdev.
 ///
 /// This function blocks forever. It should be spawned in a dedicated thread.
@@ -261,14 +275,26 @@ pub fn listen_for_hotkey(
         }
 
         // Read config with try_lock; skip event if contended
-        let hotkey_cfg = match config_clone.try_lock() {
-            Ok(cfg) => cfg.hotkey.clone(),
+        let (hotkey_cfg, profile) = match config_clone.try_lock() {
+            Ok(cfg) => (
+                cfg.hotkey.clone(),
+                (is_press && !was_recording)
+                    .then(|| profile_for_keys(&pressed, &cfg))
+                    .flatten(),
+            ),
             Err(_) => {
                 warn!("Hotkey: config lock contention, skipping event");
                 return;
             }
         };
 
+        if let Some(profile) = profile {
+            drop(pressed);
+            info!("Switching to profile {:?} by hotkey", profile);
+            let _ = event_tx.send(AppEvent::SwitchProfile(Some(profile)));
+            return;
+        }
+
         // Drop pressed lock before sending events (which may block briefly)
         let combo_active = is_hotkey_active(&pressed, &hotkey_cfg);
         drop(pressed);
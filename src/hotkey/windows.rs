@@ -161,6 +161,20 @@ fn format_hotkey(config: &HotkeyConfig) -> String {
     }
 }
 
+/// The profile whose switch shortcut is pressed, if any
+fn profile_for_keys(pressed_keys: &HashSet<Key>, config: &Config) -> Option<String> {
+    config
+        .profiles
+        .iter()
+        .find(|(_, profile)| {
+            profile
+                .hotkey
+                .as_ref()
+                .is_some_and(|hotkey| is_hotkey_active(pressed_keys, hotkey))
+        })
+        .map(|(name, _)| name.clone())
+}
+
 /// Listen for the configured Windows hotkey until the process exits.
 pub fn listen_for_hotkey(
     event_tx: Sender<AppEvent>,
@@ -228,7 +242,14 @@ pub fn listen_for_hotkey(
                 return;
             };
             let hotkey_config = current_config.hotkey.clone();
+            let profile = (is_press && !was_recording)
+                .then(|| profile_for_keys(&pressed, &current_config))
+                .flatten();
             drop(current_config);
+            if let Some(profile) = profile {
+                let _ = event_tx.send(AppEvent::SwitchProfile(Some(profile)));
+                return;
+            }
             let combo_active = is_hotkey_active(&pressed, &hotkey_config);
             drop(pressed);
 
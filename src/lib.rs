@@ -1,31 +1,43 @@
 //! Reusable MorpheOS Voice dictation engine and compatibility CLI entry points.
 
 mod audio;
+mod discovery;
 pub mod engine;
+mod eval;
 mod feedback;
 mod gpu;
 mod hotkey;
 mod input;
+mod model_setup;
 mod models;
 mod persistence;
 pub mod personalisation;
+mod provision;
 mod punctuation;
 mod runtime;
+mod server;
 mod settings;
 mod setup;
 pub mod state;
 mod transcribe;
 mod tray;
+mod usage;
 
 use anyhow::Result;
 use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
-pub use engine::{EngineCommand, EngineEvent, EngineHandle, EngineOptions, EnginePhase};
+pub use discovery::{discover_servers, DiscoveredServer};
+pub use engine::{
+    EngineCommand, EngineEvent, EngineHandle, EngineModel, EngineOptions, EnginePhase, ModelFailure,
+};
 pub use runtime::{
     clear_remote_api_key, get_config_dir, get_data_dir, get_remote_api_key, get_socket_path,
-    save_config, set_remote_api_key, Config, HotkeyConfig, RemoteBackendConfig, StreamingConfig,
-    TranscriptionBackend, VadConfig,
+    import_remote_client_key, remove_remote_secret, save_config, set_remote_api_key,
+    store_remote_endpoint_token, Config, DictationProfile, GpuConfig, GpuPolicy, HotkeyConfig,
+    LongDictationConfig, ModelCacheConfig, ModelCatalogueConfig, RemoteBackendConfig,
+    RemoteBudgetConfig, RemoteDialect, RemoteEndpointConfig, RemoteUploadFormat, ServerConfig,
+    StreamingConfig, TranscriptionBackend, VadConfig,
 };
 pub(crate) use runtime::{format_hotkey, AppEvent, AppState, RecordCommand, StreamingAudioMessage};
 pub use state::{AppPhase, DeliveryOutcome};
@@ -41,8 +53,19 @@ pub const LEGACY_CLI_NAME: &str = "oswispa";
 /// Desktop shells should start [`EngineHandle`] directly instead of invoking
 /// this compatibility entry point.
 pub fn run_cli() -> Result<()> {
+    // `eval` and `setup` print JSON reports on stdout, so their logs go to stderr.
+    let logs_to_stderr = std::env::args_os()
+        .nth(1)
+        .is_some_and(|command| command == "eval" || command == "setup");
     if let Err(error) = tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env().add_directive("oswispa=info".parse()?))
+        .with_writer(move || -> Box<dyn std::io::Write> {
+            if logs_to_stderr {
+                Box::new(std::io::stderr())
+            } else {
+                Box::new(std::io::stdout())
+            }
+        })
         .try_init()
     {
         warn!("Logging subscriber was already initialised: {}", error);
@@ -57,6 +80,14 @@ pub fn run_cli() -> Result<()> {
         return runtime::run_platform_smoke_test();
     }
 
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    match arguments.first().map(String::as_str) {
+        Some("serve") => return server::run_serve(&arguments[1..]),
+        Some("eval") => return eval::run_eval(&arguments[1..]),
+        Some("setup") => std::process::exit(provision::run_setup(&arguments[1..])),
+        _ => {}
+    }
+
     EngineHandle::start(EngineOptions::default())?.wait()
 }
 
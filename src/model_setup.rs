@@ -0,0 +1,247 @@
+//! Model listing, download and activation through the engine API.
+//!
+//! An embedding shell has no terminal wizard, so when no usable local model is
+//! configured the engine waits in [`EnginePhase::NeedsModel`] and serves model
+//! commands until one is activated. The same commands work once the engine is
+//! running. Downloads run on their own thread and report progress as events;
+//! failures are reported by kind so no raw error text leaves the engine.
+
+use crate::engine::{EngineCommand, EngineEvent, EngineModel, EnginePhase, ModelFailure};
+use crate::models::{self, InsufficientSpace, ModelInfo};
+use crate::runtime::load_config;
+use crate::{save_config, setup, Config, TranscriptionBackend};
+use anyhow::Result;
+use crossbeam_channel::{select, unbounded, Receiver, Sender};
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use tracing::{debug, info, warn};
+
+fn find_model(id: &str) -> Option<&'static ModelInfo> {
+    models::catalogue()
+        .iter()
+        .find(|model| model.filename == id)
+}
+
+/// File name of the model the first-run wizard would suggest, probed once.
+fn recommended_model() -> &'static str {
+    static RECOMMENDED: OnceLock<&'static str> = OnceLock::new();
+    RECOMMENDED.get_or_init(|| setup::recommend_for_this_machine().1.filename)
+}
+
+/// Background model downloads started through the engine.
+#[derive(Clone)]
+pub(crate) struct ModelDownloads {
+    events: Sender<EngineEvent>,
+    in_progress: Arc<Mutex<HashSet<String>>>,
+    finished_tx: Sender<()>,
+    finished_rx: Receiver<()>,
+}
+
+impl ModelDownloads {
+    pub(crate) fn new(events: Sender<EngineEvent>) -> Self {
+        let (finished_tx, finished_rx) = unbounded();
+        Self {
+            events,
+            in_progress: Arc::default(),
+            finished_tx,
+            finished_rx,
+        }
+    }
+
+    fn is_downloading(&self, id: &str) -> bool {
+        self.in_progress.lock().unwrap().contains(id)
+    }
+
+    fn is_busy(&self) -> bool {
+        !self.in_progress.lock().unwrap().is_empty()
+    }
+
+    fn send(&self, event: EngineEvent) {
+        let _ = self.events.send(event);
+    }
+
+    fn start(&self, id: &str) {
+        let failed = |reason| EngineEvent::ModelInstallFailed {
+            model: id.to_string(),
+            reason,
+        };
+        let Some(model) = find_model(id) else {
+            self.send(failed(ModelFailure::UnknownModel));
+            return;
+        };
+        if !self.in_progress.lock().unwrap().insert(id.to_string()) {
+            self.send(failed(ModelFailure::AlreadyDownloading));
+            return;
+        }
+
+        let downloads = self.clone();
+        std::thread::spawn(move || {
+            let outcome = downloads.download(model);
+            // Clear the download first so the model can be activated as soon
+            // as the shell sees the outcome.
+            downloads.in_progress.lock().unwrap().remove(model.filename);
+            downloads.send(outcome);
+            let _ = downloads.finished_tx.send(());
+        });
+    }
+
+    /// Download `model`, reporting progress once per percent; returns the outcome event.
+    fn download(&self, model: &'static ModelInfo) -> EngineEvent {
+        let id = model.filename.to_string();
+        let last_percent = Cell::new(None);
+        let result = models::download_model_blocking(model, |downloaded, total| {
+            let percent = (total > 0).then(|| downloaded * 100 / total);
+            if percent.is_some() && last_percent.replace(percent) == percent {
+                return;
+            }
+            self.send(EngineEvent::ModelDownloadProgress {
+                model: id.clone(),
+                downloaded_bytes: downloaded,
+                total_bytes: total,
+            });
+        });
+        match result {
+            Ok(_) => EngineEvent::ModelInstalled { model: id },
+            Err(err) => {
+                warn!("Model download for {} failed: {:#}", model.name, err);
+                let reason = if err.chain().any(|cause| cause.is::<InsufficientSpace>()) {
+                    ModelFailure::InsufficientSpace
+                } else {
+                    ModelFailure::DownloadFailed
+                };
+                EngineEvent::ModelInstallFailed { model: id, reason }
+            }
+        }
+    }
+}
+
+fn list_models(config: &Config, downloads: &ModelDownloads) -> Vec<EngineModel> {
+    models::catalogue()
+        .iter()
+        .map(|model| EngineModel {
+            id: model.filename.to_string(),
+            name: model.name.to_string(),
+            description: model.description.to_string(),
+            size_mb: model.size_mb,
+            installed: models::is_model_installed(model),
+            active: models::get_model_path(model) == config.model_path,
+            downloading: downloads.is_downloading(model.filename),
+            recommended: model.filename == recommended_model(),
+        })
+        .collect()
+}
+
+/// Point the saved configuration at an installed catalogue model.
+fn activate(id: &str, config: &Config, downloads: &ModelDownloads) -> Result<Config, ModelFailure> {
+    let model = find_model(id).ok_or(ModelFailure::UnknownModel)?;
+    if downloads.is_downloading(id) {
+        return Err(ModelFailure::AlreadyDownloading);
+    }
+    let path = models::get_model_path(model);
+    if !path.exists() {
+        return Err(ModelFailure::NotInstalled);
+    }
+    if let Err(err) = models::validate_model_path(&path) {
+        warn!("Refusing to activate {}: {:#}", model.name, err);
+        return Err(ModelFailure::InvalidModel);
+    }
+
+    let mut updated = config.clone();
+    updated.model_path = path;
+    if let Err(err) = save_config(&updated) {
+        warn!("Failed to save the activated model: {:#}", err);
+        return Err(ModelFailure::ConfigNotSaved);
+    }
+    info!("Activated model {:?}", updated.model_path);
+    Ok(updated)
+}
+
+/// Serve a model command; returns the saved configuration when a model was activated.
+pub(crate) fn handle_model_command(
+    command: EngineCommand,
+    config: &Config,
+    downloads: &ModelDownloads,
+) -> Option<Config> {
+    match command {
+        EngineCommand::ListModels => {
+            downloads.send(EngineEvent::ModelsListed {
+                models: list_models(config, downloads),
+            });
+            None
+        }
+        EngineCommand::DownloadModel(id) => {
+            downloads.start(&id);
+            None
+        }
+        EngineCommand::ActivateModel(id) => match activate(&id, config, downloads) {
+            Ok(updated) => {
+                downloads.send(EngineEvent::ModelActivated { model: id });
+                Some(updated)
+            }
+            Err(reason) => {
+                downloads.send(EngineEvent::ModelActivationFailed { model: id, reason });
+                None
+            }
+        },
+        other => {
+            debug!("{:?} is not a model command", other);
+            None
+        }
+    }
+}
+
+/// Wait in [`EnginePhase::NeedsModel`] until a usable model is activated.
+///
+/// Returns `None` when the shell shuts the engine down first. A reload that
+/// finds a usable model, or a switch to the remote backend, also ends the wait.
+pub(crate) fn wait_for_model(
+    config: &RwLock<Config>,
+    command_rx: &Receiver<EngineCommand>,
+    downloads: &ModelDownloads,
+) -> Result<Option<PathBuf>> {
+    let publish = |phase| downloads.send(EngineEvent::PhaseChanged { phase });
+    publish(EnginePhase::NeedsModel);
+    info!("Waiting for the desktop shell to install a model");
+
+    let mut installing = false;
+    loop {
+        select! {
+            recv(command_rx) -> command => match command {
+                Err(_) | Ok(EngineCommand::Shutdown) => return Ok(None),
+                Ok(EngineCommand::Reload) => {
+                    let reloaded = load_config()?;
+                    let usable = reloaded.backend == TranscriptionBackend::Remote
+                        || models::validate_model_path(&reloaded.model_path).is_ok();
+                    let model_path = reloaded.model_path.clone();
+                    *config.write().unwrap() = reloaded;
+                    if usable {
+                        return Ok(Some(model_path));
+                    }
+                }
+                Ok(EngineCommand::Start | EngineCommand::Stop | EngineCommand::Cancel) => {
+                    debug!("Ignoring dictation command until a model is installed");
+                }
+                Ok(command) => {
+                    let current = config.read().unwrap().clone();
+                    if let Some(updated) = handle_model_command(command, &current, downloads) {
+                        let model_path = updated.model_path.clone();
+                        *config.write().unwrap() = updated;
+                        return Ok(Some(model_path));
+                    }
+                }
+            },
+            recv(downloads.finished_rx) -> _ => {}
+        }
+
+        if installing != downloads.is_busy() {
+            installing = !installing;
+            publish(if installing {
+                EnginePhase::InstallingModel
+            } else {
+                EnginePhase::NeedsModel
+            });
+        }
+    }
+}
@@ -0,0 +1,449 @@
+//! Measured model speed on this machine.
+//!
+//! Each installed model transcribes a short reference clip on the CPU and, in
+//! GPU builds, on the GPU. Load time and real-time factor are cached in
+//! `benchmarks.json`, keyed by the model file's size and modification time so
+//! a replaced model is measured again.
+//!
+//! The clip is synthetic until a recorded speech clip is bundled. Whisper
+//! decodes few tokens from it, so the factor mostly reflects the encoder and is
+//! a lower bound on the cost of real dictation.
+
+use super::FileStamp;
+use crate::{get_data_dir, persistence, Config, GpuPolicy};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::{info, warn};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+pub const BENCHMARK_SCHEMA_VERSION: u32 = 1;
+const SAMPLE_RATE: usize = 16_000;
+const REFERENCE_CLIP_SECONDS: usize = 8;
+/// Roughly the tokens of eight seconds of dictation; stops a synthetic clip from looping.
+const REFERENCE_MAX_TOKENS: i32 = 48;
+/// Real-time factor at or below which dictation still feels immediate.
+pub const INTERACTIVE_RTF: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BenchmarkDevice {
+    Cpu,
+    Gpu,
+}
+
+impl std::fmt::Display for BenchmarkDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BenchmarkDevice::Cpu => write!(f, "CPU"),
+            BenchmarkDevice::Gpu => write!(f, "GPU"),
+        }
+    }
+}
+
+/// One timed run of the reference clip.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    /// Time to load the model and allocate a state
+    pub load_ms: u64,
+    /// Transcription time divided by clip length; below 1.0 is faster than real time
+    pub real_time_factor: f64,
+}
+
+/// Cached CPU and GPU measurements for one model file.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ModelMeasurements {
+    pub cpu: Option<Measurement>,
+    pub gpu: Option<Measurement>,
+}
+
+impl ModelMeasurements {
+    /// Fastest measured device.
+    pub fn best(&self) -> Option<(BenchmarkDevice, Measurement)> {
+        let cpu = self.cpu.map(|m| (BenchmarkDevice::Cpu, m));
+        let gpu = self.gpu.map(|m| (BenchmarkDevice::Gpu, m));
+        match (cpu, gpu) {
+            (Some(cpu), Some(gpu)) if gpu.1.real_time_factor < cpu.1.real_time_factor => Some(gpu),
+            (Some(cpu), _) => Some(cpu),
+            (None, gpu) => gpu,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CachedRun {
+    model_path: PathBuf,
+    #[serde(flatten)]
+    stamp: FileStamp,
+    device: BenchmarkDevice,
+    #[serde(flatten)]
+    measurement: Measurement,
+    measured_at: DateTime<Utc>,
+}
+
+/// Measurements as persisted in `benchmarks.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkCache {
+    schema_version: u32,
+    #[serde(default)]
+    runs: Vec<CachedRun>,
+}
+
+impl Default for BenchmarkCache {
+    fn default() -> Self {
+        Self {
+            schema_version: BENCHMARK_SCHEMA_VERSION,
+            runs: Vec::new(),
+        }
+    }
+}
+
+impl BenchmarkCache {
+    fn lookup(
+        &self,
+        path: &Path,
+        stamp: FileStamp,
+        device: BenchmarkDevice,
+    ) -> Option<Measurement> {
+        self.runs
+            .iter()
+            .find(|run| run.model_path == path && run.stamp == stamp && run.device == device)
+            .map(|run| run.measurement)
+    }
+
+    fn measurements(&self, path: &Path, stamp: FileStamp) -> ModelMeasurements {
+        ModelMeasurements {
+            cpu: self.lookup(path, stamp, BenchmarkDevice::Cpu),
+            gpu: self.lookup(path, stamp, BenchmarkDevice::Gpu),
+        }
+    }
+
+    fn insert(&mut self, run: CachedRun) {
+        self.runs
+            .retain(|known| !(known.model_path == run.model_path && known.device == run.device));
+        self.runs.push(run);
+    }
+
+    /// Drop runs for model files that no longer exist.
+    fn prune_missing(&mut self) {
+        self.runs.retain(|run| run.model_path.is_file());
+    }
+}
+
+pub fn benchmarks_path() -> PathBuf {
+    get_data_dir().join("benchmarks.json")
+}
+
+pub fn load_benchmarks() -> Result<BenchmarkCache> {
+    let path = benchmarks_path();
+    if path.exists() {
+        persistence::read_json_private(&path)
+            .with_context(|| format!("Benchmark cache is invalid: {:?}", path))
+    } else {
+        Ok(BenchmarkCache::default())
+    }
+}
+
+/// Cached measurements for `path`; empty when it was never benchmarked or has changed since.
+pub fn measured(path: &Path) -> ModelMeasurements {
+    let (Ok(cache), Ok(stamp)) = (load_benchmarks(), FileStamp::of(path)) else {
+        return ModelMeasurements::default();
+    };
+    cache.measurements(path, stamp)
+}
+
+/// Whether this binary was built with a GPU backend.
+pub fn gpu_build() -> bool {
+    cfg!(any(
+        feature = "gpu-cuda",
+        feature = "gpu-hipblas",
+        feature = "gpu-metal"
+    ))
+}
+
+/// Benchmark one model on the CPU and, when the build and GPU policy allow it, the GPU.
+///
+/// A GPU failure is logged and leaves only the CPU result.
+pub fn benchmark_model(path: &Path, config: &Config) -> Result<ModelMeasurements> {
+    super::verify_model_file(path)?;
+    let stamp = FileStamp::of(path)?;
+    let mut results = ModelMeasurements {
+        cpu: Some(measure(path, BenchmarkDevice::Cpu, config)?),
+        gpu: None,
+    };
+    if gpu_build() && config.gpu.policy != GpuPolicy::Never {
+        match measure(path, BenchmarkDevice::Gpu, config) {
+            Ok(measurement) => results.gpu = Some(measurement),
+            Err(err) => warn!("GPU benchmark failed for {:?}: {}", path, err),
+        }
+    }
+
+    let mut cache = load_benchmarks().unwrap_or_else(|err| {
+        warn!("Discarding unreadable benchmark cache: {}", err);
+        BenchmarkCache::default()
+    });
+    cache.prune_missing();
+    let measured_at = Utc::now();
+    for (device, measurement) in [
+        (BenchmarkDevice::Cpu, results.cpu),
+        (BenchmarkDevice::Gpu, results.gpu),
+    ] {
+        if let Some(measurement) = measurement {
+            cache.insert(CachedRun {
+                model_path: path.to_path_buf(),
+                stamp,
+                device,
+                measurement,
+                measured_at,
+            });
+        }
+    }
+    persistence::write_json_private(&benchmarks_path(), &cache)?;
+    Ok(results)
+}
+
+/// Benchmark every installed model, calling `progress` before each one.
+pub fn benchmark_installed_models(
+    config: &Config,
+    mut progress: impl FnMut(&Path),
+) -> Vec<(PathBuf, Result<ModelMeasurements>)> {
+    super::installed_model_paths()
+        .into_iter()
+        .map(|path| {
+            progress(&path);
+            let result = benchmark_model(&path, config);
+            (path, result)
+        })
+        .collect()
+}
+
+fn measure(path: &Path, device: BenchmarkDevice, config: &Config) -> Result<Measurement> {
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("Model path is not valid UTF-8: {:?}", path))?;
+    let clip = reference_clip();
+
+    let started = Instant::now();
+    let mut ctx_params = WhisperContextParameters::default();
+    ctx_params.use_gpu(device == BenchmarkDevice::Gpu);
+    if device == BenchmarkDevice::Gpu {
+        if let Some(index) = config.gpu.device_index {
+            ctx_params.gpu_device(index as i32);
+        }
+    }
+    let ctx = WhisperContext::new_with_params(path_str, ctx_params)?;
+    let mut state = ctx.create_state()?;
+    let load = started.elapsed();
+
+    // The app reuses states, so time the second pass after one-off graph allocation.
+    state.full(reference_params(), &clip)?;
+    let started = Instant::now();
+    state.full(reference_params(), &clip)?;
+    let pass = started.elapsed();
+
+    let measurement = Measurement {
+        load_ms: load.as_millis() as u64,
+        real_time_factor: pass.as_secs_f64() / REFERENCE_CLIP_SECONDS as f64,
+    };
+    info!(
+        "Benchmarked {:?} on {}: load {} ms, {:.2}x real time",
+        path, device, measurement.load_ms, measurement.real_time_factor
+    );
+    Ok(measurement)
+}
+
+fn reference_params() -> FullParams<'static, 'static> {
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_n_threads(num_cpus::get() as i32);
+    params.set_language(Some("en"));
+    params.set_no_context(true);
+    params.set_max_tokens(REFERENCE_MAX_TOKENS);
+    params.set_print_special(false);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_timestamps(false);
+    params
+}
+
+/// Eight seconds of a voiced, syllable-paced signal with short pauses.
+///
+/// A stand-in for recorded speech: encoder cost depends only on length, but
+/// Whisper decodes few or no tokens from this signal, so decoding is
+/// under-represented and measured factors are optimistic.
+pub fn reference_clip() -> Vec<f32> {
+    (0..REFERENCE_CLIP_SECONDS * SAMPLE_RATE)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            // Two-second phrases separated by a quarter-second pause
+            if t % 2.0 > 1.75 {
+                return 0.0;
+            }
+            let pitch = 120.0 + 30.0 * (std::f32::consts::TAU * 0.5 * t).sin();
+            let voice: f32 = (1..=8)
+                .map(|harmonic| {
+                    let h = harmonic as f32;
+                    (std::f32::consts::TAU * pitch * h * t).sin() / h
+                })
+                .sum();
+            let syllables = 0.5 + 0.5 * (std::f32::consts::TAU * 4.0 * t).sin();
+            0.15 * voice * syllables
+        })
+        .collect()
+}
+
+/// Speed tier from a measured real-time factor.
+pub fn speed_tier(real_time_factor: f64) -> &'static str {
+    if real_time_factor <= 0.1 {
+        "Very Fast"
+    } else if real_time_factor <= 0.25 {
+        "Fast"
+    } else if real_time_factor <= INTERACTIVE_RTF {
+        "Balanced"
+    } else if real_time_factor <= 1.0 {
+        "Moderate"
+    } else {
+        "Slow"
+    }
+}
+
+/// Index of the model to recommend from measured candidates, given as (size, measurements).
+///
+/// The largest model that stays interactive wins; larger Whisper models are
+/// the more accurate ones. When none is interactive, the fastest measured one
+/// is suggested.
+pub fn recommend_measured(candidates: &[(u64, ModelMeasurements)]) -> Option<usize> {
+    let rtf = |index: usize| candidates[index].1.best().map(|(_, m)| m.real_time_factor);
+    let measured = (0..candidates.len()).filter(|&index| rtf(index).is_some());
+    measured
+        .clone()
+        .filter(|&index| rtf(index).unwrap_or(f64::MAX) <= INTERACTIVE_RTF)
+        .max_by_key(|&index| candidates[index].0)
+        .or_else(|| {
+            measured.min_by(|&left, &right| {
+                rtf(left)
+                    .unwrap_or(f64::MAX)
+                    .total_cmp(&rtf(right).unwrap_or(f64::MAX))
+            })
+        })
+}
+
+/// Short description of a model's measurements, e.g. for the Models tab.
+pub fn describe_measurements(measurements: &ModelMeasurements) -> Option<String> {
+    let parts: Vec<String> = [
+        (BenchmarkDevice::Cpu, measurements.cpu),
+        (BenchmarkDevice::Gpu, measurements.gpu),
+    ]
+    .into_iter()
+    .filter_map(|(device, measurement)| {
+        measurement.map(|m| {
+            format!(
+                "{} {:.2}x real time, loads in {:.1}s",
+                device,
+                m.real_time_factor,
+                m.load_ms as f64 / 1000.0
+            )
+        })
+    })
+    .collect();
+    (!parts.is_empty()).then(|| parts.join(" • "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(real_time_factor: f64) -> Measurement {
+        Measurement {
+            load_ms: 400,
+            real_time_factor,
+        }
+    }
+
+    fn cpu_only(real_time_factor: f64) -> ModelMeasurements {
+        ModelMeasurements {
+            cpu: Some(measurement(real_time_factor)),
+            gpu: None,
+        }
+    }
+
+    #[test]
+    fn cached_runs_are_invalidated_when_the_model_file_changes() {
+        let path = Path::new("/models/ggml-base.en.bin");
+        let stamp = FileStamp {
+            size_bytes: 147_951_465,
+            modified_secs: 1_700_000_000,
+        };
+        let mut cache = BenchmarkCache::default();
+        cache.insert(CachedRun {
+            model_path: path.to_path_buf(),
+            stamp,
+            device: BenchmarkDevice::Cpu,
+            measurement: measurement(0.3),
+            measured_at: Utc::now(),
+        });
+        cache.insert(CachedRun {
+            model_path: path.to_path_buf(),
+            stamp,
+            device: BenchmarkDevice::Cpu,
+            measurement: measurement(0.2),
+            measured_at: Utc::now(),
+        });
+
+        assert_eq!(cache.runs.len(), 1);
+        assert_eq!(cache.measurements(path, stamp), cpu_only(0.2));
+        let replaced = FileStamp {
+            modified_secs: 1_700_000_100,
+            ..stamp
+        };
+        assert_eq!(
+            cache.measurements(path, replaced),
+            ModelMeasurements::default()
+        );
+
+        let json = serde_json::to_string(&cache).unwrap();
+        assert!(json.contains("\"real_time_factor\":0.2"));
+        assert_eq!(
+            serde_json::from_str::<BenchmarkCache>(&json).unwrap(),
+            cache
+        );
+    }
+
+    #[test]
+    fn recommendation_prefers_the_largest_interactive_model() {
+        let gpu_fast = ModelMeasurements {
+            cpu: Some(measurement(1.8)),
+            gpu: Some(measurement(0.2)),
+        };
+        assert_eq!(gpu_fast.best().unwrap().0, BenchmarkDevice::Gpu);
+
+        let candidates = [
+            (75, cpu_only(0.05)),
+            (142, cpu_only(0.12)),
+            (1500, cpu_only(0.9)),
+            (3000, gpu_fast),
+            (466, ModelMeasurements::default()),
+        ];
+        assert_eq!(recommend_measured(&candidates), Some(3));
+        assert_eq!(recommend_measured(&candidates[..3]), Some(1));
+
+        let all_slow = [(466, cpu_only(1.4)), (1500, cpu_only(3.0))];
+        assert_eq!(recommend_measured(&all_slow), Some(0));
+        assert_eq!(
+            recommend_measured(&[(75, ModelMeasurements::default())]),
+            None
+        );
+    }
+
+    #[test]
+    fn reference_clip_has_speech_and_pauses() {
+        let clip = reference_clip();
+        assert_eq!(clip.len(), REFERENCE_CLIP_SECONDS * SAMPLE_RATE);
+        assert!(clip.iter().all(|sample| sample.abs() <= 1.0));
+        let pause = &clip[(1.8 * SAMPLE_RATE as f32) as usize..(1.9 * SAMPLE_RATE as f32) as usize];
+        assert!(pause.iter().all(|sample| *sample == 0.0));
+        assert!(clip[..SAMPLE_RATE].iter().any(|sample| sample.abs() > 0.1));
+        assert_eq!(speed_tier(0.3), "Balanced");
+    }
+}
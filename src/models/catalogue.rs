@@ -0,0 +1,394 @@
+//! Configurable model catalogue, download mirror and proxy.
+//!
+//! The compiled [`AVAILABLE_MODELS`] list is the default. A JSON manifest from
+//! a local file or URL can replace it for air-gapped and corporate setups:
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "models": [
+//!     {
+//!       "name": "Base (English)",
+//!       "filename": "ggml-base.en.bin",
+//!       "size_mb": 142,
+//!       "url": "https://models.example/whisper/ggml-base.en.bin",
+//!       "description": "Approved for internal use",
+//!       "sha256": "<64 hex digits>"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Every entry must carry a SHA-256, which downloads are checked against. A
+//! manifest fetched from a URL must itself match the configured
+//! `manifest_sha256`; the last good copy is kept in the data directory so the
+//! catalogue survives going offline.
+
+use super::{is_supported_model_path, validate_model_filename, ModelInfo, AVAILABLE_MODELS};
+use crate::{get_data_dir, persistence, ModelCatalogueConfig};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub const MANIFEST_SCHEMA_VERSION: u32 = 1;
+const MANIFEST_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    schema_version: u32,
+    models: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestEntry {
+    name: String,
+    filename: String,
+    size_mb: u32,
+    /// May be omitted when a mirror serves the files
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    description: String,
+    sha256: String,
+}
+
+struct ActiveCatalogue {
+    settings: ModelCatalogueConfig,
+    /// Manifest source and pin the current `models` were loaded from
+    loaded_from: Option<(String, Option<String>)>,
+    models: &'static [ModelInfo],
+}
+
+static ACTIVE: OnceLock<RwLock<ActiveCatalogue>> = OnceLock::new();
+
+fn active() -> &'static RwLock<ActiveCatalogue> {
+    ACTIVE.get_or_init(|| {
+        RwLock::new(ActiveCatalogue {
+            settings: ModelCatalogueConfig::default(),
+            loaded_from: None,
+            models: AVAILABLE_MODELS,
+        })
+    })
+}
+
+/// Models offered for download: the configured manifest, or the built-in list.
+pub fn catalogue() -> &'static [ModelInfo] {
+    active()
+        .read()
+        .map(|active| active.models)
+        .unwrap_or(AVAILABLE_MODELS)
+}
+
+/// Apply catalogue, mirror and proxy settings; returns the number of models offered.
+///
+/// On error the mirror and proxy still apply but the built-in list stays active.
+/// An unchanged manifest setting is not loaded again.
+pub fn configure_catalogue(settings: &ModelCatalogueConfig) -> Result<usize> {
+    let mut active = active()
+        .write()
+        .map_err(|_| anyhow::anyhow!("Model catalogue lock poisoned"))?;
+    active.settings = settings.clone();
+
+    let source = settings.manifest.as_deref().map(str::trim);
+    let Some(source) = source.filter(|source| !source.is_empty()) else {
+        active.loaded_from = None;
+        active.models = AVAILABLE_MODELS;
+        return Ok(AVAILABLE_MODELS.len());
+    };
+    let key = (source.to_string(), settings.manifest_sha256.clone());
+    if active.loaded_from.as_ref() == Some(&key) {
+        return Ok(active.models.len());
+    }
+
+    match load_manifest(source, settings) {
+        Ok(models) => {
+            info!("Loaded model catalogue from {}", source);
+            // Loaded once per manifest setting, so leaking keeps `ModelInfo` borrow-free.
+            active.models = Box::leak(models.into_boxed_slice());
+            active.loaded_from = Some(key);
+            Ok(active.models.len())
+        }
+        Err(err) => {
+            active.models = AVAILABLE_MODELS;
+            active.loaded_from = None;
+            Err(err).with_context(|| format!("Model catalogue {} could not be used", source))
+        }
+    }
+}
+
+/// URL to download `model` from, honouring the configured mirror.
+pub(super) fn download_url(model: &ModelInfo) -> Result<String> {
+    let mirror = active()
+        .read()
+        .ok()
+        .and_then(|active| active.settings.mirror.clone());
+    resolve_url(model, mirror.as_deref())
+}
+
+/// A mirror copy is only fetched when the catalogue lists a digest to check it against.
+fn resolve_url(model: &ModelInfo, mirror: Option<&str>) -> Result<String> {
+    match mirror.map(str::trim).filter(|mirror| !mirror.is_empty()) {
+        Some(_) if model.sha256.is_none() => anyhow::bail!(
+            "{} has no SHA-256 to check the mirror's copy against; list it with its digest in a model catalogue manifest",
+            model.filename
+        ),
+        Some(mirror) => Ok(format!(
+            "{}/{}",
+            mirror.trim_end_matches('/'),
+            model.filename
+        )),
+        None if !model.url.is_empty() => Ok(model.url.to_string()),
+        None => anyhow::bail!(
+            "{} has no download URL; configure a model mirror",
+            model.filename
+        ),
+    }
+}
+
+/// HTTP client for catalogue and model downloads, using the configured proxy.
+pub(super) fn http_client(timeout: Option<Duration>) -> Result<reqwest::blocking::Client> {
+    let proxy = active()
+        .read()
+        .ok()
+        .and_then(|active| active.settings.proxy.clone());
+    build_client(proxy.as_deref(), timeout)
+}
+
+fn build_client(
+    proxy: Option<&str>,
+    timeout: Option<Duration>,
+) -> Result<reqwest::blocking::Client> {
+    let mut builder = reqwest::blocking::Client::builder().timeout(timeout);
+    if let Some(proxy) = proxy.map(str::trim).filter(|proxy| !proxy.is_empty()) {
+        builder = builder.proxy(
+            reqwest::Proxy::all(proxy)
+                .with_context(|| format!("Invalid download proxy {:?}", proxy))?,
+        );
+    }
+    Ok(builder.build()?)
+}
+
+fn is_url(source: &str) -> bool {
+    source.starts_with("https://") || source.starts_with("http://")
+}
+
+fn cache_path() -> PathBuf {
+    get_data_dir().join("model_catalogue.json")
+}
+
+fn load_manifest(source: &str, settings: &ModelCatalogueConfig) -> Result<Vec<ModelInfo>> {
+    let pin = settings.manifest_sha256.as_deref();
+    let bytes = if is_url(source) {
+        let pin = pin.context("A catalogue manifest fetched from a URL needs manifest_sha256")?;
+        fetch_manifest(source, settings.proxy.as_deref(), pin).or_else(|err| {
+            warn!(
+                "Could not fetch model catalogue; trying the last good copy: {:#}",
+                err
+            );
+            let cached = std::fs::read(cache_path()).context("No cached catalogue available")?;
+            check_pin(&cached, pin)?;
+            Ok::<_, anyhow::Error>(cached)
+        })?
+    } else {
+        let bytes = std::fs::read(Path::new(source))
+            .with_context(|| format!("Failed to read catalogue {:?}", source))?;
+        if let Some(pin) = pin {
+            check_pin(&bytes, pin)?;
+        }
+        bytes
+    };
+    parse_manifest(&bytes, settings.mirror.is_some())
+}
+
+fn fetch_manifest(url: &str, proxy: Option<&str>, pin: &str) -> Result<Vec<u8>> {
+    let bytes = build_client(proxy, Some(MANIFEST_FETCH_TIMEOUT))?
+        .get(url)
+        .send()?
+        .error_for_status()?
+        .bytes()?
+        .to_vec();
+    check_pin(&bytes, pin)?;
+    if let Err(err) = persistence::write_private(&cache_path(), &bytes) {
+        warn!("Failed to cache model catalogue: {}", err);
+    }
+    Ok(bytes)
+}
+
+fn check_pin(bytes: &[u8], pin: &str) -> Result<()> {
+    let actual = super::integrity::sha256_bytes(bytes);
+    if !actual.eq_ignore_ascii_case(pin.trim()) {
+        anyhow::bail!(
+            "Catalogue SHA-256 is {}, but manifest_sha256 expects {}",
+            actual,
+            pin.trim()
+        );
+    }
+    Ok(())
+}
+
+fn is_sha256(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn parse_manifest(bytes: &[u8], has_mirror: bool) -> Result<Vec<ModelInfo>> {
+    let manifest: Manifest =
+        serde_json::from_slice(bytes).context("Model catalogue is not valid JSON")?;
+    if manifest.schema_version != MANIFEST_SCHEMA_VERSION {
+        anyhow::bail!(
+            "Unsupported model catalogue schema version {}",
+            manifest.schema_version
+        );
+    }
+    if manifest.models.is_empty() {
+        anyhow::bail!("Model catalogue lists no models");
+    }
+
+    let leak = |value: String| -> &'static str { Box::leak(value.into_boxed_str()) };
+    let mut seen = HashSet::new();
+    let mut models = Vec::with_capacity(manifest.models.len());
+    for entry in manifest.models {
+        let filename = entry.filename.trim().to_string();
+        if !seen.insert(filename.clone()) {
+            anyhow::bail!("Model catalogue lists {} twice", filename);
+        }
+        if !is_supported_model_path(Path::new(&filename)) {
+            anyhow::bail!("Catalogue model {} is not a .bin or .gguf file", filename);
+        }
+        if entry.size_mb == 0 {
+            anyhow::bail!("Catalogue model {} has no size", filename);
+        }
+        if !is_sha256(entry.sha256.trim()) {
+            anyhow::bail!("Catalogue model {} needs a 64-digit SHA-256", filename);
+        }
+        let url = entry
+            .url
+            .map(|url| url.trim().to_string())
+            .unwrap_or_default();
+        match (url.is_empty(), has_mirror) {
+            (true, false) => {
+                anyhow::bail!(
+                    "Catalogue model {} has no URL and no mirror is set",
+                    filename
+                )
+            }
+            (false, _) if !is_url(&url) => {
+                anyhow::bail!(
+                    "Catalogue model {} has an unsupported URL {}",
+                    filename,
+                    url
+                )
+            }
+            _ => {}
+        }
+
+        let model = ModelInfo {
+            name: leak(entry.name),
+            filename: leak(filename),
+            size_mb: entry.size_mb,
+            url: leak(url),
+            description: leak(entry.description),
+            sha256: Some(leak(entry.sha256.trim().to_ascii_lowercase())),
+        };
+        validate_model_filename(&model)?;
+        models.push(model);
+    }
+    Ok(models)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(models: serde_json::Value) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "schema_version": MANIFEST_SCHEMA_VERSION,
+            "models": models,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn manifest_entries_become_catalogue_models() {
+        let digest = "AB".repeat(32);
+        let bytes = manifest(serde_json::json!([
+            {
+                "name": "Base (mirror)",
+                "filename": "ggml-base.en.bin",
+                "size_mb": 142,
+                "url": "https://models.example/ggml-base.en.bin",
+                "description": "Approved",
+                "sha256": digest,
+            },
+            {
+                "name": "Small",
+                "filename": "ggml-small.en.bin",
+                "size_mb": 466,
+                "sha256": "cd".repeat(32),
+            }
+        ]));
+
+        let models = parse_manifest(&bytes, true).unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].filename, "ggml-base.en.bin");
+        assert_eq!(models[0].sha256, Some("ab".repeat(32).as_str()));
+        assert_eq!(models[1].url, "");
+
+        // Without a mirror every entry needs its own URL.
+        assert!(parse_manifest(&bytes, false).is_err());
+    }
+
+    #[test]
+    fn unsafe_or_incomplete_entries_are_rejected() {
+        let entry = |filename: &str, sha256: &str| {
+            serde_json::json!({
+                "name": "Model",
+                "filename": filename,
+                "size_mb": 10,
+                "url": "https://models.example/model.bin",
+                "sha256": sha256,
+            })
+        };
+        let good = "ab".repeat(32);
+
+        for models in [
+            serde_json::json!([entry("../model.bin", &good)]),
+            serde_json::json!([entry("model.exe", &good)]),
+            serde_json::json!([entry("model.bin", "not-a-digest")]),
+            serde_json::json!([entry("model.bin", &good), entry("model.bin", &good)]),
+            serde_json::json!([]),
+        ] {
+            assert!(parse_manifest(&manifest(models), false).is_err());
+        }
+
+        let future = br#"{"schema_version": 99, "models": []}"#;
+        assert!(parse_manifest(future, false).is_err());
+    }
+
+    #[test]
+    fn mirrors_replace_model_urls_and_pins_guard_manifests() {
+        let model = &AVAILABLE_MODELS[0];
+        assert_eq!(resolve_url(model, None).unwrap(), model.url);
+        let unlisted = ModelInfo {
+            sha256: None,
+            ..model.clone()
+        };
+        assert!(resolve_url(&unlisted, Some("http://mirror.local/whisper/")).is_err());
+        let listed = ModelInfo {
+            sha256: Some("ab"),
+            ..model.clone()
+        };
+        assert_eq!(
+            resolve_url(&listed, Some("http://mirror.local/whisper/")).unwrap(),
+            format!("http://mirror.local/whisper/{}", model.filename)
+        );
+
+        let abc_digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert!(check_pin(b"abc", &abc_digest.to_uppercase()).is_ok());
+        assert!(check_pin(b"abd", abc_digest).is_err());
+        assert!(build_client(Some("not a proxy url"), None).is_err());
+    }
+}
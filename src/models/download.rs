@@ -0,0 +1,548 @@
+//! Resumable model downloads.
+//!
+//! A download is written to `<model>.downloading` with a `.downloading.json`
+//! sidecar holding the URL, total size and the server's validator (ETag or
+//! Last-Modified). An interrupted transfer keeps both, and the next attempt,
+//! in the same call or a later one, asks for the remainder with `Range` and
+//! `If-Range` so a changed file on the server restarts from zero instead of
+//! being spliced. The finished file must reach the announced size and pass
+//! the model checks and SHA-256 before it replaces the installed model.
+
+use super::{install_validated_model, integrity, space, validate_downloaded_model, ModelInfo};
+use anyhow::{Context, Result};
+use reqwest::header::{CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Attempts per download call before the partial file is left for next time.
+const MAX_ATTEMPTS: u32 = 5;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// What is known about a partially downloaded file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct PartialMeta {
+    url: String,
+    #[serde(default)]
+    total_bytes: Option<u64>,
+    #[serde(default)]
+    etag: Option<String>,
+    #[serde(default)]
+    last_modified: Option<String>,
+}
+
+impl PartialMeta {
+    /// Value for `If-Range`; a strong ETag is preferred over a date.
+    fn validator(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+struct Paths {
+    partial: PathBuf,
+    meta: PathBuf,
+}
+
+impl Paths {
+    fn for_destination(dest: &Path) -> Self {
+        let name = dest.file_name().unwrap_or_default().to_string_lossy();
+        Self {
+            partial: dest.with_file_name(format!("{}.downloading", name)),
+            meta: dest.with_file_name(format!("{}.downloading.json", name)),
+        }
+    }
+
+    fn discard(&self) {
+        let _ = std::fs::remove_file(&self.partial);
+        let _ = std::fs::remove_file(&self.meta);
+    }
+
+    /// Bytes already on disk that can be resumed for `url`.
+    fn resumable(&self, url: &str) -> Option<(u64, PartialMeta)> {
+        let meta: PartialMeta = std::fs::read(&self.meta)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())?;
+        let offset = std::fs::metadata(&self.partial).ok()?.len();
+        (meta.url == url && meta.validator().is_some() && offset > 0).then_some((offset, meta))
+    }
+
+    fn save_meta(&self, meta: &PartialMeta) -> Result<()> {
+        std::fs::write(&self.meta, serde_json::to_vec_pretty(meta)?)?;
+        Ok(())
+    }
+}
+
+/// Retry behaviour, shortened in tests.
+pub(super) struct RetryPolicy {
+    pub attempts: u32,
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: MAX_ATTEMPTS,
+            delay: RETRY_DELAY,
+        }
+    }
+}
+
+/// Outcome of one HTTP attempt.
+enum Attempt {
+    Complete,
+    /// The connection ended early; what arrived is kept for the next attempt
+    Interrupted(anyhow::Error),
+}
+
+/// Download `url` into `dest_dir` for `model`, resuming any earlier partial file.
+///
+/// Returns the installed path and its SHA-256.
+pub(super) fn download_resumable<F>(
+    model: &ModelInfo,
+    client: &reqwest::blocking::Client,
+    url: &str,
+    dest_dir: &Path,
+    retry: &RetryPolicy,
+    progress_callback: F,
+) -> Result<(PathBuf, String)>
+where
+    F: Fn(u64, u64),
+{
+    std::fs::create_dir_all(dest_dir)?;
+    let dest = dest_dir.join(model.filename);
+    let paths = Paths::for_destination(&dest);
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match fetch(client, model, url, &paths, &progress_callback)? {
+            Attempt::Complete => break,
+            Attempt::Interrupted(err) if attempt < retry.attempts => {
+                warn!(
+                    "Download of {} interrupted ({}); resuming (attempt {} of {})",
+                    model.filename,
+                    err,
+                    attempt + 1,
+                    retry.attempts
+                );
+                std::thread::sleep(retry.delay);
+            }
+            Attempt::Interrupted(err) => {
+                return Err(err).context(
+                    "Download interrupted; the partial file is kept and will resume next time",
+                );
+            }
+        }
+    }
+
+    let result = validate_downloaded_model(model, &paths.partial)
+        .and_then(|_| integrity::verify_download(model, &paths.partial));
+    let digest = match result {
+        Ok(digest) => digest,
+        Err(err) => {
+            paths.discard();
+            return Err(err);
+        }
+    };
+    install_validated_model(&paths.partial, &dest)?;
+    let _ = std::fs::remove_file(&paths.meta);
+    Ok((dest, digest))
+}
+
+fn fetch<F>(
+    client: &reqwest::blocking::Client,
+    model: &ModelInfo,
+    url: &str,
+    paths: &Paths,
+    progress_callback: &F,
+) -> Result<Attempt>
+where
+    F: Fn(u64, u64),
+{
+    let resume = paths.resumable(url);
+    if resume.is_none() {
+        paths.discard();
+    }
+
+    let mut request = client.get(url);
+    if let Some((offset, meta)) = &resume {
+        info!("Resuming {} from byte {}", model.filename, offset);
+        request = request.header(RANGE, format!("bytes={}-", offset));
+        if let Some(validator) = meta.validator() {
+            request = request.header(IF_RANGE, validator);
+        }
+    }
+
+    let response = match request.send() {
+        Ok(response) => response,
+        Err(err) => return Ok(Attempt::Interrupted(err.into())),
+    };
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+            .map(str::to_string)
+    };
+
+    let (offset, total, meta) = match (response.status(), resume) {
+        (StatusCode::PARTIAL_CONTENT, Some((offset, meta))) => {
+            let range = header(CONTENT_RANGE).and_then(|value| parse_content_range(&value));
+            match range {
+                Some((start, total)) if start == offset => {
+                    (offset, total.or(meta.total_bytes), meta)
+                }
+                _ => {
+                    paths.discard();
+                    return Ok(Attempt::Interrupted(anyhow::anyhow!(
+                        "Server resumed at an unexpected offset"
+                    )));
+                }
+            }
+        }
+        (StatusCode::RANGE_NOT_SATISFIABLE, Some((offset, meta)))
+            if meta.total_bytes == Some(offset) =>
+        {
+            return Ok(Attempt::Complete);
+        }
+        (status, _) if status.is_success() => {
+            // A full response: first attempt, or the file changed and If-Range failed.
+            let total = response.content_length();
+            if total.is_some_and(|length| length < super::minimum_expected_model_bytes(model)) {
+                anyhow::bail!("Model server returned an incomplete payload");
+            }
+            let meta = PartialMeta {
+                url: url.to_string(),
+                total_bytes: total,
+                etag: header(ETAG),
+                last_modified: header(LAST_MODIFIED),
+            };
+            let _ = std::fs::remove_file(&paths.partial);
+            (0, total, meta)
+        }
+        (status, _) => {
+            paths.discard();
+            anyhow::bail!("Model server answered {}", status);
+        }
+    };
+    paths.save_meta(&meta)?;
+
+    let estimated_total = total.unwrap_or(model.size_mb as u64 * 1024 * 1024);
+    if let Some(dir) = paths.partial.parent() {
+        space::ensure_space(
+            dir,
+            estimated_total.saturating_sub(offset),
+            &format!("download {}", model.name),
+        )?;
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&paths.partial)?;
+    let mut downloaded = offset;
+    let mut reader = response;
+    let mut buf = [0u8; 32768];
+    let outcome = loop {
+        match reader.read(&mut buf) {
+            Ok(0) => break None,
+            Ok(n) => {
+                file.write_all(&buf[..n])?;
+                downloaded += n as u64;
+                progress_callback(downloaded, estimated_total);
+            }
+            Err(err) => break Some(anyhow::Error::from(err)),
+        }
+    };
+    file.sync_all()?;
+
+    match (outcome, total) {
+        (Some(err), _) => Ok(Attempt::Interrupted(err)),
+        (None, Some(total)) if downloaded < total => Ok(Attempt::Interrupted(anyhow::anyhow!(
+            "connection closed after {} of {} bytes",
+            downloaded,
+            total
+        ))),
+        (None, Some(total)) if downloaded > total => {
+            paths.discard();
+            anyhow::bail!(
+                "Received {} bytes, more than the announced {}",
+                downloaded,
+                total
+            )
+        }
+        (None, _) => Ok(Attempt::Complete),
+    }
+}
+
+/// Start offset and total size from `Content-Range: bytes start-end/total`.
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    let (span, total) = range.split_once('/')?;
+    let (start, _) = span.split_once('-')?;
+    Some((start.trim().parse().ok()?, total.trim().parse().ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufRead;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    const PAYLOAD_BYTES: usize = 1024 * 1024 + 4096;
+
+    fn payload(version: u8) -> Vec<u8> {
+        let mut bytes: Vec<u8> = (0..PAYLOAD_BYTES)
+            .map(|i| (i % 251) as u8 ^ version)
+            .collect();
+        let header = crate::models::header::fixture_header(51864, 4, 1);
+        bytes[..header.len()].copy_from_slice(&header);
+        bytes
+    }
+
+    fn fixture_model() -> ModelInfo {
+        ModelInfo {
+            name: "Fixture",
+            filename: "fixture.bin",
+            size_mb: 1,
+            url: "",
+            description: "test fixture",
+            sha256: None,
+        }
+    }
+
+    fn client() -> reqwest::blocking::Client {
+        reqwest::blocking::Client::new()
+    }
+
+    fn no_wait() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 3,
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// How the stand-in answers one request.
+    #[derive(Clone, Copy)]
+    enum Behaviour {
+        /// Honour `Range` for the current ETag but close after `cut` bytes of body
+        DropAfter(usize),
+        Serve,
+    }
+
+    struct StandIn {
+        url: String,
+        requests: Arc<Mutex<Vec<Vec<String>>>>,
+        etag: Arc<Mutex<String>>,
+        body: Arc<Mutex<Vec<u8>>>,
+    }
+
+    /// A tiny HTTP/1.1 server answering one scripted behaviour per connection.
+    fn stand_in(script: Vec<Behaviour>, body: Vec<u8>) -> StandIn {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/fixture.bin", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let etag = Arc::new(Mutex::new("\"v1\"".to_string()));
+        let body = Arc::new(Mutex::new(body));
+        let (log, current_etag, current_body) = (requests.clone(), etag.clone(), body.clone());
+
+        std::thread::spawn(move || {
+            for behaviour in script {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+                let mut headers = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    headers.push(line.trim().to_string());
+                }
+                let header = |name: &str| {
+                    headers.iter().find_map(|line| {
+                        let (key, value) = line.split_once(':')?;
+                        key.eq_ignore_ascii_case(name)
+                            .then(|| value.trim().to_string())
+                    })
+                };
+                let etag = current_etag.lock().unwrap().clone();
+                let body = current_body.lock().unwrap().clone();
+                let start = match (header("Range"), header("If-Range")) {
+                    (Some(range), Some(validator)) if validator == etag => range
+                        .trim_start_matches("bytes=")
+                        .trim_end_matches('-')
+                        .parse::<usize>()
+                        .unwrap(),
+                    _ => 0,
+                };
+                log.lock().unwrap().push(headers);
+
+                let status = if start > 0 {
+                    format!(
+                        "206 Partial Content\r\nContent-Range: bytes {}-{}/{}",
+                        start,
+                        body.len() - 1,
+                        body.len()
+                    )
+                } else {
+                    "200 OK".to_string()
+                };
+                let head = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nETag: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len() - start,
+                    etag
+                );
+                stream.write_all(head.as_bytes()).unwrap();
+                let rest = &body[start..];
+                let sent = match behaviour {
+                    Behaviour::DropAfter(cut) => &rest[..cut.min(rest.len())],
+                    Behaviour::Serve => rest,
+                };
+                let _ = stream.write_all(sent);
+                let _ = stream.flush();
+            }
+        });
+
+        StandIn {
+            url,
+            requests,
+            etag,
+            body,
+        }
+    }
+
+    #[test]
+    fn dropped_connections_resume_with_range_and_if_range() {
+        let body = payload(0);
+        let server = stand_in(
+            vec![
+                Behaviour::DropAfter(300_000),
+                Behaviour::DropAfter(200_000),
+                Behaviour::Serve,
+            ],
+            body.clone(),
+        );
+        let dir = tempfile::tempdir().unwrap();
+
+        let (path, digest) = download_resumable(
+            &fixture_model(),
+            &client(),
+            &server.url,
+            dir.path(),
+            &no_wait(),
+            |_, _| {},
+        )
+        .unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), body);
+        assert_eq!(digest.len(), 64);
+        assert!(!dir.path().join("fixture.bin.downloading").exists());
+        assert!(!dir.path().join("fixture.bin.downloading.json").exists());
+
+        let requests = server.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(!requests[0].iter().any(|line| line.starts_with("range:")));
+        assert!(requests[1].contains(&"range: bytes=300000-".to_string()));
+        assert!(requests[1].contains(&"if-range: \"v1\"".to_string()));
+        assert!(requests[2].contains(&"range: bytes=500000-".to_string()));
+    }
+
+    #[test]
+    fn partial_files_survive_failed_calls_and_restart_when_the_file_changed() {
+        let server = stand_in(
+            vec![
+                Behaviour::DropAfter(400_000),
+                Behaviour::DropAfter(100_000),
+                Behaviour::Serve,
+            ],
+            payload(0),
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let once = RetryPolicy {
+            attempts: 1,
+            delay: Duration::ZERO,
+        };
+
+        let err = download_resumable(
+            &fixture_model(),
+            &client(),
+            &server.url,
+            dir.path(),
+            &once,
+            |_, _| {},
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", err).contains("partial file is kept"));
+        let partial = dir.path().join("fixture.bin.downloading");
+        assert_eq!(std::fs::metadata(&partial).unwrap().len(), 400_000);
+
+        // The published file changes: If-Range no longer matches, so the server
+        // sends the whole new file and the stale prefix must not be kept.
+        *server.etag.lock().unwrap() = "\"v2\"".to_string();
+        *server.body.lock().unwrap() = payload(7);
+        let err = download_resumable(
+            &fixture_model(),
+            &client(),
+            &server.url,
+            dir.path(),
+            &once,
+            |_, _| {},
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", err).contains("interrupted"));
+        assert_eq!(std::fs::metadata(&partial).unwrap().len(), 100_000);
+
+        let (path, _) = download_resumable(
+            &fixture_model(),
+            &client(),
+            &server.url,
+            dir.path(),
+            &once,
+            |_, _| {},
+        )
+        .unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), payload(7));
+        let requests = server.requests.lock().unwrap();
+        assert!(requests[1].contains(&"if-range: \"v1\"".to_string()));
+        assert!(requests[2].contains(&"range: bytes=100000-".to_string()));
+    }
+
+    #[test]
+    fn corrupt_resumed_files_are_discarded() {
+        let mut body = payload(0);
+        body[..4].copy_from_slice(b"html");
+        let server = stand_in(vec![Behaviour::DropAfter(1000), Behaviour::Serve], body);
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(download_resumable(
+            &fixture_model(),
+            &client(),
+            &server.url,
+            dir.path(),
+            &no_wait(),
+            |_, _| {}
+        )
+        .is_err());
+        assert!(!dir.path().join("fixture.bin").exists());
+        assert!(!dir.path().join("fixture.bin.downloading").exists());
+        assert!(!dir.path().join("fixture.bin.downloading.json").exists());
+    }
+
+    #[test]
+    fn content_range_headers_are_parsed() {
+        assert_eq!(
+            parse_content_range("bytes 100-199/200"),
+            Some((100, Some(200)))
+        );
+        assert_eq!(parse_content_range("bytes 5-9/*"), Some((5, None)));
+        assert_eq!(parse_content_range("items 0-1/2"), None);
+    }
+}
@@ -0,0 +1,256 @@
+//! whisper.cpp ggml model headers.
+//!
+//! A ggml Whisper file starts with the `ggml` magic followed by eleven
+//! little-endian `i32` hyper-parameters. Reading them gives the real vocabulary,
+//! layer counts, mel bins and quantisation instead of guessing from the file
+//! size, and lets files that whisper.cpp would refuse be rejected up front.
+//! GGUF files carry their metadata differently and are accepted on the magic
+//! alone.
+
+use anyhow::{Context, Result};
+use std::io::Read;
+use std::path::Path;
+
+const GGML_MAGIC: &[u8; 4] = b"lmgg";
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+const HPARAM_COUNT: usize = 11;
+const HEADER_BYTES: usize = 4 + HPARAM_COUNT * 4;
+/// whisper.cpp folds the quantisation format version into `ftype` in steps of this.
+const QUANT_VERSION_FACTOR: i32 = 1000;
+/// English-only models have 51864 tokens; multilingual ones add language tokens.
+const ENGLISH_VOCAB: i32 = 51864;
+
+/// Hyper-parameters from a ggml Whisper model header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelHeader {
+    pub n_vocab: i32,
+    pub n_audio_ctx: i32,
+    pub n_audio_state: i32,
+    pub n_audio_head: i32,
+    pub n_audio_layer: i32,
+    pub n_text_ctx: i32,
+    pub n_text_state: i32,
+    pub n_text_head: i32,
+    pub n_text_layer: i32,
+    pub n_mels: i32,
+    /// Weight type, without the quantisation format version
+    pub ftype: i32,
+}
+
+impl ModelHeader {
+    pub fn multilingual(&self) -> bool {
+        self.n_vocab > ENGLISH_VOCAB
+    }
+
+    /// Whisper size the encoder depth corresponds to.
+    pub fn size_class(&self) -> &'static str {
+        match self.n_audio_layer {
+            4 => "Tiny",
+            6 => "Base",
+            12 => "Small",
+            24 => "Medium",
+            32 => "Large",
+            _ => "Custom",
+        }
+    }
+
+    /// Accuracy tier by encoder depth, matching the size-based estimate for full-precision files.
+    pub fn accuracy_tier(&self) -> &'static str {
+        match self.n_audio_layer {
+            ..=4 => "Basic",
+            5..=6 => "Good",
+            7..=12 => "Better",
+            13..=24 => "High",
+            _ => "Highest",
+        }
+    }
+
+    pub fn quantisation(&self) -> Option<&'static str> {
+        Some(match self.ftype {
+            0 => "F32",
+            1 => "F16",
+            2 => "Q4_0",
+            3 | 4 => "Q4_1",
+            7 => "Q8_0",
+            8 => "Q5_0",
+            9 => "Q5_1",
+            10 => "Q2_K",
+            11 => "Q3_K",
+            12 => "Q4_K",
+            13 => "Q5_K",
+            14 => "Q6_K",
+            _ => return None,
+        })
+    }
+
+    /// One-line summary, e.g. "Small (12 encoder / 12 decoder layers) • multilingual • Q8_0".
+    pub fn describe(&self) -> String {
+        format!(
+            "{} ({} encoder / {} decoder layers) • {} • {}",
+            self.size_class(),
+            self.n_audio_layer,
+            self.n_text_layer,
+            if self.multilingual() {
+                "multilingual"
+            } else {
+                "English-only"
+            },
+            self.quantisation().unwrap_or("unknown weights")
+        )
+    }
+
+    fn validate(&self) -> Result<()> {
+        let positive = [
+            ("n_audio_ctx", self.n_audio_ctx),
+            ("n_audio_state", self.n_audio_state),
+            ("n_audio_head", self.n_audio_head),
+            ("n_text_ctx", self.n_text_ctx),
+            ("n_text_state", self.n_text_state),
+            ("n_text_head", self.n_text_head),
+        ];
+        if let Some((name, value)) = positive.iter().find(|(_, value)| *value <= 0) {
+            anyhow::bail!("Not a Whisper model: {} is {}", name, value);
+        }
+        if !(ENGLISH_VOCAB..=ENGLISH_VOCAB + 200).contains(&self.n_vocab) {
+            anyhow::bail!("Not a Whisper model: vocabulary of {} tokens", self.n_vocab);
+        }
+        if !matches!(self.n_mels, 80 | 128) {
+            anyhow::bail!("Not a Whisper model: {} mel bins", self.n_mels);
+        }
+        for (name, layers) in [
+            ("encoder", self.n_audio_layer),
+            ("decoder", self.n_text_layer),
+        ] {
+            if !(1..=64).contains(&layers) {
+                anyhow::bail!("Not a Whisper model: {} {} layers", layers, name);
+            }
+        }
+        if self.quantisation().is_none() {
+            anyhow::bail!("Unsupported model weight type {}", self.ftype);
+        }
+        Ok(())
+    }
+}
+
+/// Parse the start of a model file; `None` for GGUF files, whose header is not read.
+pub(crate) fn parse_header(bytes: &[u8]) -> Result<Option<ModelHeader>> {
+    let magic = bytes.get(..4).context("Model file is too short")?;
+    if magic == GGUF_MAGIC {
+        return Ok(None);
+    }
+    if magic != GGML_MAGIC {
+        anyhow::bail!("File is not a recognised GGML/GGUF model");
+    }
+    let values: Vec<i32> = bytes
+        .get(4..HEADER_BYTES)
+        .context("Model header is truncated")?
+        .chunks_exact(4)
+        .map(|chunk| i32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect();
+
+    let header = ModelHeader {
+        n_vocab: values[0],
+        n_audio_ctx: values[1],
+        n_audio_state: values[2],
+        n_audio_head: values[3],
+        n_audio_layer: values[4],
+        n_text_ctx: values[5],
+        n_text_state: values[6],
+        n_text_head: values[7],
+        n_text_layer: values[8],
+        n_mels: values[9],
+        ftype: values[10] % QUANT_VERSION_FACTOR,
+    };
+    header.validate()?;
+    Ok(Some(header))
+}
+
+/// Read and check the header of the model at `path`.
+pub fn read_header(path: &Path) -> Result<Option<ModelHeader>> {
+    let mut bytes = Vec::with_capacity(HEADER_BYTES);
+    std::fs::File::open(path)?
+        .take(HEADER_BYTES as u64)
+        .read_to_end(&mut bytes)?;
+    parse_header(&bytes).with_context(|| format!("Invalid model file {:?}", path))
+}
+
+/// Why an English-only model does not suit the language settings, if it does not.
+pub(crate) fn english_only_warning(
+    model_name: &str,
+    language: &str,
+    translate_to_english: bool,
+) -> Option<String> {
+    if translate_to_english {
+        Some(format!(
+            "{} is English-only and cannot translate; choose a multilingual model or turn off Translate to English",
+            model_name
+        ))
+    } else if language != "en" {
+        Some(format!(
+            "{} is English-only but the language is set to \"{}\"; other languages will be transcribed poorly",
+            model_name, language
+        ))
+    } else {
+        None
+    }
+}
+
+/// Header bytes of a plausible Whisper model, for tests elsewhere in the crate.
+#[cfg(test)]
+pub(crate) fn fixture_header(n_vocab: i32, layers: i32, ftype: i32) -> Vec<u8> {
+    let hparams = [
+        n_vocab, 1500, 512, 8, layers, 448, 512, 8, layers, 80, ftype,
+    ];
+    let mut bytes = GGML_MAGIC.to_vec();
+    for value in hparams {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ggml_headers_describe_the_model() {
+        let header = parse_header(&fixture_header(51865, 12, 1007))
+            .unwrap()
+            .unwrap();
+        assert!(header.multilingual());
+        assert_eq!(header.ftype, 7);
+        assert_eq!(
+            header.describe(),
+            "Small (12 encoder / 12 decoder layers) • multilingual • Q8_0"
+        );
+
+        let english = parse_header(&fixture_header(51864, 6, 1)).unwrap().unwrap();
+        assert!(!english.multilingual());
+        assert_eq!(english.size_class(), "Base");
+        assert_eq!(english.accuracy_tier(), "Good");
+
+        assert_eq!(parse_header(b"GGUF\x03\0\0\0").unwrap(), None);
+    }
+
+    #[test]
+    fn incompatible_files_are_rejected() {
+        let mut zeroed = GGML_MAGIC.to_vec();
+        zeroed.resize(HEADER_BYTES, 0);
+        assert!(parse_header(&zeroed).is_err());
+        assert!(parse_header(&fixture_header(32000, 32, 1)).is_err());
+        assert!(parse_header(&fixture_header(51865, 12, 42)).is_err());
+        assert!(parse_header(&fixture_header(51865, 12, 1)[..20]).is_err());
+        assert!(parse_header(b"<html>").is_err());
+    }
+
+    #[test]
+    fn english_only_models_warn_about_other_languages() {
+        assert_eq!(english_only_warning("ggml-base.en.bin", "en", false), None);
+        assert!(english_only_warning("ggml-base.en.bin", "de", false)
+            .unwrap()
+            .contains("\"de\""));
+        assert!(english_only_warning("ggml-base.en.bin", "en", true)
+            .unwrap()
+            .contains("cannot translate"));
+    }
+}
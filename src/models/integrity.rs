@@ -0,0 +1,244 @@
+//! SHA-256 verification of model files.
+//!
+//! Downloads are checked against the catalogue digest when one is listed. A
+//! download from a configured mirror is refused without one; the built-in
+//! catalogue lists none yet, so a download tampered with on the way from the
+//! model's own URL is not detected. Every downloaded or imported
+//! model has its digest recorded in `model_digests.json`, and a model whose
+//! size or modification time has changed since is hashed again before it is
+//! loaded, so a file corrupted or replaced after install is refused instead of
+//! reaching whisper.cpp.
+
+use super::{catalogue, FileStamp, ModelInfo};
+use crate::{get_data_dir, persistence};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+pub const DIGEST_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct DigestRecord {
+    sha256: String,
+    #[serde(flatten)]
+    stamp: FileStamp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct DigestStore {
+    schema_version: u32,
+    #[serde(default)]
+    files: BTreeMap<PathBuf, DigestRecord>,
+}
+
+impl Default for DigestStore {
+    fn default() -> Self {
+        Self {
+            schema_version: DIGEST_SCHEMA_VERSION,
+            files: BTreeMap::new(),
+        }
+    }
+}
+
+fn digests_path() -> PathBuf {
+    get_data_dir().join("model_digests.json")
+}
+
+fn load_store() -> DigestStore {
+    let path = digests_path();
+    if !path.exists() {
+        return DigestStore::default();
+    }
+    persistence::read_json_private(&path).unwrap_or_else(|err| {
+        warn!(
+            "Ignoring unreadable model digest record {:?}: {}",
+            path, err
+        );
+        DigestStore::default()
+    })
+}
+
+/// Lower-case hex SHA-256 of a file, read in blocks.
+pub(crate) fn sha256_file(path: &Path) -> Result<String> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Lower-case hex SHA-256 of an in-memory payload.
+pub(crate) fn sha256_bytes(bytes: &[u8]) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
+
+fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn catalogue_digest(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+    catalogue()
+        .iter()
+        .find(|model| model.filename == name)
+        .and_then(|model| model.sha256)
+}
+
+fn check_expected(expected: Option<&str>, actual: &str, path: &Path) -> Result<()> {
+    match expected {
+        Some(expected) if !expected.eq_ignore_ascii_case(actual) => anyhow::bail!(
+            "SHA-256 mismatch for {:?}: expected {}, got {}; the file is corrupted or not the published model",
+            path,
+            expected,
+            actual
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Hash a downloaded payload and compare it with the catalogue digest, if listed.
+pub(crate) fn verify_download(model: &ModelInfo, path: &Path) -> Result<String> {
+    let digest = sha256_file(path)?;
+    if model.sha256.is_none() {
+        warn!(
+            "{} has no published SHA-256; recording the downloaded file ({}) as served",
+            model.filename, digest
+        );
+    }
+    check_expected(model.sha256, &digest, path)?;
+    Ok(digest)
+}
+
+/// Remember `digest` as the content of the model at `path`; later changes are refused.
+pub(crate) fn record_verified(path: &Path, digest: &str) -> Result<()> {
+    let mut store = load_store();
+    store.files.insert(
+        path.to_path_buf(),
+        DigestRecord {
+            sha256: digest.to_string(),
+            stamp: FileStamp::of(path)?,
+        },
+    );
+    store.files.retain(|known, _| known.is_file());
+    persistence::write_json_private(&digests_path(), &store)
+}
+
+/// Drop the recorded digest of a deleted model.
+pub(crate) fn forget(path: &Path) {
+    let mut store = load_store();
+    if store.files.remove(path).is_some() {
+        if let Err(err) = persistence::write_json_private(&digests_path(), &store) {
+            warn!("Failed to update model digest record: {}", err);
+        }
+    }
+}
+
+/// Hash an imported model, check a catalogue digest for curated file names and record it.
+pub(crate) fn verify_import(path: &Path) -> Result<()> {
+    let digest = sha256_file(path)?;
+    check_expected(catalogue_digest(path), &digest, path)?;
+    record_verified(path, &digest)
+}
+
+/// Refuse a model whose content no longer matches its recorded or published digest.
+///
+/// Files unchanged since they were last verified are not hashed again. A model
+/// with no record yet, such as one installed by an older release, is hashed
+/// once, checked against the catalogue and recorded.
+pub fn verify_model_file(path: &Path) -> Result<()> {
+    let stamp = FileStamp::of(path)?;
+    let mut store = load_store();
+    let recorded = store.files.get(path).cloned();
+    if recorded
+        .as_ref()
+        .is_some_and(|record| record.stamp == stamp)
+    {
+        return Ok(());
+    }
+
+    info!("Verifying SHA-256 of {:?}", path);
+    let digest = sha256_file(path)?;
+    check_stored(recorded.as_ref(), catalogue_digest(path), &digest, path)?;
+
+    store.files.insert(
+        path.to_path_buf(),
+        DigestRecord {
+            sha256: digest,
+            stamp,
+        },
+    );
+    if let Err(err) = persistence::write_json_private(&digests_path(), &store) {
+        warn!("Failed to record model digest: {}", err);
+    }
+    Ok(())
+}
+
+fn check_stored(
+    recorded: Option<&DigestRecord>,
+    published: Option<&str>,
+    actual: &str,
+    path: &Path,
+) -> Result<()> {
+    if published.is_some() {
+        return check_expected(published, actual, path);
+    }
+    match recorded {
+        Some(record) if record.sha256 != actual => anyhow::bail!(
+            "Model {:?} changed since it was verified (SHA-256 {} instead of {}); download or import it again",
+            path,
+            actual,
+            record.sha256
+        ),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_digest_matches_the_known_sha256() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.bin");
+        std::fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn changed_or_unpublished_content_is_refused() {
+        let path = Path::new("/models/ggml-base.en.bin");
+        let record = DigestRecord {
+            sha256: "aaaa".to_string(),
+            stamp: FileStamp {
+                size_bytes: 3,
+                modified_secs: 1,
+            },
+        };
+
+        assert!(check_stored(None, None, "bbbb", path).is_ok());
+        assert!(check_stored(Some(&record), None, "aaaa", path).is_ok());
+        let changed = check_stored(Some(&record), None, "bbbb", path).unwrap_err();
+        assert!(changed
+            .to_string()
+            .contains("changed since it was verified"));
+
+        assert!(check_stored(Some(&record), Some("BBBB"), "bbbb", path).is_ok());
+        let tampered = check_stored(None, Some("cccc"), "bbbb", path).unwrap_err();
+        assert!(tampered.to_string().contains("SHA-256 mismatch"));
+    }
+}
@@ -2,11 +2,26 @@
 //!
 //! Handles downloading, listing, and switching between Whisper models.
 
+pub mod benchmark;
+mod catalogue;
+mod download;
+mod header;
+mod integrity;
+mod space;
+pub mod storage;
+
 use anyhow::Result;
-use std::io::Read;
+use benchmark::ModelMeasurements;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 use tracing::info;
 
+pub use catalogue::{catalogue, configure_catalogue};
+pub use header::{read_header, ModelHeader};
+pub use integrity::verify_model_file;
+pub use space::InsufficientSpace;
+
 const MIN_EXPECTED_MODEL_PERCENT: u64 = 80;
 const MIN_CUSTOM_MODEL_BYTES: u64 = 1024 * 1024;
 
@@ -18,17 +33,50 @@ pub struct ModelInfo {
     pub size_mb: u32,
     pub url: &'static str,
     pub description: &'static str,
+    /// SHA-256 of the published file, checked after download and import.
+    /// `scripts/model_digests.py` prints the current values. Built-in entries do
+    /// not list one yet, so the first download from the model's own URL is
+    /// trusted as served and only later changes to the file are caught; a
+    /// mirror download without one is refused.
+    pub sha256: Option<&'static str>,
+}
+
+/// Size and modification time identifying the model file a cached result belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FileStamp {
+    pub size_bytes: u64,
+    pub modified_secs: u64,
+}
+
+impl FileStamp {
+    pub(crate) fn of(path: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        let modified_secs = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        Ok(Self {
+            size_bytes: metadata.len(),
+            modified_secs,
+        })
+    }
 }
 
-/// Lightweight model profile derived from model file size.
+/// Model profile: speed measured on this machine once benchmarked, otherwise
+/// estimated from file size; accuracy from the model header, or file size for GGUF.
 #[derive(Debug, Clone)]
 pub struct ModelBenchmark {
     pub size_mb: f64,
     pub speed_tier: &'static str,
     pub accuracy_tier: &'static str,
+    pub measured: ModelMeasurements,
+    /// Parsed ggml header; `None` for GGUF files
+    pub header: Option<ModelHeader>,
 }
 
-/// All available models from Hugging Face
+/// Built-in models from Hugging Face, offered unless a catalogue manifest is configured
 pub const AVAILABLE_MODELS: &[ModelInfo] = &[
     ModelInfo {
         name: "Tiny (English)",
@@ -36,6 +84,7 @@ pub const AVAILABLE_MODELS: &[ModelInfo] = &[
         size_mb: 75,
         url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny.en.bin",
         description: "Fastest, basic accuracy",
+        sha256: None,
     },
     ModelInfo {
         name: "Tiny (Multilingual)",
@@ -43,6 +92,7 @@ pub const AVAILABLE_MODELS: &[ModelInfo] = &[
         size_mb: 75,
         url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny.bin",
         description: "Fastest, supports all languages",
+        sha256: None,
     },
     ModelInfo {
         name: "Base (English)",
@@ -50,6 +100,7 @@ pub const AVAILABLE_MODELS: &[ModelInfo] = &[
         size_mb: 142,
         url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-base.en.bin",
         description: "Fast, good accuracy (recommended)",
+        sha256: None,
     },
     ModelInfo {
         name: "Base (Multilingual)",
@@ -57,6 +108,7 @@ pub const AVAILABLE_MODELS: &[ModelInfo] = &[
         size_mb: 142,
         url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-base.bin",
         description: "Fast, good accuracy, all languages",
+        sha256: None,
     },
     ModelInfo {
         name: "Small (English)",
@@ -64,6 +116,7 @@ pub const AVAILABLE_MODELS: &[ModelInfo] = &[
         size_mb: 466,
         url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small.en.bin",
         description: "Medium speed, better accuracy",
+        sha256: None,
     },
     ModelInfo {
         name: "Small (Multilingual)",
@@ -71,6 +124,7 @@ pub const AVAILABLE_MODELS: &[ModelInfo] = &[
         size_mb: 466,
         url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small.bin",
         description: "Medium speed, better accuracy, all languages",
+        sha256: None,
     },
     ModelInfo {
         name: "Medium (English)",
@@ -78,6 +132,7 @@ pub const AVAILABLE_MODELS: &[ModelInfo] = &[
         size_mb: 1500,
         url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-medium.en.bin",
         description: "Slow, high accuracy",
+        sha256: None,
     },
     ModelInfo {
         name: "Distil Large v3",
@@ -85,6 +140,7 @@ pub const AVAILABLE_MODELS: &[ModelInfo] = &[
         size_mb: 1530,
         url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-distil-large-v3.bin",
         description: "Best speed/accuracy for high-end English dictation",
+        sha256: None,
     },
     ModelInfo {
         name: "Large v3",
@@ -92,6 +148,47 @@ pub const AVAILABLE_MODELS: &[ModelInfo] = &[
         size_mb: 3000,
         url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-large-v3.bin",
         description: "Slowest, best accuracy, all languages",
+        sha256: None,
+    },
+    ModelInfo {
+        name: "Base (English, 8-bit)",
+        filename: "ggml-base.en-q8_0.bin",
+        size_mb: 78,
+        url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-base.en-q8_0.bin",
+        description: "Base accuracy at about half the size",
+        sha256: None,
+    },
+    ModelInfo {
+        name: "Small (English, 8-bit)",
+        filename: "ggml-small.en-q8_0.bin",
+        size_mb: 252,
+        url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small.en-q8_0.bin",
+        description: "Small accuracy with less memory",
+        sha256: None,
+    },
+    ModelInfo {
+        name: "Medium (English, 5-bit)",
+        filename: "ggml-medium.en-q5_0.bin",
+        size_mb: 514,
+        url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-medium.en-q5_0.bin",
+        description: "Near medium accuracy in a third of the memory",
+        sha256: None,
+    },
+    ModelInfo {
+        name: "Medium (English, 8-bit)",
+        filename: "ggml-medium.en-q8_0.bin",
+        size_mb: 785,
+        url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-medium.en-q8_0.bin",
+        description: "Medium accuracy in about half the memory",
+        sha256: None,
+    },
+    ModelInfo {
+        name: "Large v3 (5-bit)",
+        filename: "ggml-large-v3-q5_0.bin",
+        size_mb: 1080,
+        url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-large-v3-q5_0.bin",
+        description: "Large v3 accuracy, all languages, in about a third of the memory",
+        sha256: None,
     },
 ];
 
@@ -135,16 +232,11 @@ fn validate_downloaded_model(model: &ModelInfo, path: &Path) -> Result<()> {
         );
     }
 
-    validate_model_magic(path)
+    validate_model_header(path)
 }
 
-fn validate_model_magic(path: &Path) -> Result<()> {
-    let mut magic = [0_u8; 4];
-    std::fs::File::open(path)?.read_exact(&mut magic)?;
-    if &magic != b"lmgg" && &magic != b"GGUF" {
-        anyhow::bail!("File is not a recognised GGML/GGUF model");
-    }
-    Ok(())
+fn validate_model_header(path: &Path) -> Result<()> {
+    read_header(path).map(|_| ())
 }
 
 /// Validate a configured or imported model before attempting to load it.
@@ -153,7 +245,7 @@ pub fn validate_model_path(path: &Path) -> Result<()> {
         anyhow::bail!("Model path is not a regular file: {:?}", path);
     }
 
-    if let Some(model) = AVAILABLE_MODELS.iter().find(|model| {
+    if let Some(model) = catalogue().iter().find(|model| {
         path.file_name()
             .map(|name| name == std::ffi::OsStr::new(model.filename))
             .unwrap_or(false)
@@ -165,7 +257,7 @@ pub fn validate_model_path(path: &Path) -> Result<()> {
     if size < MIN_CUSTOM_MODEL_BYTES {
         anyhow::bail!("Model is incomplete: got {} bytes", size);
     }
-    validate_model_magic(path)
+    validate_model_header(path)
 }
 
 fn install_validated_model(temp_path: &Path, dest_path: &Path) -> Result<()> {
@@ -211,12 +303,27 @@ pub fn import_model_from_path(source: &Path) -> Result<PathBuf> {
     let source_canon = std::fs::canonicalize(source).ok();
     let dest_canon = std::fs::canonicalize(&dest).ok();
     if source_canon.is_some() && source_canon == dest_canon {
+        integrity::verify_import(&dest)?;
         return Ok(dest);
     }
 
+    let size = std::fs::metadata(source)?.len();
+    space::ensure_space(
+        &models_dir,
+        size,
+        &format!("import {}", file_name.to_string_lossy()),
+    )?;
     let temp_dest = dest.with_extension("importing");
-    std::fs::copy(source, &temp_dest)?;
-    std::fs::rename(&temp_dest, &dest)?;
+    if let Err(err) =
+        std::fs::copy(source, &temp_dest).and_then(|_| std::fs::rename(&temp_dest, &dest))
+    {
+        let _ = std::fs::remove_file(&temp_dest);
+        return Err(err.into());
+    }
+    if let Err(err) = integrity::verify_import(&dest) {
+        let _ = std::fs::remove_file(&dest);
+        return Err(err);
+    }
 
     info!("Imported model {:?} to {:?}", source, dest);
     Ok(dest)
@@ -228,7 +335,7 @@ pub fn list_custom_models() -> Vec<PathBuf> {
     let mut models = Vec::new();
 
     let built_in_names: std::collections::HashSet<&str> =
-        AVAILABLE_MODELS.iter().map(|m| m.filename).collect();
+        catalogue().iter().map(|m| m.filename).collect();
 
     if let Ok(entries) = std::fs::read_dir(models_dir) {
         for entry in entries.flatten() {
@@ -253,12 +360,12 @@ pub fn list_custom_models() -> Vec<PathBuf> {
     models
 }
 
-/// Estimate model speed/accuracy tiers based on on-disk size.
-pub fn estimate_model_benchmark(path: &Path) -> Result<ModelBenchmark> {
+/// Profile a model from its cached benchmark and on-disk size.
+pub fn model_benchmark(path: &Path) -> Result<ModelBenchmark> {
     let metadata = std::fs::metadata(path)?;
     let size_mb = metadata.len() as f64 / (1024.0 * 1024.0);
 
-    let (speed_tier, accuracy_tier) = if size_mb <= 100.0 {
+    let (estimated_speed, size_accuracy) = if size_mb <= 100.0 {
         ("Very Fast", "Basic")
     } else if size_mb <= 300.0 {
         ("Fast", "Good")
@@ -270,140 +377,127 @@ pub fn estimate_model_benchmark(path: &Path) -> Result<ModelBenchmark> {
         ("Slow", "Highest")
     };
 
+    let header = read_header(path)?;
+    let accuracy_tier = header
+        .map(|header| header.accuracy_tier())
+        .unwrap_or(size_accuracy);
+
+    let measured = benchmark::measured(path);
+    let speed_tier = measured
+        .best()
+        .map(|(_, measurement)| benchmark::speed_tier(measurement.real_time_factor))
+        .unwrap_or(estimated_speed);
+
     Ok(ModelBenchmark {
         size_mb,
         speed_tier,
         accuracy_tier,
+        measured,
+        header,
     })
 }
 
-/// Download a model with progress callback (async, requires GUI feature for tokio runtime)
-#[cfg(feature = "gui")]
-pub async fn download_model<F>(model: &ModelInfo, progress_callback: F) -> Result<PathBuf>
-where
-    F: Fn(u64, u64) + Send + 'static,
-{
-    use futures_util::StreamExt;
-    use std::io::Write;
-
-    validate_model_filename(model)?;
-
-    let models_dir = get_models_dir();
-    std::fs::create_dir_all(&models_dir)?;
-
-    let dest_path = models_dir.join(model.filename);
-    let temp_path = models_dir.join(format!("{}.downloading", model.filename));
-    let _ = std::fs::remove_file(&temp_path);
-
-    info!("Downloading {} to {:?}", model.name, dest_path);
-
-    let client = reqwest::Client::new();
-    let response = client.get(model.url).send().await?.error_for_status()?;
-
-    if response
-        .content_length()
-        .map(|length| length < minimum_expected_model_bytes(model))
-        .unwrap_or(false)
-    {
-        anyhow::bail!("Model server returned an incomplete payload");
+/// Warning when the active model is English-only but the language settings need more.
+///
+/// Uses the header where it can be read, otherwise the `.en` file name convention.
+pub fn language_warning(config: &crate::Config) -> Option<String> {
+    let model_path = model_for_language(config, &config.language);
+    let name = model_path.file_name()?.to_string_lossy();
+    if !is_multilingual(model_path) {
+        return header::english_only_warning(&name, &config.language, config.translate_to_english);
     }
+    config
+        .language_models
+        .iter()
+        .filter(|(language, path)| language.as_str() != "en" && !is_multilingual(path))
+        .find_map(|(language, path)| {
+            let name = path.file_name()?.to_string_lossy();
+            header::english_only_warning(&name, language, false)
+        })
+}
 
-    let total_size = response
-        .content_length()
-        .unwrap_or(model.size_mb as u64 * 1024 * 1024);
-    let mut downloaded: u64 = 0;
-
-    let result: Result<()> = async {
-        let mut file = std::fs::File::create(&temp_path)?;
-        let mut stream = response.bytes_stream();
+/// Whether the model at `path` handles languages other than English.
+pub fn is_multilingual(path: &Path) -> bool {
+    match read_header(path) {
+        Ok(Some(header)) => header.multilingual(),
+        _ => !path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().contains(".en.")),
+    }
+}
 
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            file.write_all(&chunk)?;
-            downloaded += chunk.len() as u64;
-            progress_callback(downloaded, total_size);
-        }
+/// The model for dictating in `language`: its installed `language_models`
+/// entry, otherwise `model_path`.
+pub fn model_for_language<'a>(config: &'a crate::Config, language: &str) -> &'a Path {
+    config
+        .language_models
+        .get(language)
+        .filter(|path| path.exists())
+        .map(PathBuf::as_path)
+        .unwrap_or(&config.model_path)
+}
 
-        file.sync_all()?;
-        drop(file);
-        validate_downloaded_model(model, &temp_path)?;
-        install_validated_model(&temp_path, &dest_path)?;
-        Ok(())
-    }
-    .await;
+/// Installed curated and custom model files.
+pub fn installed_model_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = catalogue()
+        .iter()
+        .filter(|model| is_model_installed(model))
+        .map(get_model_path)
+        .collect();
+    paths.extend(list_custom_models());
+    paths
+}
 
-    if let Err(err) = result {
-        let _ = std::fs::remove_file(&temp_path);
-        return Err(err);
-    }
+/// Fail early when the models directory cannot hold `model`.
+///
+/// Uses the catalogue size less any partial download that will be resumed; the
+/// download checks again once the server reports the exact size.
+pub fn check_download_space(model: &ModelInfo) -> Result<()> {
+    let models_dir = get_models_dir();
+    let partial = models_dir.join(format!("{}.downloading", model.filename));
+    let resumable = std::fs::metadata(partial).map(|m| m.len()).unwrap_or(0);
+    let needed = (model.size_mb as u64 * 1024 * 1024).saturating_sub(resumable);
+    space::ensure_space(&models_dir, needed, &format!("download {}", model.name))
+}
 
-    info!("Download complete: {:?}", dest_path);
-    Ok(dest_path)
+/// Download a model with progress callback (async, requires GUI feature for tokio runtime)
+///
+/// Runs the resumable blocking download on tokio's blocking pool.
+#[cfg(feature = "gui")]
+pub async fn download_model<F>(model: &ModelInfo, progress_callback: F) -> Result<PathBuf>
+where
+    F: Fn(u64, u64) + Send + 'static,
+{
+    let model = model.clone();
+    tokio::task::spawn_blocking(move || download_model_blocking(&model, progress_callback)).await?
 }
 
 /// Blocking model download for CLI use (no tokio/GUI required).
 ///
-/// Downloads the model file with a simple progress callback and atomic rename.
+/// An interrupted download keeps its partial file and resumes where it
+/// stopped, both within this call and on the next one.
 pub fn download_model_blocking<F>(model: &ModelInfo, progress_callback: F) -> Result<PathBuf>
 where
     F: Fn(u64, u64),
 {
-    use std::io::Write;
-
     validate_model_filename(model)?;
 
     let models_dir = get_models_dir();
-    std::fs::create_dir_all(&models_dir)?;
-
-    let dest_path = models_dir.join(model.filename);
-    let temp_path = models_dir.join(format!("{}.downloading", model.filename));
-    let _ = std::fs::remove_file(&temp_path);
-
-    info!("Downloading {} to {:?}", model.name, dest_path);
-
-    let client = reqwest::blocking::Client::builder().timeout(None).build()?;
-
-    let response = client.get(model.url).send()?.error_for_status()?;
-
-    if response
-        .content_length()
-        .map(|length| length < minimum_expected_model_bytes(model))
-        .unwrap_or(false)
-    {
-        anyhow::bail!("Model server returned an incomplete payload");
-    }
-
-    let total_size = response
-        .content_length()
-        .unwrap_or(model.size_mb as u64 * 1024 * 1024);
-
-    let result = (|| -> Result<()> {
-        let mut file = std::fs::File::create(&temp_path)?;
-        let mut reader = response;
-        let mut downloaded: u64 = 0;
-        let mut buf = [0u8; 32768];
-
-        loop {
-            let n = reader.read(&mut buf)?;
-            if n == 0 {
-                break;
-            }
-            file.write_all(&buf[..n])?;
-            downloaded += n as u64;
-            progress_callback(downloaded, total_size);
-        }
-
-        file.sync_all()?;
-        drop(file);
-        validate_downloaded_model(model, &temp_path)?;
-        install_validated_model(&temp_path, &dest_path)?;
-        Ok(())
-    })();
-
-    if let Err(err) = result {
-        let _ = std::fs::remove_file(&temp_path);
-        return Err(err);
-    }
+    check_download_space(model)?;
+    info!(
+        "Downloading {} to {:?}",
+        model.name,
+        models_dir.join(model.filename)
+    );
+    let (dest_path, digest) = download::download_resumable(
+        model,
+        &catalogue::http_client(None)?,
+        &catalogue::download_url(model)?,
+        &models_dir,
+        &download::RetryPolicy::default(),
+        progress_callback,
+    )?;
+    integrity::record_verified(&dest_path, &digest)?;
 
     info!("Download complete: {:?}", dest_path);
     Ok(dest_path)
@@ -421,6 +515,7 @@ mod tests {
             size_mb: 1,
             url: "https://example.invalid/fixture.bin",
             description: "test fixture",
+            sha256: None,
         }
     }
 
@@ -437,7 +532,8 @@ mod tests {
         let dir = tempfile::tempdir().unwrap();
         let path = dir.path().join(model.filename);
         let mut file = std::fs::File::create(&path).unwrap();
-        file.write_all(b"lmgg").unwrap();
+        file.write_all(&header::fixture_header(51864, 4, 1))
+            .unwrap();
         file.seek(SeekFrom::Start(minimum_expected_model_bytes(&model) - 1))
             .unwrap();
         file.write_all(&[0]).unwrap();
@@ -467,4 +563,18 @@ mod tests {
         drop(file);
         assert!(validate_model_path(&path).is_err());
     }
+
+    #[test]
+    fn listed_catalogue_digests_are_sha256_hex() {
+        for model in AVAILABLE_MODELS {
+            if let Some(digest) = model.sha256 {
+                assert_eq!(digest.len(), 64, "{}", model.filename);
+                assert!(
+                    digest.chars().all(|c| c.is_ascii_hexdigit()),
+                    "{}",
+                    model.filename
+                );
+            }
+        }
+    }
 }
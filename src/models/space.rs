@@ -0,0 +1,161 @@
+//! Free-space preflight for model downloads and imports.
+//!
+//! Downloads and imports write a temporary file next to the final model and
+//! rename it into place, so the final copy reuses the temporary file's blocks
+//! and the requirement is the temporary file's size once. A fixed headroom is
+//! kept free so an install never fills the disk the data directory lives on.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Space left free after an install, for history, logs and the digest records.
+const HEADROOM_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Bytes available to this user on the filesystem holding `path`.
+fn available_bytes(path: &Path) -> Option<u64> {
+    // The models directory may not exist before the first download.
+    let existing = path.ancestors().find(|ancestor| ancestor.exists())?;
+    available_bytes_at(existing)
+}
+
+#[cfg(unix)]
+fn available_bytes_at(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+        return None;
+    }
+    // The field types are narrower than u64 on macOS.
+    #[allow(clippy::useless_conversion)]
+    let available = u64::from(stats.f_bavail).saturating_mul(u64::from(stats.f_frsize));
+    Some(available)
+}
+
+#[cfg(windows)]
+fn available_bytes_at(dir: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDiskFreeSpaceExW(
+            directory: *const u16,
+            available_to_caller: *mut u64,
+            total: *mut u64,
+            total_free: *mut u64,
+        ) -> i32;
+    }
+
+    let wide: Vec<u16> = dir.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    (ok != 0).then_some(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn available_bytes_at(_dir: &Path) -> Option<u64> {
+    None
+}
+
+fn format_megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// Refuse to start writing `needed` bytes into `dir` when the disk cannot hold them.
+///
+/// `action` completes "Not enough disk space to ...". When free space cannot be
+/// determined the check is skipped rather than blocking the install.
+pub(crate) fn ensure_space(dir: &Path, needed: u64, action: &str) -> Result<()> {
+    let Some(available) = available_bytes(dir) else {
+        debug!("Free space for {:?} is unknown; skipping preflight", dir);
+        return Ok(());
+    };
+    check_space(dir, needed, available, action)
+}
+
+/// An install that would not fit, with the numbers needed to act on it.
+#[derive(Debug, Clone)]
+pub struct InsufficientSpace {
+    pub action: String,
+    pub dir: PathBuf,
+    pub needed_bytes: u64,
+    pub available_bytes: u64,
+}
+
+impl InsufficientSpace {
+    /// Bytes to free before the install fits, headroom included.
+    pub fn shortfall_bytes(&self) -> u64 {
+        self.needed_bytes
+            .saturating_add(HEADROOM_BYTES)
+            .saturating_sub(self.available_bytes)
+    }
+}
+
+impl std::fmt::Display for InsufficientSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Not enough disk space to {}: it needs {} plus {} headroom in {:?}, but only {} is free. \
+             Free at least {} on that disk (for example by deleting unused models in Settings → Models) and try again.",
+            self.action,
+            format_megabytes(self.needed_bytes),
+            format_megabytes(HEADROOM_BYTES),
+            self.dir,
+            format_megabytes(self.available_bytes),
+            format_megabytes(self.shortfall_bytes())
+        )
+    }
+}
+
+impl std::error::Error for InsufficientSpace {}
+
+fn check_space(dir: &Path, needed: u64, available: u64, action: &str) -> Result<()> {
+    if available >= needed.saturating_add(HEADROOM_BYTES) {
+        return Ok(());
+    }
+    Err(InsufficientSpace {
+        action: action.to_string(),
+        dir: dir.to_path_buf(),
+        needed_bytes: needed,
+        available_bytes: available,
+    }
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    #[test]
+    fn shortfall_includes_headroom() {
+        let dir = Path::new("/models");
+        assert!(check_space(dir, 100 * MB, 100 * MB + HEADROOM_BYTES, "download").is_ok());
+
+        let err = check_space(dir, 1000 * MB, 500 * MB, "download Large").unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("download Large"));
+        assert!(message.contains("needs 1000.0 MB plus 64.0 MB headroom"));
+        assert!(message.contains("only 500.0 MB is free"));
+        assert!(message.contains("Free at least 564.0 MB"));
+        let shortfall = err.downcast_ref::<InsufficientSpace>().unwrap();
+        assert_eq!(shortfall.shortfall_bytes(), 564 * MB);
+    }
+
+    #[test]
+    fn missing_directories_are_measured_on_their_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("not").join("created").join("yet");
+        assert!(available_bytes(&missing).is_some());
+    }
+}
@@ -0,0 +1,430 @@
+//! Disk usage, removal and clean-up of installed models.
+//!
+//! Models are listed with their size and the last time a Whisper context was
+//! created or reused for them. The active and fallback models cannot be
+//! removed. Partial `*.downloading` and `*.importing` files left by interrupted
+//! downloads and imports are removed once they have not changed for an hour,
+//! so a transfer still in progress is never cut short.
+
+use super::{catalogue, get_models_dir, integrity, is_supported_model_path};
+use crate::{get_data_dir, persistence, Config};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{info, warn};
+
+pub const USAGE_SCHEMA_VERSION: u32 = 1;
+/// Partial files untouched for this long are treated as abandoned.
+const STALE_PARTIAL_AFTER: Duration = Duration::from_secs(60 * 60);
+/// Last-used times are persisted at most this often per model.
+const USAGE_WRITE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const PARTIAL_SUFFIXES: &[&str] = &[".downloading", ".downloading.json", ".importing"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct UsageRecord {
+    schema_version: u32,
+    #[serde(default)]
+    last_used: BTreeMap<PathBuf, DateTime<Local>>,
+}
+
+impl Default for UsageRecord {
+    fn default() -> Self {
+        Self {
+            schema_version: USAGE_SCHEMA_VERSION,
+            last_used: BTreeMap::new(),
+        }
+    }
+}
+
+fn usage_path() -> PathBuf {
+    get_data_dir().join("model_usage.json")
+}
+
+fn load_usage() -> UsageRecord {
+    let path = usage_path();
+    if !path.exists() {
+        return UsageRecord::default();
+    }
+    persistence::read_json_private(&path).unwrap_or_else(|err| {
+        warn!("Ignoring unreadable model usage record {:?}: {}", path, err);
+        UsageRecord::default()
+    })
+}
+
+/// Note that `path` was just used for transcription.
+pub fn record_model_used(path: &Path) {
+    static LAST_WRITTEN: OnceLock<Mutex<HashMap<PathBuf, Instant>>> = OnceLock::new();
+    let Ok(mut written) = LAST_WRITTEN.get_or_init(Default::default).lock() else {
+        return;
+    };
+    if written
+        .get(path)
+        .is_some_and(|at| at.elapsed() < USAGE_WRITE_INTERVAL)
+    {
+        return;
+    }
+    written.insert(path.to_path_buf(), Instant::now());
+
+    let mut usage = load_usage();
+    usage.last_used.insert(path.to_path_buf(), Local::now());
+    usage.last_used.retain(|known, _| known.is_file());
+    if let Err(err) = persistence::write_json_private(&usage_path(), &usage) {
+        warn!("Failed to record model usage: {}", err);
+    }
+}
+
+/// Why an installed model cannot be removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelRole {
+    Active,
+    Fallback,
+    /// The base model, replaced while the active profile sets its own
+    Default,
+    /// Routed to for a language in `language_models`
+    Language,
+    /// Set by a dictation profile that is not active
+    Profile,
+}
+
+impl std::fmt::Display for ModelRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ModelRole::Active => "active model",
+            ModelRole::Fallback => "fallback model",
+            ModelRole::Default => "default model",
+            ModelRole::Language => "model for a dictation language",
+            ModelRole::Profile => "model of a dictation profile",
+        })
+    }
+}
+
+/// An installed model file with its disk usage.
+#[derive(Debug, Clone)]
+pub struct InstalledModel {
+    pub path: PathBuf,
+    /// Catalogue name, when the file is a catalogue download
+    pub name: Option<&'static str>,
+    pub size_bytes: u64,
+    pub last_used: Option<DateTime<Local>>,
+    pub role: Option<ModelRole>,
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// What `path` is used for, judged by the configuration with the active profile applied.
+fn role_of(path: &Path, config: &Config) -> Option<ModelRole> {
+    let effective = config.with_active_profile();
+    if same_file(path, &effective.model_path) {
+        Some(ModelRole::Active)
+    } else if effective
+        .fallback_model_path
+        .as_deref()
+        .is_some_and(|fallback| same_file(path, fallback))
+    {
+        Some(ModelRole::Fallback)
+    } else if same_file(path, &config.model_path) {
+        Some(ModelRole::Default)
+    } else if effective
+        .language_models
+        .values()
+        .any(|model| same_file(path, model))
+    {
+        Some(ModelRole::Language)
+    } else if config
+        .profiles
+        .values()
+        .filter_map(|profile| profile.model_path.as_deref())
+        .any(|model| same_file(path, model))
+    {
+        Some(ModelRole::Profile)
+    } else {
+        None
+    }
+}
+
+fn is_partial_name(name: &str) -> bool {
+    PARTIAL_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// Installed models, largest first.
+pub fn installed_models(config: &Config) -> Vec<InstalledModel> {
+    installed_in(&get_models_dir(), config, &load_usage())
+}
+
+fn installed_in(dir: &Path, config: &Config, usage: &UsageRecord) -> Vec<InstalledModel> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut models: Vec<InstalledModel> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_supported_model_path(path))
+        .map(|path| {
+            let file_name = path.file_name().and_then(|name| name.to_str());
+            InstalledModel {
+                name: catalogue()
+                    .iter()
+                    .find(|model| Some(model.filename) == file_name)
+                    .map(|model| model.name),
+                size_bytes: std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+                last_used: usage.last_used.get(&path).copied(),
+                role: role_of(&path, config),
+                path,
+            }
+        })
+        .collect();
+    models.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes).then(a.path.cmp(&b.path)));
+    models
+}
+
+/// Delete an installed model; returns the bytes freed.
+///
+/// Refuses models the configuration or any profile uses and anything outside the models directory.
+pub fn delete_model(path: &Path, config: &Config) -> Result<u64> {
+    let freed = delete_in(&get_models_dir(), path, config)?;
+    let mut usage = load_usage();
+    if usage.last_used.remove(path).is_some() {
+        if let Err(err) = persistence::write_json_private(&usage_path(), &usage) {
+            warn!("Failed to update model usage record: {}", err);
+        }
+    }
+    integrity::forget(path);
+    Ok(freed)
+}
+
+fn delete_in(dir: &Path, path: &Path, config: &Config) -> Result<u64> {
+    if let Some(role) = role_of(path, config) {
+        anyhow::bail!(
+            "{:?} is the {}; choose another model before deleting it",
+            path,
+            role
+        );
+    }
+    let parent = path
+        .parent()
+        .and_then(|parent| std::fs::canonicalize(parent).ok());
+    if parent.is_none() || parent != std::fs::canonicalize(dir).ok() {
+        anyhow::bail!("{:?} is not in the models directory", path);
+    }
+    if !path.is_file() || !is_supported_model_path(path) {
+        anyhow::bail!("{:?} is not an installed model file", path);
+    }
+
+    let size = std::fs::metadata(path)?.len();
+    std::fs::remove_file(path).with_context(|| format!("Failed to delete {:?}", path))?;
+    info!("Deleted model {:?} ({} bytes)", path, size);
+    Ok(size)
+}
+
+/// Leftover partial files in the models directory and their total size.
+pub fn stale_partial_files() -> (Vec<PathBuf>, u64) {
+    stale_partials_in(&get_models_dir(), SystemTime::now())
+}
+
+fn stale_partials_in(dir: &Path, now: SystemTime) -> (Vec<PathBuf>, u64) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (Vec::new(), 0);
+    };
+    let mut total = 0;
+    let mut stale = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let idle = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .unwrap_or_default();
+        if metadata.is_file() && is_partial_name(name) && idle >= STALE_PARTIAL_AFTER {
+            total += metadata.len();
+            stale.push(path);
+        }
+    }
+    stale.sort();
+    (stale, total)
+}
+
+/// Remove stale partial files; returns how many were removed and the bytes freed.
+pub fn clean_partial_files() -> (usize, u64) {
+    let (stale, _) = stale_partial_files();
+    let mut removed = 0;
+    let mut freed = 0;
+    for path in stale {
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                removed += 1;
+                freed += size;
+            }
+            Err(err) => warn!("Failed to remove partial file {:?}: {}", path, err),
+        }
+    }
+    if removed > 0 {
+        info!("Removed {} partial model file(s), {} bytes", removed, freed);
+    }
+    (removed, freed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DictationProfile;
+
+    fn write(path: &Path, bytes: usize) {
+        std::fs::write(path, vec![0u8; bytes]).unwrap();
+    }
+
+    #[test]
+    fn installed_models_report_size_usage_and_role() {
+        let dir = tempfile::tempdir().unwrap();
+        let active = dir.path().join("ggml-base.en.bin");
+        let fallback = dir.path().join("ggml-tiny.en.bin");
+        let custom = dir.path().join("custom.gguf");
+        write(&active, 30);
+        write(&fallback, 10);
+        write(&custom, 20);
+        write(&dir.path().join("ggml-small.en.bin.downloading"), 50);
+
+        let config = Config {
+            model_path: active.clone(),
+            fallback_model_path: Some(fallback.clone()),
+            ..Config::default()
+        };
+        let used = Local::now();
+        let mut usage = UsageRecord::default();
+        usage.last_used.insert(custom.clone(), used);
+
+        let models = installed_in(dir.path(), &config, &usage);
+        let paths: Vec<&Path> = models.iter().map(|m| m.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            [active.as_path(), custom.as_path(), fallback.as_path()]
+        );
+        assert_eq!(models[0].role, Some(ModelRole::Active));
+        assert_eq!(models[0].name, Some("Base (English)"));
+        assert_eq!(models[1].last_used, Some(used));
+        assert_eq!(models[1].name, None);
+        assert_eq!(models[2].role, Some(ModelRole::Fallback));
+    }
+
+    #[test]
+    fn active_fallback_and_outside_files_are_not_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let active = dir.path().join("active.bin");
+        let fallback = dir.path().join("fallback.bin");
+        let spare = dir.path().join("spare.bin");
+        let foreign = outside.path().join("foreign.bin");
+        for path in [&active, &fallback, &spare, &foreign] {
+            write(path, 8);
+        }
+        let config = Config {
+            model_path: active.clone(),
+            fallback_model_path: Some(fallback.clone()),
+            ..Config::default()
+        };
+
+        let err = delete_in(dir.path(), &active, &config).unwrap_err();
+        assert!(err.to_string().contains("active model"));
+        assert!(delete_in(dir.path(), &fallback, &config).is_err());
+        assert!(delete_in(dir.path(), &foreign, &config).is_err());
+        assert!(active.exists() && fallback.exists() && foreign.exists());
+
+        assert_eq!(delete_in(dir.path(), &spare, &config).unwrap(), 8);
+        assert!(!spare.exists());
+    }
+
+    #[test]
+    fn language_routed_models_are_not_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let english = dir.path().join("ggml-base.en.bin");
+        write(&english, 8);
+        let config = Config {
+            model_path: dir.path().join("ggml-small.bin"),
+            language_models: BTreeMap::from([("en".to_string(), english.clone())]),
+            ..Config::default()
+        };
+
+        let err = delete_in(dir.path(), &english, &config).unwrap_err();
+        assert!(err.to_string().contains("dictation language"));
+        assert!(english.exists());
+    }
+
+    #[test]
+    fn profile_models_are_not_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("base.bin");
+        let code = dir.path().join("code.bin");
+        let email = dir.path().join("email.bin");
+        for path in [&base, &code, &email] {
+            write(path, 8);
+        }
+        let profile = |model: &Path| DictationProfile {
+            model_path: Some(model.to_path_buf()),
+            ..DictationProfile::default()
+        };
+        let config = Config {
+            model_path: base.clone(),
+            profiles: BTreeMap::from([
+                ("code".to_string(), profile(&code)),
+                ("email".to_string(), profile(&email)),
+            ]),
+            active_profile: Some("code".to_string()),
+            ..Config::default()
+        };
+
+        assert_eq!(role_of(&code, &config), Some(ModelRole::Active));
+        assert_eq!(role_of(&base, &config), Some(ModelRole::Default));
+        assert_eq!(role_of(&email, &config), Some(ModelRole::Profile));
+        for path in [&base, &code, &email] {
+            assert!(delete_in(dir.path(), path, &config).is_err());
+            assert!(path.exists());
+        }
+    }
+
+    #[test]
+    fn only_idle_partial_files_are_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "ggml-small.en.bin.downloading",
+            "ggml-small.en.bin.downloading.json",
+            "custom.importing",
+            "ggml-base.en.bin",
+        ] {
+            write(&dir.path().join(name), 4);
+        }
+
+        let (fresh, _) = stale_partials_in(dir.path(), SystemTime::now());
+        assert!(fresh.is_empty());
+
+        let later = SystemTime::now() + STALE_PARTIAL_AFTER;
+        let (stale, total) = stale_partials_in(dir.path(), later);
+        let names: Vec<_> = stale
+            .iter()
+            .map(|path| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "custom.importing",
+                "ggml-small.en.bin.downloading",
+                "ggml-small.en.bin.downloading.json"
+            ]
+        );
+        assert_eq!(total, 12);
+    }
+}
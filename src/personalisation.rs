@@ -3,10 +3,11 @@
 //! The persisted document deliberately contains only explicit phrase replacements. MorpheOS Voice does
 //! not observe edits in other applications and does not learn entries automatically.
 
-use crate::{get_data_dir, persistence};
+use crate::{get_data_dir, persistence, Config};
 use anyhow::{Context, Result};
 use regex::{Regex, RegexBuilder};
 use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
 use std::collections::HashSet;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
@@ -116,6 +117,33 @@ impl Personalisation {
         &self.document.dictionary
     }
 
+    /// The entries whose spoken phrase is listed, for a profile's dictionary subset.
+    pub fn subset(&self, spoken: &[String]) -> Self {
+        let dictionary = self
+            .document
+            .dictionary
+            .iter()
+            .filter(|entry| {
+                spoken
+                    .iter()
+                    .any(|phrase| phrase.trim().to_lowercase() == entry.spoken.to_lowercase())
+            })
+            .cloned()
+            .collect();
+        Self::from_dictionary(dictionary).expect("a subset of a valid dictionary is valid")
+    }
+
+    /// The dictionary used by `config`'s active profile.
+    pub fn for_profile(&self, config: &Config) -> Cow<'_, Self> {
+        match config
+            .dictation_profile()
+            .and_then(|profile| profile.dictionary.as_deref())
+        {
+            Some(spoken) => Cow::Owned(self.subset(spoken)),
+            None => Cow::Borrowed(self),
+        }
+    }
+
     /// Replace literal phrases in one pass. Matches are found only in the original transcript, so
     /// replacement text is never interpreted as another dictionary entry.
     pub fn apply_dictionary(&self, text: &str) -> String {
@@ -361,6 +389,7 @@ fn is_word_character(character: char) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::DictationProfile;
 
     fn entry(spoken: &str, written: &str) -> DictionaryEntry {
         DictionaryEntry {
@@ -371,6 +400,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn profiles_use_their_dictionary_subset() {
+        let personalisation = Personalisation::from_dictionary(vec![
+            entry("kube", "Kubernetes"),
+            entry("morph os", "MorpheOS"),
+        ])
+        .unwrap();
+        let mut config = Config::default();
+        config.profiles.insert(
+            "code".to_string(),
+            DictationProfile {
+                dictionary: Some(vec!["Morph OS".to_string()]),
+                ..DictationProfile::default()
+            },
+        );
+        assert!(matches!(
+            personalisation.for_profile(&config),
+            Cow::Borrowed(_)
+        ));
+
+        config.active_profile = Some("code".to_string());
+        let subset = personalisation.for_profile(&config);
+        assert_eq!(subset.dictionary(), &[entry("morph os", "MorpheOS")]);
+        assert_eq!(subset.apply_dictionary("morph os kube"), "MorpheOS kube");
+    }
+
     #[test]
     fn document_round_trip_is_versioned_and_validated() {
         let original =
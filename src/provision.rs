@@ -0,0 +1,475 @@
+//! `oswispa setup`: non-interactive provisioning for managed machines.
+//!
+//! Chooses a model (a catalogue id, or the one the first-run wizard would
+//! recommend from the hardware probe), sets the language, shortcut and
+//! backend, downloads the model and saves `config.json`. Without `--yes` the
+//! plan is only reported. A JSON report always goes to stdout, logs go to
+//! stderr, and the exit code says what went wrong:
+//!
+//! | code | meaning |
+//! |------|---------|
+//! | 0 | applied, or planned without `--yes` |
+//! | 1 | unexpected failure |
+//! | 2 | invalid command line |
+//! | 3 | unknown model or invalid setting value |
+//! | 4 | not enough disk space for the model |
+//! | 5 | model download or verification failed |
+//! | 6 | configuration could not be read or written |
+
+use crate::models::{self, InsufficientSpace, ModelInfo};
+use crate::runtime::{format_hotkey, load_config};
+use crate::setup::{self, HardwareReport};
+use crate::{get_config_dir, save_config, Config, HotkeyConfig, TranscriptionBackend};
+use serde::Serialize;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+const USAGE: &str = "Usage: oswispa setup [--model ID|recommended] [--language CODE] [--hotkey KEYS] [--backend local|remote] [--yes]";
+pub const SETUP_SCHEMA_VERSION: u32 = 1;
+
+/// Trigger keys the global shortcut listeners understand.
+const TRIGGER_KEYS: &[&str] = &[
+    "space",
+    "tab",
+    "enter",
+    "backspace",
+    "capslock",
+    "grave",
+    "f1",
+    "f2",
+    "f3",
+    "f4",
+    "f5",
+    "f6",
+    "f7",
+    "f8",
+    "f9",
+    "f10",
+    "f11",
+    "f12",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum FailureKind {
+    Unexpected,
+    Usage,
+    InvalidValue,
+    DiskSpace,
+    Download,
+    Config,
+}
+
+impl FailureKind {
+    fn exit_code(self) -> i32 {
+        match self {
+            FailureKind::Unexpected => 1,
+            FailureKind::Usage => 2,
+            FailureKind::InvalidValue => 3,
+            FailureKind::DiskSpace => 4,
+            FailureKind::Download => 5,
+            FailureKind::Config => 6,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Failure {
+    kind: FailureKind,
+    message: String,
+}
+
+fn fail(kind: FailureKind, message: impl Into<String>) -> Failure {
+    Failure {
+        kind,
+        message: message.into(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ModelChoice {
+    Recommended,
+    Id(String),
+}
+
+#[derive(Debug, Default)]
+struct Options {
+    model: Option<ModelChoice>,
+    language: Option<String>,
+    hotkey: Option<HotkeyConfig>,
+    backend: Option<TranscriptionBackend>,
+    yes: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ModelReport {
+    id: &'static str,
+    name: &'static str,
+    path: PathBuf,
+    size_mb: u32,
+    recommended: bool,
+    reason: String,
+    already_installed: bool,
+    downloaded: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ConfigReport {
+    path: PathBuf,
+    model_path: PathBuf,
+    language: String,
+    hotkey: String,
+    backend: TranscriptionBackend,
+}
+
+#[derive(Debug, Serialize)]
+struct SetupReport {
+    schema_version: u32,
+    /// "applied", "planned" (no `--yes`) or "failed"
+    status: &'static str,
+    exit_code: i32,
+    hardware: Option<HardwareReport>,
+    model: Option<ModelReport>,
+    config: Option<ConfigReport>,
+    warnings: Vec<String>,
+    error: Option<Failure>,
+}
+
+fn parse_hotkey(spec: &str) -> Result<HotkeyConfig, Failure> {
+    let mut hotkey = HotkeyConfig {
+        ctrl: false,
+        alt: false,
+        shift: false,
+        super_key: false,
+        trigger_key: None,
+    };
+    for part in spec.split('+').map(|part| part.trim().to_ascii_lowercase()) {
+        match part.as_str() {
+            "ctrl" | "control" => hotkey.ctrl = true,
+            "alt" => hotkey.alt = true,
+            "shift" => hotkey.shift = true,
+            "super" | "meta" | "win" | "cmd" => hotkey.super_key = true,
+            key if hotkey.trigger_key.is_none()
+                && (TRIGGER_KEYS.contains(&key)
+                    || (key.len() == 1 && key.chars().all(|c| c.is_ascii_alphanumeric()))) =>
+            {
+                hotkey.trigger_key = Some(key.to_string())
+            }
+            other => {
+                return Err(fail(
+                    FailureKind::InvalidValue,
+                    format!("Unsupported shortcut key {:?} in {:?}", other, spec),
+                ))
+            }
+        }
+    }
+    if !(hotkey.ctrl || hotkey.alt || hotkey.shift || hotkey.super_key) {
+        return Err(fail(
+            FailureKind::InvalidValue,
+            format!("Shortcut {:?} needs at least one modifier", spec),
+        ));
+    }
+    Ok(hotkey)
+}
+
+fn parse_language(code: &str) -> Result<String, Failure> {
+    let code = code.trim().to_ascii_lowercase();
+    // Whisper also accepts full names ("english"); the configuration stores codes only.
+    let valid = code == "auto"
+        || ((2..=3).contains(&code.len())
+            && code.chars().all(|c| c.is_ascii_lowercase())
+            && whisper_rs::get_lang_id(&code).is_some());
+    if valid {
+        Ok(code)
+    } else {
+        Err(fail(
+            FailureKind::InvalidValue,
+            format!(
+                "Invalid language {:?}; use a Whisper language code such as \"en\" or \"auto\"",
+                code
+            ),
+        ))
+    }
+}
+
+fn parse_arguments(args: &[String]) -> Result<Options, Failure> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(argument) = args.next() {
+        let mut value = || {
+            args.next().ok_or_else(|| {
+                fail(
+                    FailureKind::Usage,
+                    format!("{} needs a value\n{}", argument, USAGE),
+                )
+            })
+        };
+        match argument.as_str() {
+            "--model" => {
+                options.model = Some(match value()?.as_str() {
+                    "recommended" => ModelChoice::Recommended,
+                    id => ModelChoice::Id(id.to_string()),
+                })
+            }
+            "--language" => options.language = Some(parse_language(value()?)?),
+            "--hotkey" => options.hotkey = Some(parse_hotkey(value()?)?),
+            "--backend" => {
+                options.backend = Some(match value()?.as_str() {
+                    "local" => TranscriptionBackend::Local,
+                    "remote" => TranscriptionBackend::Remote,
+                    other => {
+                        return Err(fail(
+                            FailureKind::InvalidValue,
+                            format!("Unknown backend {:?}; use local or remote", other),
+                        ))
+                    }
+                })
+            }
+            "--yes" | "-y" => options.yes = true,
+            other => {
+                return Err(fail(
+                    FailureKind::Usage,
+                    format!("Unexpected argument: {}\n{}", other, USAGE),
+                ))
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// Catalogue model for `id`: a file name such as `ggml-base.en.bin`, or `base.en`.
+fn find_model(id: &str) -> Result<&'static ModelInfo, Failure> {
+    let wanted = id.trim().to_ascii_lowercase();
+    models::catalogue()
+        .iter()
+        .find(|model| {
+            let filename = model.filename.to_ascii_lowercase();
+            filename == wanted || filename == format!("ggml-{}.bin", wanted)
+        })
+        .ok_or_else(|| {
+            let known: Vec<&str> = models::catalogue()
+                .iter()
+                .map(|model| model.filename)
+                .collect();
+            fail(
+                FailureKind::InvalidValue,
+                format!("Unknown model {:?}; available: {}", id, known.join(", ")),
+            )
+        })
+}
+
+fn download_failure(err: anyhow::Error) -> Failure {
+    let kind = if err.chain().any(|cause| cause.is::<InsufficientSpace>()) {
+        FailureKind::DiskSpace
+    } else {
+        FailureKind::Download
+    };
+    fail(kind, format!("{:#}", err))
+}
+
+/// Run `oswispa setup`, print the JSON report and return the process exit code.
+pub(crate) fn run_setup(args: &[String]) -> i32 {
+    let mut report = SetupReport {
+        schema_version: SETUP_SCHEMA_VERSION,
+        status: "failed",
+        exit_code: 0,
+        hardware: None,
+        model: None,
+        config: None,
+        warnings: Vec::new(),
+        error: None,
+    };
+    match provision(args, &mut report) {
+        Ok(()) => {}
+        Err(failure) => {
+            warn!("Setup failed: {}", failure.message);
+            report.status = "failed";
+            report.exit_code = failure.kind.exit_code();
+            report.error = Some(failure);
+        }
+    }
+
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => println!("{}", json),
+        Err(err) => {
+            eprintln!("Failed to encode setup report: {}", err);
+            return FailureKind::Unexpected.exit_code();
+        }
+    }
+    report.exit_code
+}
+
+fn provision(args: &[String], report: &mut SetupReport) -> Result<(), Failure> {
+    let options = parse_arguments(args)?;
+    let mut config: Config = load_config().map_err(|err| {
+        fail(
+            FailureKind::Config,
+            format!("Failed to read configuration: {:#}", err),
+        )
+    })?;
+    if let Err(err) = models::configure_catalogue(&config.model_catalogue) {
+        report
+            .warnings
+            .push(format!("Using the built-in model list: {:#}", err));
+    }
+
+    let (hardware, recommended, reason) = setup::recommend_for_this_machine();
+    report.hardware = Some(hardware);
+
+    if let Some(language) = options.language {
+        config.language = language;
+    }
+    if let Some(hotkey) = options.hotkey {
+        config.hotkey = hotkey;
+    }
+    if let Some(backend) = options.backend {
+        config.backend = backend;
+    }
+
+    // A remote-only setup needs no local model unless one is asked for.
+    let choice = match (options.model, &config.backend) {
+        (Some(choice), _) => Some(choice),
+        (None, TranscriptionBackend::Local) => Some(ModelChoice::Recommended),
+        (None, TranscriptionBackend::Remote) => None,
+    };
+    let model = match choice {
+        Some(ModelChoice::Recommended) => Some(recommended),
+        Some(ModelChoice::Id(id)) => Some(find_model(&id)?),
+        None => None,
+    };
+    if let Some(model) = model {
+        config.model_path = models::get_model_path(model);
+        let is_recommended = model.filename == recommended.filename;
+        report.model = Some(ModelReport {
+            id: model.filename,
+            name: model.name,
+            path: config.model_path.clone(),
+            size_mb: model.size_mb,
+            recommended: is_recommended,
+            reason: if is_recommended {
+                reason
+            } else {
+                String::new()
+            },
+            already_installed: models::is_model_installed(model),
+            downloaded: false,
+        });
+    }
+
+    report.warnings.extend(models::language_warning(&config));
+    report.config = Some(ConfigReport {
+        path: get_config_dir().join("config.json"),
+        model_path: config.model_path.clone(),
+        language: config.language.clone(),
+        hotkey: format_hotkey(&config.hotkey),
+        backend: config.backend.clone(),
+    });
+
+    if !options.yes {
+        report.status = "planned";
+        return Ok(());
+    }
+
+    if let (Some(model), Some(model_report)) = (model, report.model.as_mut()) {
+        if !model_report.already_installed {
+            models::check_download_space(model).map_err(download_failure)?;
+            info!("Downloading {}", model.name);
+            let path =
+                models::download_model_blocking(model, |_, _| {}).map_err(download_failure)?;
+            model_report.path = path.clone();
+            model_report.downloaded = true;
+            config.model_path = path;
+        }
+    }
+
+    save_config(&config).map_err(|err| {
+        fail(
+            FailureKind::Config,
+            format!("Failed to save configuration: {:#}", err),
+        )
+    })?;
+    report.status = "applied";
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn arguments_parse_into_settings() {
+        let options = parse_arguments(&args(&[
+            "--model",
+            "small.en",
+            "--language",
+            "DE",
+            "--hotkey",
+            "ctrl+alt+f8",
+            "--backend",
+            "remote",
+            "--yes",
+        ]))
+        .unwrap();
+        assert_eq!(options.model, Some(ModelChoice::Id("small.en".to_string())));
+        assert_eq!(options.language.as_deref(), Some("de"));
+        let hotkey = options.hotkey.unwrap();
+        assert!(hotkey.ctrl && hotkey.alt && !hotkey.shift && !hotkey.super_key);
+        assert_eq!(hotkey.trigger_key.as_deref(), Some("f8"));
+        assert_eq!(options.backend, Some(TranscriptionBackend::Remote));
+        assert!(options.yes);
+
+        let recommended = parse_arguments(&args(&["--model", "recommended"])).unwrap();
+        assert_eq!(recommended.model, Some(ModelChoice::Recommended));
+        assert!(!recommended.yes);
+    }
+
+    #[test]
+    fn bad_arguments_map_to_exit_codes() {
+        let code = |values: &[&str]| parse_arguments(&args(values)).unwrap_err().kind.exit_code();
+        assert_eq!(code(&["--model"]), 2);
+        assert_eq!(code(&["--force"]), 2);
+        assert_eq!(code(&["--backend", "cloud"]), 3);
+        assert_eq!(code(&["--language", "english"]), 3);
+        assert_eq!(code(&["--language", "zz"]), 3);
+        assert_eq!(code(&["--hotkey", "space"]), 3);
+        assert_eq!(code(&["--hotkey", "ctrl+f8+f9"]), 3);
+    }
+
+    #[test]
+    fn models_are_found_by_file_name_or_short_id() {
+        assert_eq!(
+            find_model("ggml-base.en.bin").unwrap().filename,
+            "ggml-base.en.bin"
+        );
+        assert_eq!(
+            find_model("Small.en").unwrap().filename,
+            "ggml-small.en.bin"
+        );
+        let unknown = find_model("huge").unwrap_err();
+        assert_eq!(unknown.kind.exit_code(), 3);
+        assert!(unknown.message.contains("ggml-tiny.en.bin"));
+    }
+
+    #[test]
+    fn disk_space_failures_are_told_apart_from_download_errors() {
+        let space: anyhow::Error = InsufficientSpace {
+            action: "download Large".to_string(),
+            dir: PathBuf::from("/models"),
+            needed_bytes: 10,
+            available_bytes: 1,
+        }
+        .into();
+        assert_eq!(
+            download_failure(space.context("Model download failed")).kind,
+            FailureKind::DiskSpace
+        );
+        assert_eq!(
+            download_failure(anyhow::anyhow!("connection reset")).kind,
+            FailureKind::Download
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Decode uploaded audio into the 16 kHz mono PCM Whisper expects.
+
+use crate::audio::conversion::MonoPcm16Resampler;
+use anyhow::Result;
+use std::io::Cursor;
+use std::path::Path;
+
+pub(super) const SAMPLE_RATE: u32 = 16_000;
+
+/// The upload decodes to more audio than the caller accepts.
+///
+/// Compressed uploads can expand far beyond their size, so decoders stop
+/// counting frames at the limit instead of trusting the stream header.
+#[derive(Debug)]
+pub(crate) struct AudioTooLong;
+
+impl std::fmt::Display for AudioTooLong {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Audio is longer than the accepted duration")
+    }
+}
+
+impl std::error::Error for AudioTooLong {}
+
+/// Decode a WAV or FLAC upload, downmixing and resampling to 16 kHz mono.
+///
+/// With `max_seconds`, longer audio fails with [`AudioTooLong`] before it is held in memory.
+pub(crate) fn decode_upload(bytes: &[u8], max_seconds: Option<u32>) -> Result<Vec<i16>> {
+    let (samples, channels, sample_rate) = if bytes.starts_with(b"RIFF") {
+        decode_wav(bytes, max_seconds)?
+    } else if bytes.starts_with(b"fLaC") {
+        decode_flac(bytes, max_seconds)?
+    } else {
+        anyhow::bail!("Unsupported audio format; upload WAV or FLAC");
+    };
+
+    let mut resampler = MonoPcm16Resampler::new(sample_rate, SAMPLE_RATE)
+        .map_err(|err| anyhow::anyhow!("Invalid audio: {}", err))?;
+    let mut output =
+        Vec::with_capacity(samples.len() * SAMPLE_RATE as usize / sample_rate.max(1) as usize);
+    resampler
+        .process_interleaved(&samples, channels, |sample| output.push(sample))
+        .map_err(|err| anyhow::anyhow!("Invalid audio: {}", err))?;
+
+    if output.is_empty() {
+        anyhow::bail!("Audio contains no samples");
+    }
+    Ok(output)
+}
+
+/// Write decoded PCM where the local transcription pipeline can read it.
+pub(crate) fn write_wav(path: &Path, samples: &[i16]) -> Result<()> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for &sample in samples {
+        writer.write_sample(sample)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+/// Interleaved samples allowed for `max_seconds` of audio, or `None` without a limit.
+fn sample_limit(max_seconds: Option<u32>, sample_rate: u32, channels: usize) -> Option<u64> {
+    max_seconds.map(|seconds| seconds as u64 * sample_rate as u64 * channels as u64)
+}
+
+/// Collect decoded samples, failing as soon as there are more than `limit`.
+fn collect_limited<E>(
+    samples: impl Iterator<Item = std::result::Result<f32, E>>,
+    limit: Option<u64>,
+) -> Result<Vec<f32>>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let mut collected = Vec::new();
+    for sample in samples {
+        if limit.is_some_and(|limit| collected.len() as u64 >= limit) {
+            return Err(AudioTooLong.into());
+        }
+        collected.push(sample?);
+    }
+    Ok(collected)
+}
+
+fn decode_wav(bytes: &[u8], max_seconds: Option<u32>) -> Result<(Vec<f32>, usize, u32)> {
+    let mut reader = hound::WavReader::new(Cursor::new(bytes))?;
+    let spec = reader.spec();
+    let limit = sample_limit(max_seconds, spec.sample_rate, spec.channels as usize);
+    if limit.is_some_and(|limit| reader.len() as u64 > limit) {
+        return Err(AudioTooLong.into());
+    }
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Int => {
+            let max_val = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            collect_limited(
+                reader
+                    .samples::<i32>()
+                    .map(|sample| sample.map(|value| value as f32 / max_val)),
+                limit,
+            )?
+        }
+        hound::SampleFormat::Float => collect_limited(reader.samples::<f32>(), limit)?,
+    };
+    Ok((samples, spec.channels as usize, spec.sample_rate))
+}
+
+fn decode_flac(bytes: &[u8], max_seconds: Option<u32>) -> Result<(Vec<f32>, usize, u32)> {
+    let mut reader = claxon::FlacReader::new(Cursor::new(bytes))?;
+    let info = reader.streaminfo();
+    let limit = sample_limit(max_seconds, info.sample_rate, info.channels as usize);
+    // The header length is only a first check; the stream may carry more frames than it says.
+    if let (Some(limit), Some(frames)) = (limit, info.samples) {
+        if frames.saturating_mul(info.channels as u64) > limit {
+            return Err(AudioTooLong.into());
+        }
+    }
+    let max_val = (1i64 << (info.bits_per_sample - 1)) as f32;
+    let samples = collect_limited(
+        reader
+            .samples()
+            .map(|sample| sample.map(|value| value as f32 / max_val)),
+        limit,
+    )?;
+    Ok((samples, info.channels as usize, info.sample_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav_bytes(sample_rate: u32, channels: u16, frames: usize) -> Vec<u8> {
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+        for n in 0..frames * channels as usize {
+            writer.write_sample(((n % 100) as i16 - 50) * 100).unwrap();
+        }
+        writer.finalize().unwrap();
+        cursor.into_inner()
+    }
+
+    #[test]
+    fn stereo_48k_wav_becomes_16k_mono() {
+        let samples = decode_upload(&wav_bytes(48_000, 2, 48_000), None).unwrap();
+        assert_eq!(samples.len(), 16_000);
+    }
+
+    #[test]
+    fn native_rate_wav_keeps_its_length() {
+        let samples = decode_upload(&wav_bytes(SAMPLE_RATE, 1, 8_000), None).unwrap();
+        assert_eq!(samples.len(), 8_000);
+    }
+
+    #[test]
+    fn unknown_containers_are_rejected() {
+        assert!(decode_upload(b"OggS....", None).is_err());
+        assert!(decode_upload(&wav_bytes(SAMPLE_RATE, 1, 0), None).is_err());
+    }
+
+    #[test]
+    fn audio_beyond_the_limit_is_rejected() {
+        let two_seconds = wav_bytes(48_000, 2, 96_000);
+        assert_eq!(decode_upload(&two_seconds, Some(2)).unwrap().len(), 32_000);
+        let err = decode_upload(&two_seconds, Some(1)).unwrap_err();
+        assert!(err.is::<AudioTooLong>());
+    }
+
+    #[test]
+    fn samples_beyond_the_limit_stop_decoding() {
+        let samples = (0..10).map(|n| Ok::<f32, std::io::Error>(n as f32));
+        assert_eq!(
+            collect_limited(samples.clone(), Some(10)).unwrap().len(),
+            10
+        );
+        let err = collect_limited(samples, Some(9)).unwrap_err();
+        assert!(err.is::<AudioTooLong>());
+    }
+}
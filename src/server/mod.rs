@@ -0,0 +1,533 @@
+//! `oswispa serve`: an OpenAI-compatible transcription endpoint backed by local Whisper.
+//!
+//! Lets one machine with a large GPU serve thin clients whose remote backend
+//! points at `http(s)://host:port/v1/audio/transcriptions`. Requests need the
+//! bearer token stored in the owner-only secrets directory. Whisper runs one
+//! request at a time; `max_concurrent_requests` bounds how many may wait.
+
+pub(crate) mod audio;
+mod multipart;
+
+use crate::runtime::{
+    load_config, load_or_create_server_identity, load_or_create_server_token, server_token_path,
+};
+use crate::transcribe::{LocalTranscriber, Transcript};
+use crate::{discovery, models, Config};
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::{error, info, warn};
+
+const USAGE: &str = "Usage: oswispa serve [--listen ADDR:PORT] [--max-upload-mb N] [--max-audio-seconds N] [--max-concurrent N]";
+
+type TranscribeFn = dyn FnMut(&Path, &Config, Option<&str>) -> Result<Transcript> + Send;
+
+struct ServerState {
+    token: String,
+    server_id: String,
+    config: Config,
+    max_upload_bytes: usize,
+    max_audio_seconds: u32,
+    max_concurrent: usize,
+    in_flight: AtomicUsize,
+    transcribe: Mutex<Box<TranscribeFn>>,
+}
+
+/// Parse `serve` arguments, load the local model and answer requests until killed.
+pub(crate) fn run_serve(args: &[String]) -> Result<()> {
+    let mut config = load_config()?;
+    apply_arguments(&mut config, args)?;
+
+    models::validate_model_path(&config.model_path).with_context(|| {
+        format!(
+            "No usable local model at {:?}; run oswispa once to finish setup",
+            config.model_path
+        )
+    })?;
+
+    let (token, created) = load_or_create_server_token()?;
+    if created {
+        println!(
+            "Generated a new server token in {:?}; store it as each client's remote API key.",
+            server_token_path()
+        );
+    }
+
+    let server_id = discovery::server_id(&load_or_create_server_identity()?);
+    println!("Server ID: {}", server_id);
+
+    let server = tiny_http::Server::http(config.server.listen.as_str())
+        .map_err(|err| anyhow::anyhow!("Failed to listen on {}: {}", config.server.listen, err))?;
+    let mut _advertisement = None;
+    if let Ok(address) = config.server.listen.parse::<SocketAddr>() {
+        if !address.ip().is_loopback() {
+            warn!("Serving plain HTTP on a non-loopback address; use a trusted network or a TLS proxy");
+        }
+        if config.server.advertise {
+            let model = config
+                .model_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            match discovery::advertise(address, &model, &server_id) {
+                Ok(advertisement) => _advertisement = advertisement,
+                Err(err) => warn!("Failed to advertise the server on the LAN: {}", err),
+            }
+        }
+    }
+    info!(
+        "Transcription server listening on http://{}/v1/audio/transcriptions",
+        config.server.listen
+    );
+
+    let mut transcriber = LocalTranscriber::new(&config);
+    let state = Arc::new(ServerState {
+        token,
+        server_id,
+        max_upload_bytes: config.server.max_upload_mb as usize * 1024 * 1024,
+        max_audio_seconds: config.server.max_audio_seconds,
+        max_concurrent: config.server.max_concurrent_requests.max(1),
+        config,
+        in_flight: AtomicUsize::new(0),
+        transcribe: Mutex::new(Box::new(move |path, config, prompt| {
+            transcriber.transcribe_file(path, config, prompt)
+        })),
+    });
+
+    serve(&server, state);
+    Ok(())
+}
+
+fn apply_arguments(config: &mut Config, args: &[String]) -> Result<()> {
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("{} needs a value\n{}", flag, USAGE))
+        };
+        match flag.as_str() {
+            "--listen" => config.server.listen = value()?.clone(),
+            "--max-upload-mb" => {
+                config.server.max_upload_mb = value()?
+                    .parse()
+                    .with_context(|| format!("Invalid --max-upload-mb\n{}", USAGE))?
+            }
+            "--max-audio-seconds" => {
+                config.server.max_audio_seconds = value()?
+                    .parse()
+                    .with_context(|| format!("Invalid --max-audio-seconds\n{}", USAGE))?
+            }
+            "--max-concurrent" => {
+                config.server.max_concurrent_requests = value()?
+                    .parse()
+                    .with_context(|| format!("Invalid --max-concurrent\n{}", USAGE))?
+            }
+            other => anyhow::bail!("Unknown serve option: {}\n{}", other, USAGE),
+        }
+    }
+    Ok(())
+}
+
+/// Releases a concurrency slot when a request finishes, however it finishes.
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn serve(server: &tiny_http::Server, state: Arc<ServerState>) {
+    for request in server.incoming_requests() {
+        if state.in_flight.fetch_add(1, Ordering::SeqCst) >= state.max_concurrent {
+            state.in_flight.fetch_sub(1, Ordering::SeqCst);
+            let reply = Reply::error(429, "Too many concurrent requests; retry shortly");
+            let _ = request.respond(reply.into_response());
+            continue;
+        }
+
+        let state = state.clone();
+        std::thread::spawn(move || {
+            let _slot = InFlight(&state.in_flight);
+            let mut request = request;
+            let reply = handle(&mut request, &state);
+            if let Err(err) = request.respond(reply.into_response()) {
+                warn!("Failed to send transcription response: {}", err);
+            }
+        });
+    }
+}
+
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Reply {
+    fn json(status: u16, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: body.to_string(),
+        }
+    }
+
+    /// OpenAI-shaped error body so existing clients surface the message.
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(
+            status,
+            json!({"error": {"message": message, "type": "invalid_request_error"}}),
+        )
+    }
+
+    fn into_response(self) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+        let content_type =
+            tiny_http::Header::from_bytes(&b"Content-Type"[..], self.content_type.as_bytes())
+                .expect("static content type is a valid header");
+        tiny_http::Response::from_string(self.body)
+            .with_status_code(self.status)
+            .with_header(content_type)
+    }
+}
+
+fn header<'a>(request: &'a tiny_http::Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+fn authorized(request: &tiny_http::Request, token: &str) -> bool {
+    let Some(presented) = header(request, "Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+    else {
+        return false;
+    };
+
+    // Compare in constant time so the token cannot be guessed byte by byte.
+    presented.len() == token.len()
+        && presented
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |diff, (left, right)| diff | (left ^ right))
+            == 0
+}
+
+fn handle(request: &mut tiny_http::Request, state: &ServerState) -> Reply {
+    let path = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let translate = match (request.method(), path.as_str()) {
+        (tiny_http::Method::Get, "/health") => {
+            return Reply::json(200, json!({"status": "ok", "server_id": state.server_id}))
+        }
+        (tiny_http::Method::Post, "/v1/audio/transcriptions") => false,
+        (tiny_http::Method::Post, "/v1/audio/translations") => true,
+        _ => return Reply::error(404, "Not found"),
+    };
+
+    if !authorized(request, &state.token) {
+        return Reply::error(401, "Missing or invalid bearer token");
+    }
+
+    let Some(boundary) = header(request, "Content-Type").and_then(multipart::boundary) else {
+        return Reply::error(400, "Expected a multipart/form-data body");
+    };
+
+    if request
+        .body_length()
+        .map(|length| length > state.max_upload_bytes)
+        .unwrap_or(false)
+    {
+        return Reply::error(413, "Request body exceeds the upload limit");
+    }
+    let mut body = Vec::new();
+    if let Err(err) = request
+        .as_reader()
+        .take(state.max_upload_bytes as u64 + 1)
+        .read_to_end(&mut body)
+    {
+        return Reply::error(400, &format!("Failed to read request body: {}", err));
+    }
+    if body.len() > state.max_upload_bytes {
+        return Reply::error(413, "Request body exceeds the upload limit");
+    }
+
+    match transcribe_form(&body, &boundary, translate, state) {
+        Ok(reply) => reply,
+        Err(reply) => reply,
+    }
+}
+
+fn transcribe_form(
+    body: &[u8],
+    boundary: &str,
+    translate: bool,
+    state: &ServerState,
+) -> std::result::Result<Reply, Reply> {
+    let parts =
+        multipart::parse(body, boundary).map_err(|err| Reply::error(400, &err.to_string()))?;
+    let field = |name: &str| {
+        parts
+            .iter()
+            .find(|part| part.name == name)
+            .map(|part| String::from_utf8_lossy(part.data).trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let response_format = field("response_format").unwrap_or_else(|| "json".to_string());
+    if !matches!(response_format.as_str(), "json" | "verbose_json" | "text") {
+        return Err(Reply::error(
+            400,
+            "response_format must be json, verbose_json or text",
+        ));
+    }
+
+    let audio = parts
+        .iter()
+        .find(|part| part.name == "file")
+        .ok_or_else(|| Reply::error(400, "Missing file field"))?;
+    let samples =
+        audio::decode_upload(audio.data, Some(state.max_audio_seconds)).map_err(|err| {
+            if err.is::<audio::AudioTooLong>() {
+                Reply::error(
+                    413,
+                    &format!("Audio exceeds the {} second limit", state.max_audio_seconds),
+                )
+            } else {
+                Reply::error(415, &err.to_string())
+            }
+        })?;
+
+    let mut config = state.config.clone();
+    if let Some(language) = field("language") {
+        if language != "auto" && whisper_rs::get_lang_id(&language).is_none() {
+            return Err(Reply::error(
+                400,
+                "language is not a known Whisper language code",
+            ));
+        }
+        config.language = language;
+    }
+    config.translate_to_english = translate;
+    let prompt = field("prompt");
+
+    let wav = crate::audio::private_recording_temp_path()
+        .and_then(|path| audio::write_wav(&path, &samples).map(|_| path))
+        .map_err(|err| {
+            error!("Failed to stage upload: {}", err);
+            Reply::error(500, "Failed to stage audio")
+        })?;
+
+    let result = {
+        let mut transcribe = state
+            .transcribe
+            .lock()
+            .map_err(|_| Reply::error(500, "Transcriber is unavailable"))?;
+        transcribe(&wav, &config, prompt.as_deref())
+    };
+    let Transcript { text, language } = result.map_err(|err| {
+        error!("Server transcription failed: {}", err);
+        Reply::error(500, "Transcription failed")
+    })?;
+
+    let duration = samples.len() as f64 / audio::SAMPLE_RATE as f64;
+    Ok(match response_format.as_str() {
+        "text" => Reply {
+            status: 200,
+            content_type: "text/plain; charset=utf-8",
+            body: text,
+        },
+        "verbose_json" => Reply::json(
+            200,
+            json!({
+                "task": if translate { "translate" } else { "transcribe" },
+                "language": language.unwrap_or(config.language),
+                "duration": duration,
+                "text": text,
+                // The fallback chain returns joined text, so report one segment for the clip.
+                "segments": [{"id": 0, "start": 0.0, "end": duration, "text": text}],
+            }),
+        ),
+        _ => Reply::json(200, json!({"text": text})),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::blocking::{multipart as form, Client};
+
+    fn wav_upload() -> Vec<u8> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+        for n in 0..16_000 {
+            writer.write_sample((n % 200) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        cursor.into_inner()
+    }
+
+    fn start_test_server(max_upload_bytes: usize, max_audio_seconds: u32) -> String {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let address = server.server_addr().to_ip().unwrap();
+        let state = Arc::new(ServerState {
+            token: "secret-token".to_string(),
+            server_id: "ABCD-EF01".to_string(),
+            config: Config::default(),
+            max_upload_bytes,
+            max_audio_seconds,
+            max_concurrent: 2,
+            in_flight: AtomicUsize::new(0),
+            transcribe: Mutex::new(Box::new(|path, config, prompt| {
+                assert!(path.exists());
+                Ok(Transcript {
+                    text: format!(
+                        "heard {} {}",
+                        config.language,
+                        prompt.unwrap_or("no-prompt")
+                    ),
+                    language: Some(config.language.clone()),
+                })
+            })),
+        });
+        std::thread::spawn(move || serve(&server, state));
+        format!("http://{}", address)
+    }
+
+    fn upload_form(response_format: &str) -> form::Form {
+        upload_form_in("de", response_format)
+    }
+
+    fn upload_form_in(language: &str, response_format: &str) -> form::Form {
+        form::Form::new()
+            .text("model", "whisper-1")
+            .text("language", language.to_string())
+            .text("response_format", response_format.to_string())
+            .part(
+                "file",
+                form::Part::bytes(wav_upload())
+                    .file_name("audio.wav")
+                    .mime_str("audio/wav")
+                    .unwrap(),
+            )
+    }
+
+    #[test]
+    fn serves_openai_compatible_transcriptions() {
+        let base = start_test_server(1024 * 1024, 60);
+        let client = Client::new();
+
+        let response = client
+            .post(format!("{}/v1/audio/transcriptions", base))
+            .bearer_auth("secret-token")
+            .multipart(upload_form("json"))
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let body: serde_json::Value = response.json().unwrap();
+        assert_eq!(body["text"], "heard de no-prompt");
+
+        let verbose: serde_json::Value = client
+            .post(format!("{}/v1/audio/transcriptions", base))
+            .bearer_auth("secret-token")
+            .multipart(upload_form("verbose_json"))
+            .send()
+            .unwrap()
+            .json()
+            .unwrap();
+        assert_eq!(verbose["duration"], 1.0);
+        assert_eq!(verbose["task"], "transcribe");
+        assert_eq!(verbose["language"], "de");
+    }
+
+    #[test]
+    fn rejects_bad_tokens_and_oversized_bodies() {
+        let base = start_test_server(4 * 1024, 60);
+        let client = Client::new();
+
+        let unauthorized = client
+            .post(format!("{}/v1/audio/transcriptions", base))
+            .bearer_auth("wrong-token")
+            .multipart(upload_form("json"))
+            .send()
+            .unwrap();
+        assert_eq!(unauthorized.status(), 401);
+
+        let too_large = client
+            .post(format!("{}/v1/audio/transcriptions", base))
+            .bearer_auth("secret-token")
+            .multipart(upload_form("json"))
+            .send()
+            .unwrap();
+        assert_eq!(too_large.status(), 413);
+
+        let missing = client.get(format!("{}/v1/models", base)).send().unwrap();
+        assert_eq!(missing.status(), 404);
+
+        let health: serde_json::Value = client
+            .get(format!("{}/health", base))
+            .send()
+            .unwrap()
+            .json()
+            .unwrap();
+        assert_eq!(health["server_id"], "ABCD-EF01");
+    }
+
+    #[test]
+    fn rejects_overlong_audio_and_unknown_languages() {
+        let base = start_test_server(1024 * 1024, 0);
+        let client = Client::new();
+        let too_long = client
+            .post(format!("{}/v1/audio/transcriptions", base))
+            .bearer_auth("secret-token")
+            .multipart(upload_form("json"))
+            .send()
+            .unwrap();
+        assert_eq!(too_long.status(), 413);
+
+        let base = start_test_server(1024 * 1024, 60);
+        let unknown = client
+            .post(format!("{}/v1/audio/transcriptions", base))
+            .bearer_auth("secret-token")
+            .multipart(upload_form_in("zz", "json"))
+            .send()
+            .unwrap();
+        assert_eq!(unknown.status(), 400);
+    }
+
+    #[test]
+    fn serve_arguments_override_config() {
+        let mut config = Config::default();
+        apply_arguments(
+            &mut config,
+            &[
+                "--listen".to_string(),
+                "0.0.0.0:9000".to_string(),
+                "--max-concurrent".to_string(),
+                "4".to_string(),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.server.listen, "0.0.0.0:9000");
+        assert_eq!(config.server.max_concurrent_requests, 4);
+        assert!(apply_arguments(&mut config, &["--bogus".to_string()]).is_err());
+        assert!(apply_arguments(&mut config, &["--listen".to_string()]).is_err());
+    }
+}
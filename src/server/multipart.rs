@@ -0,0 +1,150 @@
+//! Minimal `multipart/form-data` parser for transcription uploads.
+//!
+//! Bodies are size-limited before parsing, so the whole request is held in
+//! memory and split on the boundary without streaming.
+
+use anyhow::Result;
+
+/// One form part: its field name, optional file name and raw contents.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Part<'a> {
+    pub name: String,
+    pub file_name: Option<String>,
+    pub data: &'a [u8],
+}
+
+/// Extract the boundary parameter from a `Content-Type` header value.
+pub(super) fn boundary(content_type: &str) -> Option<String> {
+    let mut params = content_type.split(';');
+    let media_type = params.next()?.trim();
+    if !media_type.eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+
+    params.find_map(|param| {
+        let (key, value) = param.trim().split_once('=')?;
+        if !key.trim().eq_ignore_ascii_case("boundary") {
+            return None;
+        }
+        let value = value.trim().trim_matches('"');
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// Split a form body into its parts.
+pub(super) fn parse<'a>(body: &'a [u8], boundary: &str) -> Result<Vec<Part<'a>>> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut parts = Vec::new();
+
+    let mut rest = match find(body, &delimiter) {
+        Some(start) => &body[start + delimiter.len()..],
+        None => anyhow::bail!("Multipart body has no opening boundary"),
+    };
+
+    loop {
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+        rest = rest
+            .strip_prefix(b"\r\n")
+            .ok_or_else(|| anyhow::anyhow!("Malformed multipart boundary line"))?;
+
+        let header_end = find(rest, b"\r\n\r\n")
+            .ok_or_else(|| anyhow::anyhow!("Multipart part has no header terminator"))?;
+        let headers = std::str::from_utf8(&rest[..header_end])
+            .map_err(|_| anyhow::anyhow!("Multipart headers are not UTF-8"))?;
+        let content = &rest[header_end + 4..];
+
+        let mut closing = b"\r\n".to_vec();
+        closing.extend_from_slice(&delimiter);
+        let data_end = find(content, &closing)
+            .ok_or_else(|| anyhow::anyhow!("Multipart part is not terminated"))?;
+
+        let (name, file_name) = content_disposition(headers)?;
+        parts.push(Part {
+            name,
+            file_name,
+            data: &content[..data_end],
+        });
+        rest = &content[data_end + closing.len()..];
+    }
+}
+
+fn content_disposition(headers: &str) -> Result<(String, Option<String>)> {
+    let value = headers
+        .split("\r\n")
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("content-disposition")
+                .then_some(value)
+        })
+        .ok_or_else(|| anyhow::anyhow!("Multipart part has no Content-Disposition"))?;
+
+    let mut name = None;
+    let mut file_name = None;
+    for param in value.split(';').skip(1) {
+        if let Some((key, value)) = param.trim().split_once('=') {
+            let value = value.trim().trim_matches('"').to_string();
+            match key.trim() {
+                "name" => name = Some(value),
+                "filename" => file_name = Some(value),
+                _ => {}
+            }
+        }
+    }
+
+    let name = name.ok_or_else(|| anyhow::anyhow!("Multipart part has no field name"))?;
+    Ok((name, file_name))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boundary_is_read_from_content_type() {
+        assert_eq!(
+            boundary("multipart/form-data; boundary=abc123").as_deref(),
+            Some("abc123")
+        );
+        assert_eq!(
+            boundary("Multipart/Form-Data; charset=utf-8; boundary=\"q\"").as_deref(),
+            Some("q")
+        );
+        assert_eq!(boundary("application/json"), None);
+        assert_eq!(boundary("multipart/form-data"), None);
+    }
+
+    #[test]
+    fn parses_text_and_file_parts() {
+        let body = b"--XyZ\r\n\
+Content-Disposition: form-data; name=\"model\"\r\n\r\n\
+whisper-1\r\n\
+--XyZ\r\n\
+Content-Disposition: form-data; name=\"file\"; filename=\"audio.wav\"\r\n\
+Content-Type: audio/wav\r\n\r\n\
+RIFF\r\n--not-a-boundary\r\n\
+--XyZ--\r\n";
+
+        let parts = parse(body, "XyZ").unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name, "model");
+        assert_eq!(parts[0].data, b"whisper-1");
+        assert_eq!(parts[1].file_name.as_deref(), Some("audio.wav"));
+        assert_eq!(parts[1].data, b"RIFF\r\n--not-a-boundary");
+    }
+
+    #[test]
+    fn truncated_bodies_are_rejected() {
+        let body = b"--XyZ\r\nContent-Disposition: form-data; name=\"file\"\r\n\r\nRIFF";
+        assert!(parse(body, "XyZ").is_err());
+        assert!(parse(b"no boundary here", "XyZ").is_err());
+    }
+}
@@ -3,18 +3,27 @@
 //! Full settings UI with tabs for General, Hotkey, Models, Dictionary, and Backend
 //! configuration.
 
-use crate::models::{self, ModelBenchmark, ModelInfo, AVAILABLE_MODELS};
+use crate::models::{self, ModelInfo};
 use crate::personalisation::Personalisation;
+use crate::settings::discovery::create_discovery_section;
+use crate::settings::language_models::create_language_models_section;
+use crate::settings::model_benchmarks::{create_benchmark_section, ProfileLabels};
+use crate::settings::model_sources::create_model_sources_section;
+use crate::settings::model_storage::create_storage_section;
 use crate::settings::personalisation::create_personalisation_tab;
+use crate::settings::remote_endpoints::{
+    create_endpoint_list, remove_released_client_keys, validate_endpoint_draft,
+};
+use crate::transcribe::describe_remote_request;
 use crate::{
     clear_remote_api_key, get_remote_api_key, save_config, set_remote_api_key, AppEvent, Config,
-    HotkeyConfig, TranscriptionBackend,
+    HotkeyConfig, RemoteBudgetConfig, RemoteEndpointConfig, TranscriptionBackend,
 };
 use crossbeam_channel::Sender;
 use gtk4::prelude::*;
 use gtk4::{
     Application, ApplicationWindow, Box as GtkBox, Button, CheckButton, ComboBoxText, Entry, Grid,
-    Label, Notebook, Orientation, ScrolledWindow, Separator, SpinButton,
+    Label, Notebook, Orientation, ScrolledWindow, Separator, SpinButton, TextView, WrapMode,
 };
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
@@ -22,6 +31,22 @@ use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, RwLock};
 use tracing::{error, info, warn};
 
+/// Dictation languages offered in Settings, by Whisper language code.
+pub(super) const LANGUAGES: &[(&str, &str)] = &[
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("de", "German"),
+    ("fr", "French"),
+    ("it", "Italian"),
+    ("pt", "Portuguese"),
+    ("nl", "Dutch"),
+    ("pl", "Polish"),
+    ("ru", "Russian"),
+    ("zh", "Chinese"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+];
+
 /// Show the settings dialog
 pub fn show_settings_dialog(
     config: &Arc<RwLock<Config>>,
@@ -89,11 +114,16 @@ fn build_settings_window(
         Some(&Label::new(Some("Models"))),
     );
     notebook.append_page(
-        &create_personalisation_tab(personalisation_state),
+        &create_personalisation_tab(personalisation_state.clone()),
         Some(&Label::new(Some("Dictionary"))),
     );
     notebook.append_page(
-        &create_backend_tab(config, config_state, event_tx.clone()),
+        &create_backend_tab(
+            config,
+            config_state,
+            personalisation_state,
+            event_tx.clone(),
+        ),
         Some(&Label::new(Some("Backend"))),
     );
 
@@ -157,32 +187,43 @@ fn create_general_tab(
     lang_box.append(&Label::new(Some("Language:")));
 
     let lang_combo = ComboBoxText::new();
-    let languages = [
-        ("en", "English"),
-        ("es", "Spanish"),
-        ("de", "German"),
-        ("fr", "French"),
-        ("it", "Italian"),
-        ("pt", "Portuguese"),
-        ("nl", "Dutch"),
-        ("pl", "Polish"),
-        ("ru", "Russian"),
-        ("zh", "Chinese"),
-        ("ja", "Japanese"),
-        ("ko", "Korean"),
-        ("auto", "Auto-detect"),
-    ];
-    for (code, name) in languages {
+    for (code, name) in LANGUAGES {
         lang_combo.append(Some(code), name);
     }
+    lang_combo.append(Some("auto"), "Auto-detect");
     lang_combo.set_active_id(Some(&config.language));
     lang_box.append(&lang_combo);
     vbox.append(&lang_box);
 
+    let candidates_label = Label::new(Some(
+        "Auto-detect only chooses between (none ticked allows any language):",
+    ));
+    candidates_label.set_xalign(0.0);
+    candidates_label.set_wrap(true);
+    vbox.append(&candidates_label);
+
+    let candidates_grid = Grid::new();
+    candidates_grid.set_row_spacing(4);
+    candidates_grid.set_column_spacing(12);
+    let mut candidate_checks = Vec::new();
+    for (index, (code, name)) in LANGUAGES.iter().enumerate() {
+        let check = CheckButton::with_label(name);
+        check.set_active(config.candidate_languages.iter().any(|c| c == code));
+        candidates_grid.attach(&check, (index % 4) as i32, (index / 4) as i32, 1, 1);
+        candidate_checks.push((code.to_string(), check));
+    }
+    vbox.append(&candidates_grid);
+
     let translate_check = CheckButton::with_label("Translate to English (for non-English speech)");
     translate_check.set_active(config.translate_to_english);
     vbox.append(&translate_check);
 
+    let language_warning = Label::new(models::language_warning(config).as_deref());
+    language_warning.set_wrap(true);
+    language_warning.set_xalign(0.0);
+    language_warning.add_css_class("warning");
+    vbox.append(&language_warning);
+
     let spacer = GtkBox::new(Orientation::Vertical, 0);
     spacer.set_vexpand(true);
     vbox.append(&spacer);
@@ -207,10 +248,21 @@ fn create_general_tab(
         if let Some(lang) = lang_combo.active_id() {
             new_config.language = lang.to_string();
         }
+        // Codes not offered here keep whatever config.json lists.
+        new_config
+            .candidate_languages
+            .retain(|code| !LANGUAGES.iter().any(|(offered, _)| offered == code));
+        new_config.candidate_languages.extend(
+            candidate_checks
+                .iter()
+                .filter(|(_, check)| check.is_active())
+                .map(|(code, _)| code.clone()),
+        );
 
         if let Err(e) = save_config(&new_config) {
             error!("Failed to save config: {}", e);
         } else {
+            language_warning.set_text(&models::language_warning(&new_config).unwrap_or_default());
             if let Ok(mut guard) = config_state_clone.write() {
                 *guard = new_config;
             }
@@ -465,14 +517,34 @@ fn create_models_tab(
     current_box.append(&current_label);
     vbox.append(&current_box);
 
-    let profile_text = models::estimate_model_benchmark(&config.model_path)
-        .map(|b| model_benchmark_text(&b))
-        .unwrap_or_else(|_| "No benchmark available for active model".to_string());
-    let profile_label = Label::new(Some(&profile_text));
+    let profile_labels = ProfileLabels::default();
+    let profile_label = profile_labels.track(
+        &config.model_path,
+        None,
+        "No benchmark available for active model",
+    );
     profile_label.add_css_class("dim-label");
     profile_label.set_wrap(true);
     profile_label.set_xalign(0.0);
     vbox.append(&profile_label);
+    if let Some(warning) = models::language_warning(config) {
+        let warning_label = Label::new(Some(&warning));
+        warning_label.set_wrap(true);
+        warning_label.set_xalign(0.0);
+        warning_label.add_css_class("warning");
+        vbox.append(&warning_label);
+    }
+    vbox.append(&create_benchmark_section(
+        config_state.clone(),
+        profile_labels.clone(),
+    ));
+
+    vbox.append(&Separator::new(Orientation::Horizontal));
+    vbox.append(&create_language_models_section(
+        config,
+        config_state.clone(),
+        event_tx.clone(),
+    ));
 
     vbox.append(&Separator::new(Orientation::Horizontal));
 
@@ -532,13 +604,29 @@ fn create_models_tab(
     scroll.set_min_content_height(180);
 
     let models_box = GtkBox::new(Orientation::Vertical, 8);
-    for model in AVAILABLE_MODELS {
-        let row = create_model_row(model, config, config_state.clone(), event_tx.clone());
+    for model in models::catalogue() {
+        let row = create_model_row(
+            model,
+            config,
+            config_state.clone(),
+            event_tx.clone(),
+            &profile_labels,
+        );
         models_box.append(&row);
     }
     scroll.set_child(Some(&models_box));
     vbox.append(&scroll);
 
+    vbox.append(&Separator::new(Orientation::Horizontal));
+    vbox.append(&create_storage_section(config, config_state.clone()));
+
+    vbox.append(&Separator::new(Orientation::Horizontal));
+    vbox.append(&create_model_sources_section(
+        config,
+        config_state.clone(),
+        event_tx.clone(),
+    ));
+
     let custom_models = models::list_custom_models();
     if !custom_models.is_empty() {
         vbox.append(&Separator::new(Orientation::Horizontal));
@@ -553,6 +641,7 @@ fn create_models_tab(
                 config,
                 config_state.clone(),
                 event_tx.clone(),
+                &profile_labels,
             );
             vbox.append(&row);
         }
@@ -566,6 +655,7 @@ fn create_custom_model_row(
     config: &Config,
     config_state: Arc<RwLock<Config>>,
     event_tx: Sender<AppEvent>,
+    profile_labels: &ProfileLabels,
 ) -> GtkBox {
     let row = GtkBox::new(Orientation::Horizontal, 12);
     row.set_margin_top(2);
@@ -583,10 +673,7 @@ fn create_custom_model_row(
     name_label.add_css_class("heading");
     info_box.append(&name_label);
 
-    let bench = models::estimate_model_benchmark(model_path)
-        .map(|b| model_benchmark_text(&b))
-        .unwrap_or_else(|_| "Custom local model".to_string());
-    let desc_label = Label::new(Some(&bench));
+    let desc_label = profile_labels.track(model_path, None, "Custom local model");
     desc_label.set_halign(gtk4::Align::Start);
     desc_label.add_css_class("dim-label");
     info_box.append(&desc_label);
@@ -630,6 +717,7 @@ fn create_model_row(
     config: &Config,
     config_state: Arc<RwLock<Config>>,
     event_tx: Sender<AppEvent>,
+    profile_labels: &ProfileLabels,
 ) -> GtkBox {
     let row = GtkBox::new(Orientation::Horizontal, 12);
     row.set_margin_top(4);
@@ -643,17 +731,26 @@ fn create_model_row(
     name_label.add_css_class("heading");
     info_box.append(&name_label);
 
-    let desc_label = Label::new(Some(&format!(
-        "{} • {}MB",
-        model.description, model.size_mb
-    )));
+    let is_installed = models::is_model_installed(model);
+    let desc_label = if is_installed {
+        profile_labels.track(
+            &models::get_model_path(model),
+            Some(model.description),
+            model.description,
+        )
+    } else {
+        Label::new(Some(&format!(
+            "{} • {}MB",
+            model.description, model.size_mb
+        )))
+    };
     desc_label.set_halign(gtk4::Align::Start);
+    desc_label.set_wrap(true);
     desc_label.add_css_class("dim-label");
     info_box.append(&desc_label);
 
     row.append(&info_box);
 
-    let is_installed = models::is_model_installed(model);
     let is_active = is_installed
         && config
             .model_path
@@ -677,6 +774,7 @@ fn create_model_row(
                 size_mb: 0,
                 url: "",
                 description: "",
+                sha256: None,
             });
             if let Err(e) = save_config(&new_config) {
                 error!("Failed to set active model: {}", e);
@@ -728,6 +826,7 @@ fn create_model_row(
 fn create_backend_tab(
     config: &Config,
     config_state: Arc<RwLock<Config>>,
+    personalisation_state: Arc<RwLock<Personalisation>>,
     event_tx: Sender<AppEvent>,
 ) -> GtkBox {
     let vbox = GtkBox::new(Orientation::Vertical, 12);
@@ -753,7 +852,7 @@ fn create_backend_tab(
     mode_box.append(&Label::new(Some("Backend mode:")));
     let backend_combo = ComboBoxText::new();
     backend_combo.append(Some("local"), "Local (Whisper.cpp)");
-    backend_combo.append(Some("remote"), "Remote endpoint (OpenAI-compatible)");
+    backend_combo.append(Some("remote"), "Remote endpoints (with local fallback)");
     match config.backend {
         TranscriptionBackend::Local => backend_combo.set_active_id(Some("local")),
         TranscriptionBackend::Remote => backend_combo.set_active_id(Some("remote")),
@@ -761,47 +860,55 @@ fn create_backend_tab(
     mode_box.append(&backend_combo);
     vbox.append(&mode_box);
 
-    let endpoint_box = GtkBox::new(Orientation::Horizontal, 8);
-    endpoint_box.append(&Label::new(Some("Endpoint URL:")));
-    let endpoint_entry = Entry::new();
-    endpoint_entry.set_hexpand(true);
-    endpoint_entry
-        .set_placeholder_text(Some("https://your-vps.example.com/v1/audio/transcriptions"));
-    endpoint_entry.set_text(&config.remote_backend.endpoint);
-    endpoint_box.append(&endpoint_entry);
-    vbox.append(&endpoint_box);
-
-    let model_box = GtkBox::new(Orientation::Horizontal, 8);
-    model_box.append(&Label::new(Some("Remote model:")));
-    let model_entry = Entry::new();
-    model_entry.set_hexpand(true);
-    model_entry.set_placeholder_text(Some("whisper-1"));
-    model_entry.set_text(&config.remote_backend.model);
-    model_box.append(&model_entry);
-    vbox.append(&model_box);
-
-    let timeout_box = GtkBox::new(Orientation::Horizontal, 8);
-    timeout_box.append(&Label::new(Some("Timeout (ms):")));
-    let timeout_spin = SpinButton::with_range(1_000.0, 120_000.0, 500.0);
-    timeout_spin.set_value(config.remote_backend.timeout_ms as f64);
-    timeout_box.append(&timeout_spin);
-    vbox.append(&timeout_box);
-
-    let insecure_check = CheckButton::with_label("Allow insecure HTTP endpoint (not recommended)");
-    insecure_check.set_active(config.remote_backend.allow_insecure_http);
-    vbox.append(&insecure_check);
-
-    let env_box = GtkBox::new(Orientation::Horizontal, 8);
-    env_box.append(&Label::new(Some("API key env var (optional):")));
-    let env_entry = Entry::new();
-    env_entry.set_hexpand(true);
-    env_entry.set_placeholder_text(Some("OSWISPA_REMOTE_API_KEY"));
-    env_entry.set_text(config.remote_backend.api_key_env.as_deref().unwrap_or(""));
-    env_box.append(&env_entry);
-    vbox.append(&env_box);
+    let endpoint_scroll = ScrolledWindow::new();
+    endpoint_scroll.set_vexpand(true);
+    endpoint_scroll.set_min_content_height(220);
+    let endpoint_list = create_endpoint_list(config.remote_backend.endpoints().cloned().collect());
+    endpoint_scroll.set_child(Some(&endpoint_list.widget));
+    vbox.append(&endpoint_scroll);
+
+    vbox.append(&create_discovery_section(endpoint_list.clone()));
+
+    let preview_btn = Button::with_label("Show What Will Be Sent");
+    let preview = TextView::new();
+    preview.set_editable(false);
+    preview.set_monospace(true);
+    preview.set_wrap_mode(WrapMode::WordChar);
+    let preview_for_click = preview.clone();
+    let endpoint_list_for_preview = endpoint_list.clone();
+    let config_state_for_preview = config_state.clone();
+    preview_btn.connect_clicked(move |_| {
+        let config = config_state_for_preview.read().unwrap().clone();
+        let vocabulary_prompt = personalisation_state
+            .read()
+            .ok()
+            .and_then(|dictionary| dictionary.vocabulary_prompt());
+        let requests: Vec<String> = endpoint_list_for_preview
+            .draft
+            .borrow()
+            .iter()
+            .filter(|endpoint| !endpoint.endpoint.is_empty())
+            .enumerate()
+            .map(|(index, endpoint)| {
+                format!(
+                    "Endpoint #{}\n{}",
+                    index + 1,
+                    describe_remote_request(endpoint, &config, vocabulary_prompt.as_deref())
+                )
+            })
+            .collect();
+        let text = if requests.is_empty() {
+            "No endpoint URL is set.".to_string()
+        } else {
+            requests.join("\n\n")
+        };
+        preview_for_click.buffer().set_text(&text);
+    });
+    vbox.append(&preview_btn);
+    vbox.append(&preview);
 
     let api_box = GtkBox::new(Orientation::Horizontal, 8);
-    api_box.append(&Label::new(Some("Store API key:")));
+    api_box.append(&Label::new(Some("Store shared API key:")));
     let api_key_entry = Entry::new();
     api_key_entry.set_hexpand(true);
     api_key_entry.set_visibility(false);
@@ -823,9 +930,30 @@ fn create_backend_tab(
     clear_key_check.set_active(false);
     vbox.append(&clear_key_check);
 
-    let spacer = GtkBox::new(Orientation::Vertical, 0);
-    spacer.set_vexpand(true);
-    vbox.append(&spacer);
+    vbox.append(&Separator::new(Orientation::Horizontal));
+
+    let mut usage_lines = vec![crate::usage::usage_summary(config)];
+    usage_lines.extend(crate::usage::usage_by_endpoint_today());
+    let usage_label = Label::new(Some(&usage_lines.join("\n")));
+    usage_label.set_wrap(true);
+    usage_label.set_xalign(0.0);
+    usage_label.set_selectable(true);
+    vbox.append(&usage_label);
+
+    let budget = config.remote_backend.budget;
+    let budget_box = GtkBox::new(Orientation::Horizontal, 8);
+    budget_box.append(&Label::new(Some("Daily cap (min):")));
+    let daily_cap = SpinButton::with_range(0.0, 100_000.0, 5.0);
+    daily_cap.set_value(budget.daily_minutes.unwrap_or(0) as f64);
+    budget_box.append(&daily_cap);
+    budget_box.append(&Label::new(Some("Monthly cap (min):")));
+    let monthly_cap = SpinButton::with_range(0.0, 1_000_000.0, 60.0);
+    monthly_cap.set_value(budget.monthly_minutes.unwrap_or(0) as f64);
+    budget_box.append(&monthly_cap);
+    budget_box.set_tooltip_text(Some(
+        "0 means no cap. Once a cap is reached, dictation uses the local model until the next day or month.",
+    ));
+    vbox.append(&budget_box);
 
     let save_btn = Button::with_label("Save Backend Settings");
     save_btn.add_css_class("suggested-action");
@@ -834,44 +962,29 @@ fn create_backend_tab(
     let event_tx_clone = event_tx.clone();
     save_btn.connect_clicked(move |_| {
         let mut new_config = config_state_clone.read().unwrap().clone();
+        let previous_endpoints: Vec<RemoteEndpointConfig> =
+            new_config.remote_backend.endpoints().cloned().collect();
         new_config.backend = match backend_combo.active_id().as_deref() {
             Some("remote") => TranscriptionBackend::Remote,
             _ => TranscriptionBackend::Local,
         };
-        new_config.remote_backend.endpoint = endpoint_entry.text().trim().to_string();
-        new_config.remote_backend.model = {
-            let model = model_entry.text().trim().to_string();
-            if model.is_empty() {
-                "whisper-1".to_string()
-            } else {
-                model
-            }
-        };
-        new_config.remote_backend.timeout_ms = timeout_spin.value() as u64;
-        new_config.remote_backend.allow_insecure_http = insecure_check.is_active();
-        new_config.remote_backend.api_key_env = {
-            let env = env_entry.text().trim().to_string();
-            if env.is_empty() {
-                None
-            } else {
-                Some(env)
+        let endpoints = endpoint_list.draft.borrow().clone();
+        if new_config.backend == TranscriptionBackend::Remote {
+            if let Err(reason) = validate_endpoint_draft(&endpoints) {
+                error!("{}", reason);
+                return;
             }
-        };
-
-        if new_config.backend == TranscriptionBackend::Remote
-            && new_config.remote_backend.endpoint.trim().is_empty()
-        {
-            error!("Remote backend selected but endpoint URL is empty");
-            return;
-        }
-
-        if new_config.backend == TranscriptionBackend::Remote
-            && new_config.remote_backend.endpoint.starts_with("http://")
-            && !new_config.remote_backend.allow_insecure_http
-        {
-            error!("Remote endpoint uses HTTP; enable insecure HTTP only if you explicitly trust the network");
-            return;
         }
+        new_config.remote_backend.budget = RemoteBudgetConfig {
+            daily_minutes: Some(daily_cap.value() as u32).filter(|minutes| *minutes > 0),
+            monthly_minutes: Some(monthly_cap.value() as u32).filter(|minutes| *minutes > 0),
+        };
+        new_config.remote_backend.set_endpoints(
+            endpoints
+                .into_iter()
+                .filter(|endpoint| !endpoint.endpoint.is_empty())
+                .collect(),
+        );
 
         if clear_key_check.is_active() {
             if let Err(e) = clear_remote_api_key() {
@@ -889,6 +1002,9 @@ fn create_backend_tab(
         if let Err(e) = save_config(&new_config) {
             error!("Failed to save backend config: {}", e);
         } else {
+            let current_endpoints: Vec<RemoteEndpointConfig> =
+                new_config.remote_backend.endpoints().cloned().collect();
+            remove_released_client_keys(&previous_endpoints, &current_endpoints);
             if let Ok(mut guard) = config_state_clone.write() {
                 *guard = new_config;
             }
@@ -901,13 +1017,6 @@ fn create_backend_tab(
     vbox
 }
 
-fn model_benchmark_text(benchmark: &ModelBenchmark) -> String {
-    format!(
-        "Size: {:.0}MB • Speed: {} • Accuracy: {}",
-        benchmark.size_mb, benchmark.speed_tier, benchmark.accuracy_tier
-    )
-}
-
 /// Format hotkey for display
 fn format_hotkey(hotkey: &HotkeyConfig) -> String {
     let mut parts = Vec::new();
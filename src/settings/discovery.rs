@@ -0,0 +1,200 @@
+//! "Discover LAN Servers" section of the Backend tab.
+//!
+//! Lists `oswispa serve` instances found over DNS-SD. A server is only added to
+//! the endpoint list after the user confirms they trust it, and adding one
+//! never switches the backend mode by itself. Announcements are not
+//! authenticated, so the section says so rather than promising verification.
+
+use crate::settings::remote_endpoints::EndpointList;
+use crate::{discover_servers, store_remote_endpoint_token, DiscoveredServer};
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::{Box as GtkBox, Button, CheckButton, Entry, Frame, Grid, Label, Orientation};
+use std::time::Duration;
+
+const BROWSE_TIME: Duration = Duration::from_secs(3);
+
+/// Build the discovery section; confirmed servers are appended to `endpoint_list`.
+pub(super) fn create_discovery_section(endpoint_list: EndpointList) -> GtkBox {
+    let section = GtkBox::new(Orientation::Vertical, 8);
+
+    let controls = GtkBox::new(Orientation::Horizontal, 8);
+    let discover = Button::with_label("Discover LAN Servers");
+    controls.append(&discover);
+    let status = Label::new(None);
+    status.set_xalign(0.0);
+    status.add_css_class("dim-label");
+    controls.append(&status);
+    section.append(&controls);
+
+    let caution = Label::new(Some(
+        "Announcements and server IDs are not authenticated and audio travels over plain HTTP. \
+         Only add servers on a network you trust.",
+    ));
+    caution.set_xalign(0.0);
+    caution.set_wrap(true);
+    caution.add_css_class("dim-label");
+    section.append(&caution);
+
+    let results = GtkBox::new(Orientation::Vertical, 8);
+    section.append(&results);
+
+    discover.connect_clicked(move |button| {
+        button.set_sensitive(false);
+        status.set_text("Searching the local network...");
+        while let Some(child) = results.first_child() {
+            results.remove(&child);
+        }
+
+        let (result_tx, result_rx) = crossbeam_channel::bounded(1);
+        std::thread::spawn(move || {
+            let _ = result_tx.send(discover_servers(BROWSE_TIME));
+        });
+
+        let button = button.clone();
+        let status = status.clone();
+        let results = results.clone();
+        let endpoint_list = endpoint_list.clone();
+        glib::timeout_add_local(Duration::from_millis(200), move || {
+            let outcome = match result_rx.try_recv() {
+                Ok(outcome) => outcome,
+                Err(crossbeam_channel::TryRecvError::Empty) => return glib::ControlFlow::Continue,
+                Err(crossbeam_channel::TryRecvError::Disconnected) => {
+                    Err(anyhow::anyhow!("Discovery stopped unexpectedly"))
+                }
+            };
+            button.set_sensitive(true);
+            match outcome {
+                Ok(servers) if servers.is_empty() => status.set_text("No servers found."),
+                Ok(servers) => {
+                    status.set_text(&format!("Found {} server(s).", servers.len()));
+                    for server in servers {
+                        results.append(&server_row(server, &endpoint_list));
+                    }
+                }
+                Err(err) => status.set_text(&format!("Discovery failed: {}", err)),
+            }
+            glib::ControlFlow::Break
+        });
+    });
+
+    section
+}
+
+fn server_row(server: DiscoveredServer, endpoint_list: &EndpointList) -> Frame {
+    let frame = Frame::new(Some(&server.name));
+    let grid = Grid::new();
+    grid.set_row_spacing(6);
+    grid.set_column_spacing(8);
+    grid.set_margin_top(6);
+    grid.set_margin_bottom(6);
+    grid.set_margin_start(8);
+    grid.set_margin_end(8);
+
+    let address = server.endpoint_url().unwrap_or_default();
+    let details = [
+        ("Address:", address.as_str()),
+        ("Host:", server.host.as_str()),
+        ("Model:", server.model.as_str()),
+        (
+            "Dialect:",
+            match server.dialect {
+                crate::RemoteDialect::OpenAi => "OpenAI-compatible",
+                crate::RemoteDialect::WhisperCpp => "whisper.cpp server",
+            },
+        ),
+        ("Server ID:", server.server_id.as_str()),
+    ];
+    for (row, (name, value)) in details.iter().enumerate() {
+        let label = Label::new(Some(name));
+        label.set_xalign(0.0);
+        grid.attach(&label, 0, row as i32, 1, 1);
+        let value = Label::new(Some(value));
+        value.set_xalign(0.0);
+        value.set_selectable(true);
+        grid.attach(&value, 1, row as i32, 1, 1);
+    }
+
+    let token = Entry::new();
+    token.set_hexpand(true);
+    token.set_visibility(false);
+    token.set_placeholder_text(Some("Server token printed by oswispa serve"));
+    grid.attach(&Label::new(Some("Token:")), 0, 5, 1, 1);
+    grid.attach(&token, 1, 5, 1, 1);
+
+    let confirm = CheckButton::with_label(
+        "I trust this network and the server ID matches the one oswispa serve printed",
+    );
+    grid.attach(&confirm, 0, 6, 2, 1);
+
+    let add = Button::with_label("Add as Endpoint");
+    add.set_sensitive(false);
+    let feedback = Label::new(None);
+    feedback.set_xalign(0.0);
+    feedback.add_css_class("dim-label");
+    grid.attach(&add, 0, 7, 1, 1);
+    grid.attach(&feedback, 1, 7, 1, 1);
+
+    let add_for_confirm = add.clone();
+    confirm.connect_toggled(move |check| add_for_confirm.set_sensitive(check.is_active()));
+
+    let endpoint_list = endpoint_list.clone();
+    add.connect_clicked(move |button| {
+        button.set_sensitive(false);
+        confirm.set_sensitive(false);
+        feedback.set_text("Contacting the server...");
+
+        let (check_tx, check_rx) = crossbeam_channel::bounded(1);
+        let server_for_check = server.clone();
+        std::thread::spawn(move || {
+            let _ = check_tx.send(server_for_check.check_server_id());
+        });
+
+        let button = button.clone();
+        let confirm = confirm.clone();
+        let feedback = feedback.clone();
+        let token = token.clone();
+        let server = server.clone();
+        let endpoint_list = endpoint_list.clone();
+        glib::timeout_add_local(Duration::from_millis(200), move || {
+            let outcome = match check_rx.try_recv() {
+                Ok(outcome) => outcome,
+                Err(crossbeam_channel::TryRecvError::Empty) => return glib::ControlFlow::Continue,
+                Err(crossbeam_channel::TryRecvError::Disconnected) => {
+                    Err(anyhow::anyhow!("The server check stopped unexpectedly"))
+                }
+            };
+            let not_added = |reason: String| {
+                feedback.set_text(&format!("Not added: {}", reason));
+                button.set_sensitive(true);
+                confirm.set_sensitive(true);
+            };
+            if let Err(err) = outcome {
+                not_added(err.to_string());
+                return glib::ControlFlow::Break;
+            }
+            let Some(mut endpoint) = server.to_endpoint() else {
+                not_added("the server has no usable address.".to_string());
+                return glib::ControlFlow::Break;
+            };
+
+            let typed_token = token.text().trim().to_string();
+            if !typed_token.is_empty() {
+                match store_remote_endpoint_token(&server.host, server.port, &typed_token) {
+                    Ok(name) => endpoint.api_key_secret = Some(name),
+                    Err(err) => {
+                        not_added(format!("could not store the token: {}", err));
+                        return glib::ControlFlow::Break;
+                    }
+                }
+            }
+
+            endpoint_list.push(endpoint);
+            feedback.set_text("Added. Select remote mode and save to use it.");
+            glib::ControlFlow::Break
+        });
+    });
+
+    frame.set_child(Some(&grid));
+    frame
+}
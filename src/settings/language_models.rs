@@ -0,0 +1,117 @@
+//! "Models per language" section of the Models tab.
+
+use super::dialog::LANGUAGES;
+use crate::{models, save_config, AppEvent, Config};
+use crossbeam_channel::Sender;
+use gtk4::prelude::*;
+use gtk4::{Box as GtkBox, Button, ComboBoxText, Grid, Label, Orientation};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use tracing::{error, info};
+
+const DEFAULT_ID: &str = "default";
+
+fn file_label(path: &std::path::Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// One model choice per language, saved with its own button.
+pub(super) fn create_language_models_section(
+    config: &Config,
+    config_state: Arc<RwLock<Config>>,
+    event_tx: Sender<AppEvent>,
+) -> GtkBox {
+    let section = GtkBox::new(Orientation::Vertical, 8);
+
+    let title = Label::new(Some("Models per language"));
+    title.set_xalign(0.0);
+    title.add_css_class("heading");
+    section.append(&title);
+
+    let hint = Label::new(Some(
+        "Dictation in these languages uses the chosen model, including languages found by \
+         Auto-detect. Other languages use the active model.",
+    ));
+    hint.set_xalign(0.0);
+    hint.set_wrap(true);
+    hint.add_css_class("dim-label");
+    section.append(&hint);
+
+    let mut installed = models::installed_model_paths();
+    // Keep a configured model selectable even if its file has gone missing.
+    for path in config.language_models.values() {
+        if !installed.contains(path) {
+            installed.push(path.clone());
+        }
+    }
+
+    let grid = Grid::new();
+    grid.set_row_spacing(6);
+    grid.set_column_spacing(8);
+    let mut combos = Vec::new();
+    for (row, (code, name)) in LANGUAGES.iter().enumerate() {
+        let label = Label::new(Some(name));
+        label.set_xalign(0.0);
+        grid.attach(&label, 0, row as i32, 1, 1);
+
+        let combo = ComboBoxText::new();
+        combo.set_hexpand(true);
+        combo.append(Some(DEFAULT_ID), "Active model");
+        for path in &installed {
+            combo.append(Some(&path.to_string_lossy()), &file_label(path));
+        }
+        match config.language_models.get(*code) {
+            Some(path) => combo.set_active_id(Some(&path.to_string_lossy())),
+            None => combo.set_active_id(Some(DEFAULT_ID)),
+        };
+        grid.attach(&combo, 1, row as i32, 1, 1);
+        combos.push((code.to_string(), combo));
+    }
+    section.append(&grid);
+
+    let controls = GtkBox::new(Orientation::Horizontal, 8);
+    let save = Button::with_label("Save Language Models");
+    controls.append(&save);
+    let status = Label::new(None);
+    status.set_xalign(0.0);
+    status.set_wrap(true);
+    status.add_css_class("dim-label");
+    controls.append(&status);
+    section.append(&controls);
+
+    save.connect_clicked(move |_| {
+        let mut new_config = config_state.read().unwrap().clone();
+        // Languages not offered here keep whatever config.json maps them to.
+        let mut language_models: BTreeMap<String, PathBuf> = new_config
+            .language_models
+            .into_iter()
+            .filter(|(code, _)| !LANGUAGES.iter().any(|(offered, _)| offered == code))
+            .collect();
+        for (code, combo) in &combos {
+            match combo.active_id() {
+                Some(id) if id != DEFAULT_ID => {
+                    language_models.insert(code.clone(), PathBuf::from(id.as_str()));
+                }
+                _ => {}
+            }
+        }
+        new_config.language_models = language_models;
+
+        if let Err(e) = save_config(&new_config) {
+            error!("Failed to save language models: {}", e);
+            status.set_text("Could not save; see the log.");
+            return;
+        }
+        status.set_text(&models::language_warning(&new_config).unwrap_or_else(|| "Saved.".into()));
+        if let Ok(mut guard) = config_state.write() {
+            *guard = new_config;
+        }
+        info!("Language models saved");
+        let _ = event_tx.send(AppEvent::ReloadConfig);
+    });
+
+    section
+}
@@ -5,7 +5,19 @@
 #[cfg(feature = "gui")]
 mod dialog;
 #[cfg(feature = "gui")]
+mod discovery;
+#[cfg(feature = "gui")]
+mod language_models;
+#[cfg(feature = "gui")]
+mod model_benchmarks;
+#[cfg(feature = "gui")]
+mod model_sources;
+#[cfg(feature = "gui")]
+mod model_storage;
+#[cfg(feature = "gui")]
 mod personalisation;
+#[cfg(feature = "gui")]
+mod remote_endpoints;
 
 #[cfg(feature = "gui")]
 pub use dialog::*;
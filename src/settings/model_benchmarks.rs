@@ -0,0 +1,177 @@
+//! Benchmark controls and measured model profiles for the Models tab.
+
+use crate::models::benchmark::{self, ModelMeasurements};
+use crate::models::{self, ModelBenchmark};
+use crate::Config;
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::{Box as GtkBox, Button, Label, Orientation};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+struct TrackedProfile {
+    path: PathBuf,
+    description: Option<&'static str>,
+    fallback: &'static str,
+    label: Label,
+}
+
+/// Profile labels on the Models tab, refreshed when a benchmark run finishes.
+#[derive(Clone, Default)]
+pub(super) struct ProfileLabels(Rc<RefCell<Vec<TrackedProfile>>>);
+
+impl ProfileLabels {
+    /// Create a label showing the profile of `path`, optionally after a model description.
+    pub(super) fn track(
+        &self,
+        path: &Path,
+        description: Option<&'static str>,
+        fallback: &'static str,
+    ) -> Label {
+        let label = Label::new(Some(&profile_text(path, description, fallback)));
+        self.0.borrow_mut().push(TrackedProfile {
+            path: path.to_path_buf(),
+            description,
+            fallback,
+            label: label.clone(),
+        });
+        label
+    }
+
+    fn refresh(&self) {
+        for tracked in self.0.borrow().iter() {
+            tracked.label.set_text(&profile_text(
+                &tracked.path,
+                tracked.description,
+                tracked.fallback,
+            ));
+        }
+    }
+}
+
+fn profile_text(path: &Path, description: Option<&str>, fallback: &str) -> String {
+    match (models::model_benchmark(path), description) {
+        (Ok(profile), Some(description)) => {
+            format!("{} • {}", description, model_benchmark_text(&profile))
+        }
+        (Ok(profile), None) => model_benchmark_text(&profile),
+        (Err(_), _) => fallback.to_string(),
+    }
+}
+
+fn model_benchmark_text(benchmark: &ModelBenchmark) -> String {
+    let speed = match benchmark::describe_measurements(&benchmark.measured) {
+        Some(measured) => format!("{} ({})", benchmark.speed_tier, measured),
+        None => format!("{} (estimated)", benchmark.speed_tier),
+    };
+    let text = format!(
+        "Size: {:.0}MB • Speed: {} • Accuracy: {}",
+        benchmark.size_mb, speed, benchmark.accuracy_tier
+    );
+    match &benchmark.header {
+        Some(header) => format!("{} • {}", text, header.describe()),
+        None => text,
+    }
+}
+
+fn recommendation_text() -> String {
+    let paths = models::installed_model_paths();
+    let candidates: Vec<(u64, ModelMeasurements)> = paths
+        .iter()
+        .map(|path| {
+            let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            (size, benchmark::measured(path))
+        })
+        .collect();
+    let Some(index) = benchmark::recommend_measured(&candidates) else {
+        return "Benchmark your installed models to get a recommendation measured on this machine."
+            .to_string();
+    };
+    let name = paths[index]
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match candidates[index].1.best() {
+        Some((device, measurement)) => format!(
+            "Recommended on this machine: {} ({:.2}x real time on the {}; real dictation decodes more text and runs slower)",
+            name, measurement.real_time_factor, device
+        ),
+        None => format!("Recommended on this machine: {}", name),
+    }
+}
+
+/// Button that measures every installed model, plus the measured recommendation.
+pub(super) fn create_benchmark_section(
+    config_state: Arc<RwLock<Config>>,
+    labels: ProfileLabels,
+) -> GtkBox {
+    let section = GtkBox::new(Orientation::Vertical, 6);
+
+    let recommendation = Label::new(Some(&recommendation_text()));
+    recommendation.set_wrap(true);
+    recommendation.set_xalign(0.0);
+    section.append(&recommendation);
+
+    let controls = GtkBox::new(Orientation::Horizontal, 8);
+    let run = Button::with_label("Benchmark Installed Models");
+    run.set_tooltip_text(Some(
+        "Times each installed model on a short synthetic clip on this machine. It mostly measures audio encoding, so real dictation is slower than the result. Large models can take a few minutes.",
+    ));
+    controls.append(&run);
+    let status = Label::new(None);
+    status.set_xalign(0.0);
+    status.set_wrap(true);
+    status.add_css_class("dim-label");
+    controls.append(&status);
+    section.append(&controls);
+
+    run.connect_clicked(move |button| {
+        button.set_sensitive(false);
+        status.set_text("Preparing benchmark...");
+
+        let config = config_state.read().unwrap().clone();
+        let (progress_tx, progress_rx) = crossbeam_channel::unbounded::<String>();
+        let (done_tx, done_rx) = crossbeam_channel::bounded(1);
+        std::thread::spawn(move || {
+            let results = benchmark::benchmark_installed_models(&config, |path| {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                let _ = progress_tx.send(format!("Measuring {}...", name));
+            });
+            let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+            let _ = done_tx.send((results.len(), failed));
+        });
+
+        let button = button.clone();
+        let status = status.clone();
+        let recommendation = recommendation.clone();
+        let labels = labels.clone();
+        glib::timeout_add_local(Duration::from_millis(250), move || {
+            while let Ok(message) = progress_rx.try_recv() {
+                status.set_text(&message);
+            }
+            let (total, failed) = match done_rx.try_recv() {
+                Ok(summary) => summary,
+                Err(crossbeam_channel::TryRecvError::Empty) => return glib::ControlFlow::Continue,
+                Err(crossbeam_channel::TryRecvError::Disconnected) => (0, 0),
+            };
+            button.set_sensitive(true);
+            status.set_text(&match (total, failed) {
+                (0, _) => "No installed models to benchmark.".to_string(),
+                (total, 0) => format!("Measured {} model(s).", total),
+                (total, failed) => format!(
+                    "Measured {} of {} model(s); see the log for failures.",
+                    total - failed,
+                    total
+                ),
+            });
+            labels.refresh();
+            recommendation.set_text(&recommendation_text());
+            glib::ControlFlow::Break
+        });
+    });
+
+    section
+}
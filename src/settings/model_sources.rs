@@ -0,0 +1,112 @@
+//! "Download sources" section of the Models tab: catalogue manifest, mirror and proxy.
+
+use crate::{save_config, AppEvent, Config, ModelCatalogueConfig};
+use crossbeam_channel::Sender;
+use gtk4::prelude::*;
+use gtk4::{Box as GtkBox, Button, Entry, Grid, Label, Orientation};
+use std::sync::{Arc, RwLock};
+use tracing::{error, info};
+
+fn optional_text(entry: &Entry) -> Option<String> {
+    Some(entry.text().trim().to_string()).filter(|text| !text.is_empty())
+}
+
+fn labelled_entry(
+    grid: &Grid,
+    row: i32,
+    label: &str,
+    placeholder: &str,
+    value: Option<&str>,
+) -> Entry {
+    let label = Label::new(Some(label));
+    label.set_xalign(0.0);
+    grid.attach(&label, 0, row, 1, 1);
+    let entry = Entry::new();
+    entry.set_hexpand(true);
+    entry.set_placeholder_text(Some(placeholder));
+    entry.set_text(value.unwrap_or_default());
+    grid.attach(&entry, 1, row, 1, 1);
+    entry
+}
+
+/// Entries for the model catalogue settings, saved with their own button.
+pub(super) fn create_model_sources_section(
+    config: &Config,
+    config_state: Arc<RwLock<Config>>,
+    event_tx: Sender<AppEvent>,
+) -> GtkBox {
+    let section = GtkBox::new(Orientation::Vertical, 8);
+    let settings = &config.model_catalogue;
+
+    let title = Label::new(Some("Download sources"));
+    title.set_xalign(0.0);
+    title.add_css_class("heading");
+    section.append(&title);
+
+    let grid = Grid::new();
+    grid.set_row_spacing(6);
+    grid.set_column_spacing(8);
+    let manifest = labelled_entry(
+        &grid,
+        0,
+        "Catalogue manifest:",
+        "Built-in list (URL or path to a JSON catalogue)",
+        settings.manifest.as_deref(),
+    );
+    let manifest_sha256 = labelled_entry(
+        &grid,
+        1,
+        "Manifest SHA-256:",
+        "Required for a manifest URL",
+        settings.manifest_sha256.as_deref(),
+    );
+    let mirror = labelled_entry(
+        &grid,
+        2,
+        "Model mirror:",
+        "https://mirror.example/whisper",
+        settings.mirror.as_deref(),
+    );
+    let proxy = labelled_entry(
+        &grid,
+        3,
+        "Download proxy:",
+        "System proxy settings",
+        settings.proxy.as_deref(),
+    );
+    section.append(&grid);
+
+    let controls = GtkBox::new(Orientation::Horizontal, 8);
+    let save = Button::with_label("Save Download Sources");
+    controls.append(&save);
+    let status = Label::new(None);
+    status.set_xalign(0.0);
+    status.set_wrap(true);
+    status.add_css_class("dim-label");
+    controls.append(&status);
+    section.append(&controls);
+
+    save.connect_clicked(move |_| {
+        let mut new_config = config_state.read().unwrap().clone();
+        new_config.model_catalogue = ModelCatalogueConfig {
+            manifest: optional_text(&manifest),
+            manifest_sha256: optional_text(&manifest_sha256),
+            mirror: optional_text(&mirror),
+            proxy: optional_text(&proxy),
+        };
+
+        if let Err(e) = save_config(&new_config) {
+            error!("Failed to save download sources: {}", e);
+            status.set_text("Could not save; see the log.");
+            return;
+        }
+        if let Ok(mut guard) = config_state.write() {
+            *guard = new_config;
+        }
+        info!("Download sources saved");
+        status.set_text("Saved. Reopen Settings to see an updated model list.");
+        let _ = event_tx.send(AppEvent::ReloadConfig);
+    });
+
+    section
+}
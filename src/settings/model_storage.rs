@@ -0,0 +1,151 @@
+//! "Storage" section of the Models tab: disk usage, deletion and partial-file clean-up.
+
+use crate::models::storage::{self, InstalledModel};
+use crate::Config;
+use gtk4::prelude::*;
+use gtk4::{Box as GtkBox, Button, Label, Orientation};
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+use tracing::error;
+
+fn format_size(bytes: u64) -> String {
+    let mb = bytes as f64 / (1024.0 * 1024.0);
+    if mb >= 1024.0 {
+        format!("{:.1} GB", mb / 1024.0)
+    } else {
+        format!("{:.0} MB", mb)
+    }
+}
+
+fn usage_text(models: &[InstalledModel]) -> String {
+    let total: u64 = models.iter().map(|model| model.size_bytes).sum();
+    format!(
+        "{} installed model(s) using {}",
+        models.len(),
+        format_size(total)
+    )
+}
+
+fn partial_text() -> (String, bool) {
+    match storage::stale_partial_files() {
+        (files, _) if files.is_empty() => ("No leftover partial files.".to_string(), false),
+        (files, bytes) => (
+            format!(
+                "{} leftover partial file(s) using {}",
+                files.len(),
+                format_size(bytes)
+            ),
+            true,
+        ),
+    }
+}
+
+fn model_row(model: &InstalledModel, config_state: &Arc<RwLock<Config>>, usage: &Label) -> GtkBox {
+    let row = GtkBox::new(Orientation::Horizontal, 8);
+    let file_name = model
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let last_used = model
+        .last_used
+        .map(|at| format!("last used {}", at.format("%Y-%m-%d")))
+        .unwrap_or_else(|| "not used yet".to_string());
+    let mut text = format!(
+        "{} • {} • {}",
+        model.name.unwrap_or(&file_name),
+        format_size(model.size_bytes),
+        last_used
+    );
+    if let Some(role) = model.role {
+        text.push_str(&format!(" • {}", role));
+    }
+    let label = Label::new(Some(&text));
+    label.set_xalign(0.0);
+    label.set_hexpand(true);
+    label.set_wrap(true);
+    row.append(&label);
+
+    let delete = Button::with_label("Delete");
+    if let Some(role) = model.role {
+        delete.set_sensitive(false);
+        delete.set_tooltip_text(Some(&format!(
+            "This is the {}; choose another model first.",
+            role
+        )));
+    }
+    let armed = Rc::new(Cell::new(false));
+    let path = model.path.clone();
+    let config_state = config_state.clone();
+    let usage = usage.clone();
+    let row_for_delete = row.clone();
+    delete.connect_clicked(move |button| {
+        if !armed.replace(true) {
+            button.set_label("Click again to delete");
+            button.add_css_class("destructive-action");
+            return;
+        }
+        let config = config_state.read().unwrap().clone();
+        match storage::delete_model(&path, &config) {
+            Ok(_) => {
+                row_for_delete.set_visible(false);
+                usage.set_text(&usage_text(&storage::installed_models(&config)));
+            }
+            Err(e) => {
+                error!("Failed to delete model: {:#}", e);
+                button.set_label("Delete failed");
+                button.set_sensitive(false);
+            }
+        }
+    });
+    row.append(&delete);
+    row
+}
+
+/// List installed models with disk usage and deletion, plus partial-file clean-up.
+pub(super) fn create_storage_section(config: &Config, config_state: Arc<RwLock<Config>>) -> GtkBox {
+    let section = GtkBox::new(Orientation::Vertical, 8);
+
+    let title = Label::new(Some("Storage"));
+    title.set_xalign(0.0);
+    title.add_css_class("heading");
+    section.append(&title);
+
+    let models = storage::installed_models(config);
+    let usage = Label::new(Some(&usage_text(&models)));
+    usage.set_xalign(0.0);
+    usage.add_css_class("dim-label");
+    section.append(&usage);
+
+    for model in &models {
+        section.append(&model_row(model, &config_state, &usage));
+    }
+
+    let cleanup_row = GtkBox::new(Orientation::Horizontal, 8);
+    let (text, any) = partial_text();
+    let partial_label = Label::new(Some(&text));
+    partial_label.set_xalign(0.0);
+    partial_label.set_hexpand(true);
+    cleanup_row.append(&partial_label);
+    let cleanup = Button::with_label("Clean Up Partial Files");
+    cleanup.set_tooltip_text(Some(
+        "Removes interrupted downloads and imports that have not changed for an hour.",
+    ));
+    cleanup.set_sensitive(any);
+    cleanup.connect_clicked(move |button| {
+        let (removed, freed) = storage::clean_partial_files();
+        let (text, any) = partial_text();
+        partial_label.set_text(&format!(
+            "Removed {} file(s), freeing {}. {}",
+            removed,
+            format_size(freed),
+            text
+        ));
+        button.set_sensitive(any);
+    });
+    cleanup_row.append(&cleanup);
+    section.append(&cleanup_row);
+
+    section
+}
@@ -0,0 +1,494 @@
+//! Ordered editor for remote transcription endpoints on the Backend tab.
+
+use crate::transcribe::supported_upload_format;
+use crate::{
+    import_remote_client_key, remove_remote_secret, RemoteDialect, RemoteEndpointConfig,
+    RemoteUploadFormat,
+};
+use gtk4::prelude::*;
+use gtk4::{
+    Box as GtkBox, Button, CheckButton, ComboBoxText, Entry, FileChooserAction, FileChooserNative,
+    Frame, Grid, Label, Orientation, ResponseType, SpinButton, TextView, WrapMode,
+};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+use tracing::warn;
+
+/// Working copy of the endpoint list, shared between row widgets and the save button.
+pub(super) type EndpointDraft = Rc<RefCell<Vec<RemoteEndpointConfig>>>;
+
+/// Endpoint list editor together with its live draft.
+#[derive(Clone)]
+pub(super) struct EndpointList {
+    pub widget: GtkBox,
+    pub draft: EndpointDraft,
+    rows: GtkBox,
+}
+
+impl EndpointList {
+    /// Append an endpoint, replacing the blank placeholder row if that is all there is.
+    pub fn push(&self, endpoint: RemoteEndpointConfig) {
+        {
+            let mut endpoints = self.draft.borrow_mut();
+            if endpoints.len() == 1 && endpoints[0].endpoint.is_empty() {
+                endpoints.clear();
+            }
+            endpoints.push(endpoint);
+        }
+        refresh_rows(&self.rows, &self.draft);
+    }
+}
+
+/// Build the endpoint list editor.
+pub(super) fn create_endpoint_list(endpoints: Vec<RemoteEndpointConfig>) -> EndpointList {
+    let section = GtkBox::new(Orientation::Vertical, 8);
+
+    let heading = Label::new(Some("Endpoints (tried in order before local fallback)"));
+    heading.set_xalign(0.0);
+    heading.add_css_class("heading");
+    section.append(&heading);
+
+    let rows = GtkBox::new(Orientation::Vertical, 8);
+    section.append(&rows);
+
+    let draft: EndpointDraft = Rc::new(RefCell::new(if endpoints.is_empty() {
+        vec![RemoteEndpointConfig::default()]
+    } else {
+        endpoints
+    }));
+    refresh_rows(&rows, &draft);
+
+    let add = Button::with_label("Add Endpoint");
+    let rows_for_add = rows.clone();
+    let draft_for_add = draft.clone();
+    add.connect_clicked(move |_| {
+        // A new failover must opt in to receiving the stored key explicitly.
+        draft_for_add.borrow_mut().push(RemoteEndpointConfig {
+            use_stored_api_key: false,
+            ..RemoteEndpointConfig::default()
+        });
+        refresh_rows(&rows_for_add, &draft_for_add);
+    });
+    section.append(&add);
+
+    EndpointList {
+        widget: section,
+        draft,
+        rows,
+    }
+}
+
+fn refresh_rows(container: &GtkBox, draft: &EndpointDraft) {
+    while let Some(child) = container.first_child() {
+        container.remove(&child);
+    }
+
+    let snapshot = draft.borrow().clone();
+    let count = snapshot.len();
+    for (index, endpoint) in snapshot.into_iter().enumerate() {
+        let mut title = if index == 0 {
+            "Primary endpoint".to_string()
+        } else {
+            format!("Failover #{}", index)
+        };
+        if let Some(server_id) = &endpoint.discovered_server_id {
+            title.push_str(&format!(" (pinned LAN server {})", server_id));
+        }
+        let frame = Frame::new(Some(&title));
+        let grid = Grid::new();
+        grid.set_row_spacing(6);
+        grid.set_column_spacing(8);
+        grid.set_margin_top(6);
+        grid.set_margin_bottom(6);
+        grid.set_margin_start(8);
+        grid.set_margin_end(8);
+
+        let url = Entry::new();
+        url.set_hexpand(true);
+        url.set_placeholder_text(Some("https://your-vps.example.com/v1/audio/transcriptions"));
+        url.set_text(&endpoint.endpoint);
+        grid.attach(&Label::new(Some("URL:")), 0, 0, 1, 1);
+        grid.attach(&url, 1, 0, 3, 1);
+
+        let stream_url = Entry::new();
+        stream_url.set_hexpand(true);
+        stream_url.set_placeholder_text(Some("Optional wss:// URL for live partial results"));
+        stream_url.set_text(&endpoint.stream_endpoint);
+        grid.attach(&Label::new(Some("Streaming URL:")), 0, 1, 1, 1);
+        grid.attach(&stream_url, 1, 1, 3, 1);
+
+        let dialect = ComboBoxText::new();
+        dialect.append(Some("openai"), "OpenAI-compatible");
+        dialect.append(Some("whisper_cpp"), "whisper.cpp server");
+        dialect.set_active_id(Some(match endpoint.dialect {
+            RemoteDialect::OpenAi => "openai",
+            RemoteDialect::WhisperCpp => "whisper_cpp",
+        }));
+        grid.attach(&Label::new(Some("Dialect:")), 0, 2, 1, 1);
+        grid.attach(&dialect, 1, 2, 1, 1);
+
+        let model = Entry::new();
+        model.set_placeholder_text(Some("whisper-1"));
+        model.set_text(&endpoint.model);
+        grid.attach(&Label::new(Some("Model:")), 2, 2, 1, 1);
+        grid.attach(&model, 3, 2, 1, 1);
+
+        let timeout = SpinButton::with_range(1_000.0, 120_000.0, 500.0);
+        timeout.set_value(endpoint.timeout_ms as f64);
+        grid.attach(&Label::new(Some("Timeout (ms):")), 0, 3, 1, 1);
+        grid.attach(&timeout, 1, 3, 1, 1);
+
+        let env = Entry::new();
+        env.set_placeholder_text(Some("OSWISPA_REMOTE_API_KEY"));
+        env.set_text(endpoint.api_key_env.as_deref().unwrap_or(""));
+        grid.attach(&Label::new(Some("API key env var:")), 2, 3, 1, 1);
+        grid.attach(&env, 3, 3, 1, 1);
+
+        let upload_format = ComboBoxText::new();
+        upload_format.append(Some("wav"), "WAV (uncompressed)");
+        upload_format.append(Some("flac"), "FLAC (lossless)");
+        #[cfg(feature = "remote-opus")]
+        upload_format.append(Some("opus"), "Opus (smallest)");
+        upload_format.set_active_id(Some(
+            match supported_upload_format(endpoint.upload_format) {
+                RemoteUploadFormat::Wav => "wav",
+                RemoteUploadFormat::Flac => "flac",
+                RemoteUploadFormat::Opus => "opus",
+            },
+        ));
+        upload_format.set_tooltip_text(Some(
+            "Compressed uploads are faster on slow links; the server must be able to decode them",
+        ));
+        grid.attach(&Label::new(Some("Upload as:")), 0, 4, 1, 1);
+        grid.attach(&upload_format, 1, 4, 1, 1);
+
+        let ca_bundle = Entry::new();
+        ca_bundle.set_placeholder_text(Some("Optional PEM bundle for a private CA"));
+        ca_bundle.set_text(&path_text(&endpoint.ca_bundle_path));
+        grid.attach(&Label::new(Some("CA bundle:")), 0, 5, 1, 1);
+        grid.attach(&ca_bundle, 1, 5, 3, 1);
+
+        let client_cert = Entry::new();
+        client_cert.set_placeholder_text(Some("Optional PEM certificate for mutual TLS"));
+        client_cert.set_text(&path_text(&endpoint.client_cert_path));
+        grid.attach(&Label::new(Some("Client certificate:")), 0, 6, 1, 1);
+        grid.attach(&client_cert, 1, 6, 3, 1);
+
+        let key_box = GtkBox::new(Orientation::Horizontal, 8);
+        let key_label = Label::new(Some(
+            endpoint
+                .client_key_secret
+                .as_deref()
+                .unwrap_or("No key imported"),
+        ));
+        key_label.set_xalign(0.0);
+        key_label.set_hexpand(true);
+        let import_key = Button::with_label("Import Key…");
+        import_key.set_tooltip_text(Some(
+            "Copies an unencrypted PKCS#8 PEM key into the private secrets folder",
+        ));
+        let clear_key = Button::with_label("Clear Key");
+        clear_key.set_tooltip_text(Some(
+            "The key file is deleted from the secrets folder when you save",
+        ));
+        clear_key.set_sensitive(endpoint.client_key_secret.is_some());
+        key_box.append(&key_label);
+        key_box.append(&import_key);
+        key_box.append(&clear_key);
+        grid.attach(&Label::new(Some("Client key:")), 0, 7, 1, 1);
+        grid.attach(&key_box, 1, 7, 3, 1);
+
+        let headers = TextView::new();
+        headers.set_wrap_mode(WrapMode::None);
+        headers.set_monospace(true);
+        headers.set_tooltip_text(Some("One header per line, e.g. X-Team-Id: speech"));
+        headers
+            .buffer()
+            .set_text(&headers_text(&endpoint.extra_headers));
+        let headers_frame = Frame::new(None);
+        headers_frame.set_child(Some(&headers));
+        grid.attach(&Label::new(Some("Extra headers:")), 0, 8, 1, 1);
+        grid.attach(&headers_frame, 1, 8, 3, 1);
+
+        let stored_key = CheckButton::with_label("Send stored API key");
+        stored_key.set_active(endpoint.use_stored_api_key);
+        grid.attach(&stored_key, 0, 9, 2, 1);
+
+        let insecure = CheckButton::with_label("Allow insecure HTTP (not recommended)");
+        insecure.set_active(endpoint.allow_insecure_http);
+        grid.attach(&insecure, 2, 9, 2, 1);
+
+        let forward_prompt = CheckButton::with_label(
+            "Send my vocabulary prompt (dictionary terms leave this device)",
+        );
+        forward_prompt.set_active(endpoint.forward_vocabulary_prompt);
+        grid.attach(&forward_prompt, 0, 10, 2, 1);
+
+        let verbose_json = CheckButton::with_label("Request verbose_json responses");
+        verbose_json.set_active(endpoint.request_verbose_json);
+        grid.attach(&verbose_json, 2, 10, 2, 1);
+
+        let actions = GtkBox::new(Orientation::Horizontal, 8);
+        let up = Button::with_label("Move Up");
+        up.set_sensitive(index > 0);
+        let down = Button::with_label("Move Down");
+        down.set_sensitive(index + 1 < count);
+        let remove = Button::with_label("Remove");
+        remove.add_css_class("destructive-action");
+        remove.set_sensitive(count > 1);
+        actions.append(&up);
+        actions.append(&down);
+        actions.append(&remove);
+        grid.attach(&actions, 0, 11, 4, 1);
+
+        frame.set_child(Some(&grid));
+        container.append(&frame);
+
+        let sync: Rc<dyn Fn()> = Rc::new({
+            let draft = draft.clone();
+            let url = url.clone();
+            let stream_url = stream_url.clone();
+            let dialect = dialect.clone();
+            let model = model.clone();
+            let timeout = timeout.clone();
+            let env = env.clone();
+            let upload_format = upload_format.clone();
+            let ca_bundle = ca_bundle.clone();
+            let client_cert = client_cert.clone();
+            let headers = headers.clone();
+            let stored_key = stored_key.clone();
+            let insecure = insecure.clone();
+            let forward_prompt = forward_prompt.clone();
+            let verbose_json = verbose_json.clone();
+            move || {
+                let mut endpoints = draft.borrow_mut();
+                let Some(entry) = endpoints.get_mut(index) else {
+                    return;
+                };
+                entry.endpoint = url.text().trim().to_string();
+                entry.stream_endpoint = stream_url.text().trim().to_string();
+                entry.dialect = match dialect.active_id().as_deref() {
+                    Some("whisper_cpp") => RemoteDialect::WhisperCpp,
+                    _ => RemoteDialect::OpenAi,
+                };
+                entry.model = match model.text().trim() {
+                    "" => "whisper-1".to_string(),
+                    value => value.to_string(),
+                };
+                entry.timeout_ms = timeout.value() as u64;
+                entry.api_key_env = match env.text().trim() {
+                    "" => None,
+                    value => Some(value.to_string()),
+                };
+                entry.upload_format = match upload_format.active_id().as_deref() {
+                    Some("flac") => RemoteUploadFormat::Flac,
+                    Some("opus") => RemoteUploadFormat::Opus,
+                    _ => RemoteUploadFormat::Wav,
+                };
+                entry.ca_bundle_path = path_from_text(&ca_bundle.text());
+                entry.client_cert_path = path_from_text(&client_cert.text());
+                let buffer = headers.buffer();
+                entry.extra_headers =
+                    parse_headers(&buffer.text(&buffer.start_iter(), &buffer.end_iter(), false));
+                entry.use_stored_api_key = stored_key.is_active();
+                entry.allow_insecure_http = insecure.is_active();
+                entry.forward_vocabulary_prompt = forward_prompt.is_active();
+                entry.request_verbose_json = verbose_json.is_active();
+            }
+        });
+
+        let sync_clone = sync.clone();
+        url.connect_changed(move |_| sync_clone());
+        let sync_clone = sync.clone();
+        stream_url.connect_changed(move |_| sync_clone());
+        let sync_clone = sync.clone();
+        dialect.connect_changed(move |_| sync_clone());
+        let sync_clone = sync.clone();
+        model.connect_changed(move |_| sync_clone());
+        let sync_clone = sync.clone();
+        timeout.connect_value_changed(move |_| sync_clone());
+        let sync_clone = sync.clone();
+        env.connect_changed(move |_| sync_clone());
+        let sync_clone = sync.clone();
+        upload_format.connect_changed(move |_| sync_clone());
+        let sync_clone = sync.clone();
+        ca_bundle.connect_changed(move |_| sync_clone());
+        let sync_clone = sync.clone();
+        client_cert.connect_changed(move |_| sync_clone());
+        let sync_clone = sync.clone();
+        headers.buffer().connect_changed(move |_| sync_clone());
+        let sync_clone = sync.clone();
+        stored_key.connect_toggled(move |_| sync_clone());
+        let sync_clone = sync.clone();
+        insecure.connect_toggled(move |_| sync_clone());
+        let sync_clone = sync.clone();
+        forward_prompt.connect_toggled(move |_| sync_clone());
+        let sync_clone = sync.clone();
+        verbose_json.connect_toggled(move |_| sync_clone());
+
+        let container_for_import = container.clone();
+        let draft_for_import = draft.clone();
+        let key_label_for_import = key_label.clone();
+        import_key.connect_clicked(move |_| {
+            let chooser = FileChooserNative::new(
+                Some("Import client key"),
+                None::<&gtk4::Window>,
+                FileChooserAction::Open,
+                Some("Import"),
+                Some("Cancel"),
+            );
+            let container = container_for_import.clone();
+            let draft = draft_for_import.clone();
+            let key_label = key_label_for_import.clone();
+            chooser.connect_response(move |chooser, response| {
+                if response != ResponseType::Accept {
+                    return;
+                }
+                let Some(path) = chooser.file().and_then(|file| file.path()) else {
+                    key_label.set_text("The selected key is not a local file.");
+                    return;
+                };
+                match import_remote_client_key(&path) {
+                    Ok(name) => {
+                        if let Some(entry) = draft.borrow_mut().get_mut(index) {
+                            entry.client_key_secret = Some(name);
+                        }
+                        refresh_rows(&container, &draft);
+                    }
+                    Err(error) => key_label.set_text(&format!("Could not import key: {error}")),
+                }
+            });
+            chooser.show();
+        });
+
+        let container_for_clear = container.clone();
+        let draft_for_clear = draft.clone();
+        clear_key.connect_clicked(move |_| {
+            if let Some(entry) = draft_for_clear.borrow_mut().get_mut(index) {
+                entry.client_key_secret = None;
+            }
+            refresh_rows(&container_for_clear, &draft_for_clear);
+        });
+
+        let container_for_up = container.clone();
+        let draft_for_up = draft.clone();
+        up.connect_clicked(move |_| {
+            draft_for_up.borrow_mut().swap(index - 1, index);
+            refresh_rows(&container_for_up, &draft_for_up);
+        });
+
+        let container_for_down = container.clone();
+        let draft_for_down = draft.clone();
+        down.connect_clicked(move |_| {
+            draft_for_down.borrow_mut().swap(index, index + 1);
+            refresh_rows(&container_for_down, &draft_for_down);
+        });
+
+        let container_for_remove = container.clone();
+        let draft_for_remove = draft.clone();
+        remove.connect_clicked(move |_| {
+            let mut endpoints = draft_for_remove.borrow_mut();
+            if endpoints.len() > 1 && index < endpoints.len() {
+                endpoints.remove(index);
+            }
+            drop(endpoints);
+            refresh_rows(&container_for_remove, &draft_for_remove);
+        });
+    }
+}
+
+fn path_text(path: &Option<PathBuf>) -> String {
+    path.as_ref()
+        .map(|path| path.display().to_string())
+        .unwrap_or_default()
+}
+
+fn path_from_text(text: &str) -> Option<PathBuf> {
+    match text.trim() {
+        "" => None,
+        value => Some(PathBuf::from(value)),
+    }
+}
+
+fn headers_text(headers: &BTreeMap<String, String>) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parse `Name: value` lines; a line without a colon keeps an empty value so validation flags it.
+fn parse_headers(text: &str) -> BTreeMap<String, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once(':') {
+            Some((name, value)) => (name.trim().to_string(), value.trim().to_string()),
+            None => (line.to_string(), String::new()),
+        })
+        .collect()
+}
+
+/// Delete client keys the saved endpoints no longer reference, e.g. after "Clear Key".
+pub(super) fn remove_released_client_keys(
+    previous: &[RemoteEndpointConfig],
+    current: &[RemoteEndpointConfig],
+) {
+    for name in released_client_keys(previous, current) {
+        if let Err(error) = remove_remote_secret(name) {
+            warn!("Failed to delete client key {}: {}", name, error);
+        }
+    }
+}
+
+fn released_client_keys<'a>(
+    previous: &'a [RemoteEndpointConfig],
+    current: &[RemoteEndpointConfig],
+) -> Vec<&'a str> {
+    let mut released: Vec<&str> = previous
+        .iter()
+        .filter_map(|endpoint| endpoint.client_key_secret.as_deref())
+        .filter(|name| {
+            !current
+                .iter()
+                .any(|endpoint| endpoint.client_key_secret.as_deref() == Some(*name))
+        })
+        .collect();
+    released.sort_unstable();
+    released.dedup();
+    released
+}
+
+/// Check the draft before it is persisted; returns a user-facing reason on failure.
+pub(super) fn validate_endpoint_draft(endpoints: &[RemoteEndpointConfig]) -> Result<(), String> {
+    if endpoints
+        .iter()
+        .all(|endpoint| endpoint.endpoint.is_empty())
+    {
+        return Err("Remote backend selected but no endpoint URL is set".to_string());
+    }
+
+    for (index, endpoint) in endpoints.iter().enumerate() {
+        if endpoint.endpoint.starts_with("http://") && !endpoint.allow_insecure_http {
+            return Err(format!(
+                "Endpoint #{} uses HTTP; enable insecure HTTP only if you explicitly trust the network",
+                index + 1
+            ));
+        }
+        if endpoint.stream_endpoint.starts_with("ws://") && !endpoint.allow_insecure_http {
+            return Err(format!(
+                "Endpoint #{} streams over plain ws://; enable insecure HTTP only if you explicitly trust the network",
+                index + 1
+            ));
+        }
+        endpoint
+            .validate_client_identity()
+            .and_then(|_| endpoint.validate_extra_headers())
+            .map_err(|error| format!("Endpoint #{}: {}", index + 1, error))?;
+    }
+
+    Ok(())
+}
@@ -5,13 +5,15 @@
 //! downloads it automatically. Manual selection is still available via an
 //! environment override for power users.
 
+use crate::gpu::InventorySource;
+use crate::models::benchmark::{self, ModelMeasurements};
 use crate::models::{self, ModelInfo, AVAILABLE_MODELS};
 use anyhow::Result;
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
 use std::hint::black_box;
 use std::io::{self, Write};
 use std::path::PathBuf;
-use std::process::Command;
 use std::time::{Duration, Instant};
 use tracing::{debug, info};
 
@@ -123,47 +125,40 @@ fn detect_hardware() -> HardwareProfile {
 
     // Linux / other: detect available GPU memory and only mark acceleration
     // active when the current binary was actually built with that backend.
-    if let Some((total, free)) = detect_nvidia_vram() {
-        return HardwareProfile {
-            gpu: GpuType::Nvidia,
-            acceleration: if cfg!(feature = "gpu-cuda") {
+    let inventory = crate::gpu::cached_inventory();
+    let detected = match inventory.source {
+        InventorySource::NvidiaSmi => inventory.device(None).map(|info| {
+            let acceleration = if cfg!(feature = "gpu-cuda") {
                 RuntimeAcceleration::Cuda
             } else {
                 RuntimeAcceleration::CpuOnly
-            },
-            vram_mb: Some(free.max(total / 2)),
-            system_memory_mb,
-            logical_cpus,
-            cpu_probe,
-            cpu_tier,
-        };
-    }
-
-    if let Some(available) = detect_amd_vram_sysfs() {
-        return HardwareProfile {
-            gpu: GpuType::Amd,
-            acceleration: if cfg!(feature = "gpu-hipblas") {
-                RuntimeAcceleration::HipBlas
-            } else {
-                RuntimeAcceleration::CpuOnly
-            },
-            vram_mb: Some(available),
-            system_memory_mb,
-            logical_cpus,
-            cpu_probe,
-            cpu_tier,
-        };
-    }
-
-    if let Some(available) = detect_amd_vram_rocm_smi() {
+            };
+            let vram = info.available_bytes().max(info.total_bytes / 2);
+            (GpuType::Nvidia, acceleration, vram)
+        }),
+        InventorySource::AmdSysfs | InventorySource::RocmSmi => inventory
+            .device(crate::gpu::rocm_visible_device_index())
+            .map(|info| {
+                let acceleration = if cfg!(feature = "gpu-hipblas") {
+                    RuntimeAcceleration::HipBlas
+                } else {
+                    RuntimeAcceleration::CpuOnly
+                };
+                (GpuType::Amd, acceleration, info.available_bytes())
+            }),
+        InventorySource::Unavailable => None,
+    };
+    if let Some((gpu, acceleration, vram_bytes)) = detected {
+        debug!(
+            "{:?} GPU from {:?}: {} MB available",
+            gpu,
+            inventory.source,
+            vram_bytes / (1024 * 1024)
+        );
         return HardwareProfile {
-            gpu: GpuType::Amd,
-            acceleration: if cfg!(feature = "gpu-hipblas") {
-                RuntimeAcceleration::HipBlas
-            } else {
-                RuntimeAcceleration::CpuOnly
-            },
-            vram_mb: Some(available),
+            gpu,
+            acceleration,
+            vram_mb: Some(vram_bytes / (1024 * 1024)),
             system_memory_mb,
             logical_cpus,
             cpu_probe,
@@ -227,7 +222,7 @@ fn detect_system_memory_mb() -> Option<u64> {
 
     #[cfg(target_os = "macos")]
     {
-        let output = Command::new("sysctl")
+        let output = std::process::Command::new("sysctl")
             .args(["-n", "hw.memsize"])
             .output()
             .ok()?;
@@ -245,36 +240,6 @@ fn detect_system_memory_mb() -> Option<u64> {
     None
 }
 
-/// nvidia-smi query -> (total_mb, free_mb)
-fn detect_nvidia_vram() -> Option<(u64, u64)> {
-    let output = Command::new("nvidia-smi")
-        .args([
-            "--query-gpu=memory.total,memory.free",
-            "--format=csv,noheader,nounits",
-        ])
-        .output()
-        .ok()?;
-
-    let stdout = String::from_utf8(output.stdout).ok()?;
-    let line = stdout.lines().next()?.trim();
-    let mut parts = line.split(',');
-    let total: u64 = parts.next()?.trim().parse().ok()?;
-    let free: u64 = parts.next()?.trim().parse().ok()?;
-    debug!("nvidia-smi: total={}MB, free={}MB", total, free);
-    Some((total, free))
-}
-
-/// AMD sysfs -> available MB (total - used)
-fn detect_amd_vram_sysfs() -> Option<u64> {
-    crate::gpu::detect_amd_sysfs_available_bytes().map(|bytes| bytes / (1024 * 1024))
-}
-
-/// rocm-smi fallback -> available MB
-fn detect_amd_vram_rocm_smi() -> Option<u64> {
-    crate::gpu::detect_rocm_smi_available_bytes(crate::gpu::rocm_visible_device_index())
-        .map(|bytes| bytes / (1024 * 1024))
-}
-
 // ---------------------------------------------------------------------------
 // Model recommendation
 // ---------------------------------------------------------------------------
@@ -285,9 +250,11 @@ struct ModelRecommendation {
     reason: String,
 }
 
+/// The catalogue entry for `filename`, or the built-in one when a manifest omits it.
 fn find_model(filename: &str) -> &'static ModelInfo {
-    AVAILABLE_MODELS
+    models::catalogue()
         .iter()
+        .chain(AVAILABLE_MODELS)
         .find(|model| model.filename == filename)
         .unwrap_or_else(|| panic!("Missing built-in model definition for {}", filename))
 }
@@ -418,6 +385,103 @@ fn recommend_models(hw: &HardwareProfile) -> Vec<ModelRecommendation> {
         .collect()
 }
 
+/// Prefer a model already measured on this machine over the hardware estimate.
+///
+/// `measured` pairs indexes into `recommendations` with cached benchmarks of
+/// installed models; without any, the hardware-based choice stands.
+fn prefer_measured(
+    recommendations: &mut [ModelRecommendation],
+    measured: &[(usize, ModelMeasurements)],
+) {
+    let candidates: Vec<(u64, ModelMeasurements)> = measured
+        .iter()
+        .map(|(index, measurements)| (recommendations[*index].model.size_mb as u64, *measurements))
+        .collect();
+    let Some(choice) = benchmark::recommend_measured(&candidates) else {
+        return;
+    };
+    let (index, measurements) = measured[choice];
+    let Some((device, measurement)) = measurements.best() else {
+        return;
+    };
+
+    for recommendation in recommendations.iter_mut() {
+        recommendation.recommended = false;
+    }
+    let chosen = &mut recommendations[index];
+    chosen.recommended = true;
+    chosen.reason = format!(
+        "Already installed and measured on this machine at {:.2}x real time on the {}.",
+        measurement.real_time_factor, device
+    );
+}
+
+fn installed_measurements(
+    recommendations: &[ModelRecommendation],
+) -> Vec<(usize, ModelMeasurements)> {
+    recommendations
+        .iter()
+        .enumerate()
+        .filter(|(_, recommendation)| models::is_model_installed(recommendation.model))
+        .map(|(index, recommendation)| {
+            (
+                index,
+                benchmark::measured(&models::get_model_path(recommendation.model)),
+            )
+        })
+        .filter(|(_, measurements)| measurements.best().is_some())
+        .collect()
+}
+
+/// Wizard models for `hw`, with the measured or hardware-based choice marked.
+fn ranked_recommendations(hw: &HardwareProfile) -> Vec<ModelRecommendation> {
+    let mut recommendations = recommend_models(hw);
+    let measured = installed_measurements(&recommendations);
+    prefer_measured(&mut recommendations, &measured);
+    recommendations
+}
+
+/// Device probe results, as reported by `oswispa setup`.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct HardwareReport {
+    pub machine: String,
+    pub runtime: String,
+    pub vram_mb: Option<u64>,
+    pub system_memory_mb: Option<u64>,
+    pub logical_cpus: usize,
+    pub cpu_tier: String,
+    pub cpu_probe_ms: u64,
+}
+
+impl From<&HardwareProfile> for HardwareReport {
+    fn from(hw: &HardwareProfile) -> Self {
+        Self {
+            machine: hw.gpu.to_string(),
+            runtime: hw.acceleration.to_string(),
+            vram_mb: hw.vram_mb,
+            system_memory_mb: hw.system_memory_mb,
+            logical_cpus: hw.logical_cpus,
+            cpu_tier: hw.cpu_tier.to_string(),
+            cpu_probe_ms: hw.cpu_probe.as_millis() as u64,
+        }
+    }
+}
+
+/// Probe this machine and return the model the wizard would choose, with its reason.
+pub(crate) fn recommend_for_this_machine() -> (HardwareReport, &'static ModelInfo, String) {
+    let hw = detect_hardware();
+    let recommendations = ranked_recommendations(&hw);
+    let chosen = recommendations
+        .iter()
+        .find(|recommendation| recommendation.recommended)
+        .unwrap_or(&recommendations[0]);
+    (
+        HardwareReport::from(&hw),
+        chosen.model,
+        chosen.reason.clone(),
+    )
+}
+
 fn manual_selection_requested() -> bool {
     matches!(
         std::env::var("OSWISPA_SETUP_MANUAL")
@@ -443,7 +507,7 @@ pub fn run_first_time_setup() -> Result<PathBuf> {
     eprintln!();
 
     let hw = detect_hardware();
-    let recommendations = recommend_models(&hw);
+    let recommendations = ranked_recommendations(&hw);
     let default_idx = recommendations
         .iter()
         .position(|r| r.recommended)
@@ -509,13 +573,19 @@ pub fn run_first_time_setup() -> Result<PathBuf> {
         eprintln!("  (The OSWISPA_ name is retained for compatibility.)");
     }
     eprintln!();
-    eprintln!("  Downloading {}...", chosen.model.name);
-    eprintln!();
-
-    let path = download_model_with_progress(chosen.model)?;
-    info!("First-time setup: downloaded model to {:?}", path);
+    let path = if models::is_model_installed(chosen.model) {
+        eprintln!("  {} is already installed.", chosen.model.name);
+        models::get_model_path(chosen.model)
+    } else {
+        models::check_download_space(chosen.model)?;
+        eprintln!("  Downloading {}...", chosen.model.name);
+        eprintln!();
+        let path = download_model_with_progress(chosen.model)?;
+        info!("First-time setup: downloaded model to {:?}", path);
+        eprintln!();
+        path
+    };
 
-    eprintln!();
     eprintln!("  ✓ Model installed successfully.");
     eprintln!("  ✓ MorpheOS Voice is ready — starting up...");
     eprintln!();
@@ -665,4 +735,42 @@ mod tests {
             .iter()
             .any(|model| model.filename == "ggml-distil-large-v3.bin"));
     }
+
+    #[test]
+    fn measured_models_override_the_hardware_estimate() {
+        let mut recommendations = recommend_models(&profile(
+            RuntimeAcceleration::CpuOnly,
+            Some(8_000),
+            None,
+            4,
+            CpuTier::Good,
+        ));
+        let base = recommendations
+            .iter()
+            .position(|rec| rec.model.filename == "ggml-base.en.bin")
+            .unwrap();
+        let small = recommendations
+            .iter()
+            .position(|rec| rec.model.filename == "ggml-small.en.bin")
+            .unwrap();
+        assert!(recommendations[base].recommended);
+
+        prefer_measured(&mut recommendations, &[]);
+        assert!(recommendations[base].recommended);
+
+        let measured = |real_time_factor| ModelMeasurements {
+            cpu: Some(benchmark::Measurement {
+                load_ms: 300,
+                real_time_factor,
+            }),
+            gpu: None,
+        };
+        prefer_measured(
+            &mut recommendations,
+            &[(base, measured(0.1)), (small, measured(0.35))],
+        );
+        assert!(recommendations[small].recommended);
+        assert!(!recommendations[base].recommended);
+        assert!(recommendations[small].reason.contains("0.35x"));
+    }
 }
@@ -0,0 +1,155 @@
+//! Splitting long recordings at pauses so chunks can be transcribed in parallel.
+//!
+//! Each chunk stays inside Whisper's 30 second window. Cuts are placed in the
+//! quietest stretch of the second half of each window, and every chunk runs a
+//! little past its cut so a word spoken across the boundary is heard whole by
+//! at least one chunk; the repeated words are removed when the texts are
+//! stitched back together.
+
+use super::{append_stream_text, is_garbage_output};
+use crate::LongDictationConfig;
+use std::ops::Range;
+
+pub(super) const SAMPLE_RATE: usize = 16_000;
+/// Energy is compared over 20 ms frames
+const FRAME_SAMPLES: usize = SAMPLE_RATE / 50;
+/// Audio shared by neighbouring chunks
+const OVERLAP_SAMPLES: usize = SAMPLE_RATE / 2;
+const MIN_CHUNK_SECONDS: u32 = 5;
+const MAX_CHUNK_SECONDS: u32 = 28;
+
+/// Sample ranges to transcribe separately, or `None` when the recording is short enough for one pass.
+pub(super) fn plan_for(config: &LongDictationConfig, samples: &[f32]) -> Option<Vec<Range<usize>>> {
+    if !config.enabled || samples.len() <= config.threshold_seconds as usize * SAMPLE_RATE {
+        return None;
+    }
+    let max_chunk = config
+        .max_chunk_seconds
+        .clamp(MIN_CHUNK_SECONDS, MAX_CHUNK_SECONDS) as usize
+        * SAMPLE_RATE;
+    let chunks = split_at_pauses(samples, max_chunk, OVERLAP_SAMPLES);
+    (chunks.len() > 1).then_some(chunks)
+}
+
+/// Cut `samples` into ranges of at most `max_chunk + overlap` samples.
+pub(super) fn split_at_pauses(
+    samples: &[f32],
+    max_chunk: usize,
+    overlap: usize,
+) -> Vec<Range<usize>> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while samples.len() - start > max_chunk {
+        // Search the second half of the window, leaving enough for a useful last chunk.
+        let search_start = start + max_chunk / 2;
+        let search_end = (start + max_chunk).min(samples.len() - max_chunk / 4);
+        let cut = quietest_frame(samples, search_start, search_end);
+        chunks.push(start..(cut + overlap).min(samples.len()));
+        start = cut;
+    }
+    chunks.push(start..samples.len());
+    chunks
+}
+
+/// Centre of the lowest-energy frame in `from..to`; later frames win ties.
+fn quietest_frame(samples: &[f32], from: usize, to: usize) -> usize {
+    let mut best = (f32::INFINITY, to);
+    let mut frame_start = from;
+    while frame_start + FRAME_SAMPLES <= to {
+        let frame = &samples[frame_start..frame_start + FRAME_SAMPLES];
+        let energy = frame.iter().map(|sample| sample * sample).sum::<f32>();
+        if energy <= best.0 {
+            best = (energy, frame_start + FRAME_SAMPLES / 2);
+        }
+        frame_start += FRAME_SAMPLES;
+    }
+    best.1
+}
+
+/// Join chunk transcripts in order, dropping words repeated across the overlap and blank chunks.
+pub(super) fn stitch<'a>(texts: impl IntoIterator<Item = &'a str>) -> String {
+    let mut transcript = String::new();
+    for text in texts {
+        if !is_garbage_output(text) {
+            append_stream_text(&mut transcript, text);
+        }
+    }
+    transcript
+}
+
+/// Chunks transcribed at once on the CPU when no worker count is configured.
+pub(super) fn default_workers() -> usize {
+    (num_cpus::get() / 4).clamp(1, 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn speech_with_pauses(seconds: usize, pauses: &[Range<usize>]) -> Vec<f32> {
+        (0..seconds * SAMPLE_RATE)
+            .map(|i| {
+                if pauses.iter().any(|pause| pause.contains(&i)) {
+                    0.0
+                } else {
+                    0.3 * ((i as f32) * 0.07).sin()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cuts_land_in_pauses_and_cover_the_recording() {
+        let pause = 17 * SAMPLE_RATE..18 * SAMPLE_RATE;
+        let samples = speech_with_pauses(40, std::slice::from_ref(&pause));
+        let chunks = split_at_pauses(&samples, 25 * SAMPLE_RATE, OVERLAP_SAMPLES);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].start, 0);
+        assert!(pause.contains(&chunks[1].start));
+        assert_eq!(chunks[0].end, chunks[1].start + OVERLAP_SAMPLES);
+        assert_eq!(chunks[1].end, samples.len());
+    }
+
+    #[test]
+    fn chunks_stay_inside_the_whisper_window_without_pauses() {
+        let samples = speech_with_pauses(130, &[]);
+        let config = LongDictationConfig {
+            max_chunk_seconds: 60,
+            ..LongDictationConfig::default()
+        };
+        let chunks = plan_for(&config, &samples).unwrap();
+
+        assert!(chunks.len() >= 5);
+        for pair in chunks.windows(2) {
+            assert!(pair[1].start < pair[0].end);
+        }
+        for chunk in &chunks {
+            assert!(chunk.len() <= MAX_CHUNK_SECONDS as usize * SAMPLE_RATE + OVERLAP_SAMPLES);
+            assert!(chunk.len() >= 4 * SAMPLE_RATE);
+        }
+        assert_eq!(chunks.last().unwrap().end, samples.len());
+
+        let short = speech_with_pauses(30, &[]);
+        assert!(plan_for(&LongDictationConfig::default(), &short).is_none());
+        let disabled = LongDictationConfig {
+            enabled: false,
+            ..LongDictationConfig::default()
+        };
+        assert!(plan_for(&disabled, &samples).is_none());
+    }
+
+    #[test]
+    fn stitching_drops_overlap_and_blank_chunks() {
+        let text = stitch([
+            "The quarterly report is",
+            "report is due on Friday.",
+            "[BLANK_AUDIO]",
+            "Please send it to Sam.",
+        ]);
+        assert_eq!(
+            text,
+            "The quarterly report is due on Friday. Please send it to Sam."
+        );
+    }
+}
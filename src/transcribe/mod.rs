@@ -7,31 +7,69 @@
 //! - Automatic fallback to smaller model when VRAM is constrained
 //! - CPU fallback when GPU is unavailable
 
+mod chunking;
+mod remote;
+mod remote_stream;
+mod routing;
+mod upload;
+
+pub(crate) use remote::describe_remote_request;
+pub(crate) use routing::{check_language_pin, unknown_candidate_languages};
+pub(crate) use upload::supported_upload_format;
+
 use crate::{
-    personalisation::Personalisation, AppEvent, Config, StreamingAudioMessage, TranscriptionBackend,
+    personalisation::Personalisation, AppEvent, Config, GpuPolicy, StreamingAudioMessage,
+    TranscriptionBackend,
 };
 use anyhow::Result;
 use crossbeam_channel::{select, Receiver, Sender};
-use reqwest::blocking::{multipart, Client};
-use serde_json::Value;
-use std::io::Read;
+use std::collections::BTreeMap;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use whisper_rs::{
+    FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState,
+};
 
 /// Minimum available VRAM in bytes to attempt GPU transcription (2GB)
 const MIN_VRAM_BYTES: u64 = 2 * 1024 * 1024 * 1024;
 /// Keep a conservative reserve so MorpheOS Voice does not consume the last chunk of free VRAM.
 const GPU_RESERVED_HEADROOM_BYTES: u64 = 6 * 1024 * 1024 * 1024;
-const MAX_REMOTE_RESPONSE_BYTES: u64 = 2 * 1024 * 1024;
+/// How often the worker checks for idle contexts to unload
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Text of a finished transcription and the language it was transcribed as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Transcript {
+    pub text: String,
+    /// Whisper language code; `None` when the backend was left to decide and did not say
+    pub language: Option<String>,
+}
+
+impl Transcript {
+    fn new(text: String, config: &Config) -> Self {
+        Self::with_language(text, None, config)
+    }
+
+    /// Prefer the language the backend reported over the configured one.
+    fn with_language(text: String, detected: Option<String>, config: &Config) -> Self {
+        Self {
+            text,
+            language: detected
+                .or_else(|| (config.language != "auto").then(|| config.language.clone())),
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct CacheSignature {
     backend: TranscriptionBackend,
     model_path: PathBuf,
     fallback_model_path: Option<PathBuf>,
+    language_models: BTreeMap<String, PathBuf>,
 }
 
 impl From<&Config> for CacheSignature {
@@ -40,6 +78,7 @@ impl From<&Config> for CacheSignature {
             backend: config.backend.clone(),
             model_path: config.model_path.clone(),
             fallback_model_path: config.fallback_model_path.clone(),
+            language_models: config.language_models.clone(),
         }
     }
 }
@@ -47,7 +86,47 @@ impl From<&Config> for CacheSignature {
 struct CachedContext {
     model_path: PathBuf,
     use_gpu: bool,
+    /// Estimated resident size, taken from the model file
+    size_bytes: u64,
+    last_used: Instant,
     ctx: WhisperContext,
+    /// Decoder state reused across passes so its buffers are allocated once
+    state: Option<WhisperState>,
+}
+
+impl CachedContext {
+    /// Run `pass` with this context's reusable state.
+    ///
+    /// `whisper_full_with_state` clears previous segments and, with
+    /// `no_context`, ignores earlier tokens, so a successful pass leaves the
+    /// state ready for the next one. A failed pass drops it and the next use
+    /// starts from a fresh state.
+    fn with_state<T>(&mut self, pass: impl FnOnce(&mut WhisperState) -> Result<T>) -> Result<T> {
+        self.with_states(1, |states| pass(&mut states[0]))
+    }
+
+    /// Like [`Self::with_state`] with `count` states for concurrent passes.
+    ///
+    /// Only the first state is kept afterwards; the extra ones are freed so
+    /// parallel chunking does not hold several KV caches between dictations.
+    fn with_states<T>(
+        &mut self,
+        count: usize,
+        pass: impl FnOnce(&mut [WhisperState]) -> Result<T>,
+    ) -> Result<T> {
+        let mut states = Vec::with_capacity(count.max(1));
+        if let Some(state) = self.state.take() {
+            states.push(state);
+        }
+        while states.len() < count.max(1) {
+            states.push(self.ctx.create_state()?);
+        }
+        let result = pass(&mut states);
+        if result.is_ok() {
+            self.state = states.into_iter().next();
+        }
+        result
+    }
 }
 
 #[derive(Default)]
@@ -55,9 +134,39 @@ struct LiveStreamState {
     transcript: String,
 }
 
+/// Memory budgets (in bytes) and GPU device for cached contexts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct CacheSettings {
+    ram_bytes: Option<u64>,
+    vram_bytes: Option<u64>,
+    gpu_device: Option<usize>,
+}
+
+impl From<&Config> for CacheSettings {
+    fn from(config: &Config) -> Self {
+        let mib = |value: u64| value.saturating_mul(1024 * 1024);
+        Self {
+            ram_bytes: config.model_cache.ram_budget_mb.map(mib),
+            vram_bytes: config.model_cache.vram_budget_mb.map(mib),
+            gpu_device: config.gpu.device_index,
+        }
+    }
+}
+
+impl CacheSettings {
+    fn budget_for(&self, use_gpu: bool) -> Option<u64> {
+        if use_gpu {
+            self.vram_bytes
+        } else {
+            self.ram_bytes
+        }
+    }
+}
+
 #[derive(Default)]
 struct ContextCache {
     contexts: Vec<CachedContext>,
+    settings: CacheSettings,
 }
 
 impl ContextCache {
@@ -71,7 +180,60 @@ impl ContextCache {
             .any(|entry| entry.use_gpu == use_gpu && entry.model_path == model_path)
     }
 
-    fn get_or_create(&mut self, model_path: &Path, use_gpu: bool) -> Result<&WhisperContext> {
+    /// Whether `model_path` can be loaded without unloading `resident` under the budget.
+    fn fits_alongside(&self, model_path: &Path, resident: &Path, use_gpu: bool) -> bool {
+        let Some(budget) = self.settings.budget_for(use_gpu) else {
+            return true;
+        };
+        let size = |path: &Path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        size(model_path).saturating_add(size(resident)) <= budget
+    }
+
+    /// Apply new settings, unloading least recently used contexts that no longer fit.
+    fn configure(&mut self, settings: CacheSettings) {
+        if self.settings == settings {
+            return;
+        }
+        if self.settings.gpu_device != settings.gpu_device {
+            self.contexts.retain(|entry| !entry.use_gpu);
+        }
+        self.settings = settings;
+        for use_gpu in [false, true] {
+            if let Some(budget) = settings.budget_for(use_gpu) {
+                self.evict_to_fit(use_gpu, 0, budget);
+            }
+        }
+    }
+
+    /// Drop contexts unused for at least `idle`; returns how many were unloaded.
+    fn unload_idle(&mut self, idle: Duration) -> usize {
+        let before = self.contexts.len();
+        self.contexts
+            .retain(|entry| entry.last_used.elapsed() < idle);
+        before - self.contexts.len()
+    }
+
+    fn evict_to_fit(&mut self, use_gpu: bool, incoming_bytes: u64, budget: u64) {
+        let resident: Vec<(usize, u64, Instant)> = self
+            .contexts
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.use_gpu == use_gpu)
+            .map(|(index, entry)| (index, entry.size_bytes, entry.last_used))
+            .collect();
+        let mut evict = lru_evictions(&resident, incoming_bytes, budget);
+        evict.sort_unstable_by(|left, right| right.cmp(left));
+        for index in evict {
+            let entry = self.contexts.remove(index);
+            info!(
+                "Unloading Whisper context ({} mode) for {:?} to stay within the memory budget",
+                if entry.use_gpu { "GPU" } else { "CPU" },
+                entry.model_path
+            );
+        }
+    }
+
+    fn get_or_create(&mut self, model_path: &Path, use_gpu: bool) -> Result<&mut CachedContext> {
         if let Some(index) = self
             .contexts
             .iter()
@@ -82,10 +244,29 @@ impl ContextCache {
                 if use_gpu { "GPU" } else { "CPU" },
                 model_path
             );
-            return Ok(&self.contexts[index].ctx);
+            self.contexts[index].last_used = Instant::now();
+            crate::models::storage::record_model_used(model_path);
+            return Ok(&mut self.contexts[index]);
         }
 
+        crate::models::verify_model_file(model_path)?;
         let mode_str = if use_gpu { "GPU" } else { "CPU" };
+        let size_bytes = std::fs::metadata(model_path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        if let Some(budget) = self.settings.budget_for(use_gpu) {
+            if size_bytes > budget {
+                anyhow::bail!(
+                    "Model {:?} needs about {} MiB, above the {} memory budget of {} MiB",
+                    model_path,
+                    size_bytes / 1024 / 1024,
+                    mode_str,
+                    budget / 1024 / 1024
+                );
+            }
+            self.evict_to_fit(use_gpu, size_bytes, budget);
+        }
+
         info!(
             "Creating Whisper context ({} mode) for {:?}",
             mode_str, model_path
@@ -94,6 +275,8 @@ impl ContextCache {
         let mut ctx_params = WhisperContextParameters::default();
         if !use_gpu {
             ctx_params.use_gpu(false);
+        } else if let Some(device) = self.settings.gpu_device {
+            ctx_params.gpu_device(device as i32);
         }
 
         let ctx = WhisperContext::new_with_params(
@@ -104,34 +287,97 @@ impl ContextCache {
         )?;
 
         info!("Whisper {} context created successfully", mode_str);
+        crate::models::storage::record_model_used(model_path);
 
         self.contexts.push(CachedContext {
             model_path: model_path.to_path_buf(),
             use_gpu,
+            size_bytes,
+            last_used: Instant::now(),
             ctx,
+            state: None,
         });
         let index = self.contexts.len() - 1;
-        Ok(&self.contexts[index].ctx)
+        Ok(&mut self.contexts[index])
+    }
+}
+
+/// Indices of `(index, size, last_used)` entries to unload, oldest first, so
+/// that `incoming_bytes` more fits within `budget`.
+fn lru_evictions(
+    resident: &[(usize, u64, Instant)],
+    incoming_bytes: u64,
+    budget: u64,
+) -> Vec<usize> {
+    let mut by_age = resident.to_vec();
+    by_age.sort_by_key(|(_, _, last_used)| *last_used);
+
+    let mut total: u64 = by_age.iter().map(|(_, size, _)| size).sum();
+    let mut evict = Vec::new();
+    for (index, size, _) in by_age {
+        if total.saturating_add(incoming_bytes) <= budget {
+            break;
+        }
+        total -= size;
+        evict.push(index);
+    }
+    evict
+}
+
+/// Local Whisper execution for callers outside the dictation worker, such as `oswispa serve`.
+pub(crate) struct LocalTranscriber {
+    context_cache: ContextCache,
+}
+
+impl LocalTranscriber {
+    pub(crate) fn new(config: &Config) -> Self {
+        let mut context_cache = ContextCache::default();
+        context_cache.configure(CacheSettings::from(config));
+        prewarm_active_local_context(config, &mut context_cache);
+        Self { context_cache }
+    }
+
+    /// Run the normal GPU/CPU fallback chain over a 16 kHz mono WAV file.
+    pub(crate) fn transcribe_file(
+        &mut self,
+        audio_path: &Path,
+        config: &Config,
+        vocabulary_prompt: Option<&str>,
+    ) -> Result<Transcript> {
+        transcribe_local(
+            &audio_path.to_path_buf(),
+            config,
+            &mut self.context_cache,
+            vocabulary_prompt,
+        )
     }
 }
 
 /// Transcription worker that processes audio files with lazy context initialization
+///
+/// `pin_rx` carries the language pinned for the next dictation; a finished
+/// recording consumes it and a cancelled one leaves it pinned.
 pub fn transcription_worker(
     audio_rx: Receiver<Option<PathBuf>>,
     stream_rx: Receiver<StreamingAudioMessage>,
+    pin_rx: Receiver<Option<String>>,
     event_tx: Sender<AppEvent>,
     config: Arc<RwLock<Config>>,
     personalisation: Arc<RwLock<Personalisation>>,
 ) {
     info!("Transcription worker started (lazy initialization mode)");
 
-    let startup_config = config.read().unwrap().clone();
+    let startup_config = config.read().unwrap().with_active_profile();
     if let Some(ref fallback) = startup_config.fallback_model_path {
         info!("Fallback model configured: {:?}", fallback);
     }
     let mut context_cache = ContextCache::default();
-    let mut cache_signature = CacheSignature::from(&startup_config);
+    context_cache.configure(CacheSettings::from(&startup_config));
+    let mut cache_signature = CacheSignature::from(&*config.read().unwrap());
+    let idle_check = crossbeam_channel::tick(IDLE_CHECK_INTERVAL);
     let mut live_stream = LiveStreamState::default();
+    let mut remote_stream: Option<remote_stream::RemoteStreamSession> = None;
+    let mut pinned_language: Option<String> = None;
 
     if startup_config.backend == TranscriptionBackend::Local {
         prewarm_active_local_context(&startup_config, &mut context_cache);
@@ -139,18 +385,35 @@ pub fn transcription_worker(
 
     loop {
         select! {
+            recv(pin_rx) -> pin => match pin {
+                Ok(language) => pinned_language = language,
+                Err(_) => break,
+            },
             recv(stream_rx) -> stream_msg => {
-                let current_config = refresh_config_cache(
-                    &config,
-                    &mut context_cache,
-                    &mut cache_signature,
+                let current_config = routing::with_pinned_language(
+                    refresh_config_cache(&config, &mut context_cache, &mut cache_signature),
+                    pinned_language.clone(),
                 );
 
                 match stream_msg {
+                    Ok(StreamingAudioMessage::Prewarm) => {
+                        let resident = routing::resident_model(&current_config);
+                        if current_config.backend == TranscriptionBackend::Local
+                            && !context_cache.contains(&resident, true)
+                            && !context_cache.contains(&resident, false)
+                        {
+                            prewarm_active_local_context(&current_config, &mut context_cache);
+                        }
+                    }
                     Ok(StreamingAudioMessage::Begin) => {
                         live_stream = LiveStreamState::default();
+                        remote_stream = start_remote_stream(&current_config, &event_tx);
                     }
                     Ok(StreamingAudioMessage::Chunk(samples)) => {
+                        if let Some(session) = &remote_stream {
+                            session.send_chunk(samples);
+                            continue;
+                        }
                         if !should_use_live_streaming(&current_config) {
                             continue;
                         }
@@ -158,7 +421,7 @@ pub fn transcription_worker(
                         let vocabulary_prompt = personalisation
                             .read()
                             .ok()
-                            .and_then(|dictionary| dictionary.vocabulary_prompt());
+                            .and_then(|dictionary| dictionary.for_profile(&current_config).vocabulary_prompt());
                         match transcribe_stream_chunk(
                             &samples,
                             &current_config,
@@ -178,6 +441,9 @@ pub fn transcription_worker(
                         }
                     }
                     Ok(StreamingAudioMessage::Finalize) => {
+                        if let Some(session) = &remote_stream {
+                            session.finalize();
+                        }
                         debug!(
                             "Streaming finalized with {} chars of partial text; waiting for full-file pass",
                             live_stream.transcript.len()
@@ -185,10 +451,24 @@ pub fn transcription_worker(
                     }
                     Ok(StreamingAudioMessage::Cancel) => {
                         live_stream = LiveStreamState::default();
+                        remote_stream = None;
                     }
                     Err(_) => break,
                 }
             }
+            recv(idle_check) -> _ => {
+                let idle_minutes = config.read().unwrap().model_cache.idle_unload_minutes;
+                if let Some(minutes) = idle_minutes {
+                    let unloaded =
+                        context_cache.unload_idle(Duration::from_secs(u64::from(minutes) * 60));
+                    if unloaded > 0 {
+                        info!(
+                            "Unloaded {} idle Whisper context(s) after {} min without dictation",
+                            unloaded, minutes
+                        );
+                    }
+                }
+            }
             recv(audio_rx) -> audio_path_opt => {
                 let current_config = refresh_config_cache(
                     &config,
@@ -201,32 +481,52 @@ pub fn transcription_worker(
                     Ok(None) => {
                         debug!("Received None - recording was cancelled, skipping transcription");
                         live_stream = LiveStreamState::default();
+                        remote_stream = None;
                         continue;
                     }
                     Err(_) => break,
                 };
+                // A pinned language applies to this dictation only.
+                let current_config =
+                    routing::with_pinned_language(current_config, pinned_language.take());
 
                 info!("Processing audio file: {:?}", audio_path);
                 let vocabulary_prompt = personalisation
                     .read()
                     .ok()
-                    .and_then(|dictionary| dictionary.vocabulary_prompt());
+                    .and_then(|dictionary| dictionary.for_profile(&current_config).vocabulary_prompt());
 
                 let result = match current_config.backend {
                     TranscriptionBackend::Local => {
-                        transcribe_with_fallback(
+                        transcribe_local(
                             &audio_path,
                             &current_config,
                             &mut context_cache,
                             vocabulary_prompt.as_deref(),
                         )
                     }
-                    TranscriptionBackend::Remote => transcribe_remote_with_local_fallback(
-                        &audio_path,
-                        &current_config,
-                        &mut context_cache,
-                        vocabulary_prompt.as_deref(),
-                    ),
+                    TranscriptionBackend::Remote => match remote_stream.take().map(|session| session.wait_final()) {
+                        Some(Ok(text)) => {
+                            if let Some(endpoint) = current_config.remote_backend.streaming_endpoint() {
+                                crate::usage::record_remote_request(&endpoint.stream_endpoint, &audio_path, true);
+                            }
+                            Ok(Transcript::new(text, &current_config))
+                        }
+                        streamed => {
+                            if let Some(Err(err)) = streamed {
+                                warn!("Remote stream failed, uploading the recording instead: {}", err);
+                                if let Some(endpoint) = current_config.remote_backend.streaming_endpoint() {
+                                    crate::usage::record_remote_request(&endpoint.stream_endpoint, &audio_path, false);
+                                }
+                            }
+                            transcribe_remote_with_local_fallback(
+                                &audio_path,
+                                &current_config,
+                                &mut context_cache,
+                                vocabulary_prompt.as_deref(),
+                            )
+                        }
+                    },
                 };
 
                 emit_transcription_result(result, &event_tx);
@@ -245,8 +545,12 @@ fn refresh_config_cache(
     context_cache: &mut ContextCache,
     cache_signature: &mut CacheSignature,
 ) -> Config {
-    let current_config = config.read().unwrap().clone();
-    let current_signature = CacheSignature::from(&current_config);
+    let base_config = config.read().unwrap().clone();
+    let current_config = base_config.with_active_profile();
+    context_cache.configure(CacheSettings::from(&current_config));
+    // Switching profiles keeps the cache: each profile's model stays loaded
+    // while the memory budget allows, so alternating profiles does not reload.
+    let current_signature = CacheSignature::from(&base_config);
     if current_signature != *cache_signature {
         info!("Transcription config changed - resetting cached Whisper contexts");
         context_cache.clear();
@@ -264,13 +568,42 @@ fn should_use_live_streaming(config: &Config) -> bool {
     config.backend == TranscriptionBackend::Local && config.streaming.enabled
 }
 
-fn emit_transcription_result(result: Result<String>, event_tx: &Sender<AppEvent>) {
+/// Open a realtime session when the remote backend has a streaming URL configured.
+fn start_remote_stream(
+    config: &Config,
+    event_tx: &Sender<AppEvent>,
+) -> Option<remote_stream::RemoteStreamSession> {
+    if config.backend != TranscriptionBackend::Remote || !config.live_streaming_active() {
+        return None;
+    }
+    let endpoint = config.remote_backend.streaming_endpoint()?;
+    if let Some(reason) = crate::usage::remote_budget_exceeded(config) {
+        info!("Not streaming to the remote backend: {}", reason);
+        return None;
+    }
+    match remote_stream::RemoteStreamSession::spawn(endpoint, config, event_tx.clone()) {
+        Ok(session) => Some(session),
+        Err(err) => {
+            warn!(
+                "Remote streaming unavailable, recording will be uploaded: {}",
+                err
+            );
+            None
+        }
+    }
+}
+
+fn emit_transcription_result(result: Result<Transcript>, event_tx: &Sender<AppEvent>) {
     match result {
-        Ok(text) => {
+        Ok(Transcript { text, language }) => {
             let text = text.trim().to_string();
             if !text.is_empty() && !is_garbage_output(&text) {
-                info!("Transcription successful: {} chars", text.len());
-                let _ = event_tx.send(AppEvent::TranscriptionComplete(text));
+                info!(
+                    "Transcription successful: {} chars, language {:?}",
+                    text.len(),
+                    language
+                );
+                let _ = event_tx.send(AppEvent::TranscriptionComplete { text, language });
             } else {
                 info!("Empty or garbage transcription");
                 let _ = event_tx.send(AppEvent::Error("No speech detected".to_string()));
@@ -328,9 +661,15 @@ fn transcribe_remote_with_local_fallback(
     config: &Config,
     context_cache: &mut ContextCache,
     vocabulary_prompt: Option<&str>,
-) -> Result<String> {
-    match transcribe_with_remote_backend(audio_path, config) {
-        Ok(text) => Ok(text),
+) -> Result<Transcript> {
+    let remote_result = match crate::usage::remote_budget_exceeded(config) {
+        Some(reason) => Err(anyhow::anyhow!("Remote budget reached: {}", reason)),
+        None => remote::transcribe_with_remote_backends(audio_path, config, vocabulary_prompt),
+    };
+    match remote_result {
+        Ok(remote::RemoteTranscript { text, language }) => {
+            Ok(Transcript::with_language(text, language, config))
+        }
         Err(err) => {
             warn!("Remote backend transcription failed: {}", err);
 
@@ -342,7 +681,7 @@ fn transcribe_remote_with_local_fallback(
                     .unwrap_or(false)
             {
                 warn!("Falling back to local transcription backend");
-                transcribe_with_fallback(audio_path, config, context_cache, vocabulary_prompt)
+                transcribe_local(audio_path, config, context_cache, vocabulary_prompt)
             } else {
                 Err(anyhow::anyhow!(
                     "Remote transcription failed and no local model is available: {}",
@@ -353,116 +692,16 @@ fn transcribe_remote_with_local_fallback(
     }
 }
 
-fn resolve_remote_api_key(config: &Config) -> Option<String> {
-    if let Some(env_var) = config
-        .remote_backend
-        .api_key_env
-        .as_ref()
-        .map(|v| v.trim())
-        .filter(|v| !v.is_empty())
-    {
-        return std::env::var(env_var)
-            .ok()
-            .map(|v| v.trim().to_string())
-            .filter(|v| !v.is_empty());
-    }
-
-    crate::get_remote_api_key()
-}
-
-fn validate_remote_endpoint(endpoint: &str, allow_insecure_http: bool) -> Result<()> {
-    let url = reqwest::Url::parse(endpoint)?;
-    match url.scheme() {
-        "https" => Ok(()),
-        "http" if allow_insecure_http => Ok(()),
-        "http" => anyhow::bail!(
-            "Remote endpoint must use HTTPS. Enable allow_insecure_http to opt into plain HTTP."
-        ),
-        scheme => anyhow::bail!("Remote endpoint uses unsupported URL scheme: {}", scheme),
-    }
-}
-
-fn transcribe_with_remote_backend(audio_path: &PathBuf, config: &Config) -> Result<String> {
-    let endpoint = config.remote_backend.endpoint.trim();
-    if endpoint.is_empty() {
-        anyhow::bail!("Remote backend endpoint is empty");
-    }
-
-    validate_remote_endpoint(endpoint, config.remote_backend.allow_insecure_http)?;
-
-    let timeout_ms = config.remote_backend.timeout_ms.max(1_000);
-    let client = Client::builder()
-        .timeout(Duration::from_millis(timeout_ms))
-        .build()?;
-
-    let mut form = multipart::Form::new().text("model", config.remote_backend.model.clone());
-
-    if config.language != "auto" {
-        form = form.text("language", config.language.clone());
-    }
-    if config.translate_to_english {
-        form = form.text("task", "translate");
-    }
-
-    let audio_bytes = std::fs::read(audio_path)?;
-    let audio_part = multipart::Part::bytes(audio_bytes)
-        .file_name("audio.wav")
-        .mime_str("audio/wav")?;
-    form = form.part("file", audio_part);
-
-    let mut request = client
-        .post(endpoint)
-        .header("Accept", "application/json")
-        .multipart(form);
-
-    if let Some(token) = resolve_remote_api_key(config) {
-        request = request.bearer_auth(token);
-    }
-
-    let response = request.send()?;
-    let status = response.status();
-    if response
-        .content_length()
-        .map(|length| length > MAX_REMOTE_RESPONSE_BYTES)
-        .unwrap_or(false)
-    {
-        anyhow::bail!("Remote backend response exceeded the 2 MiB limit");
-    }
-
-    let mut body = String::new();
-    response
-        .take(MAX_REMOTE_RESPONSE_BYTES + 1)
-        .read_to_string(&mut body)?;
-    if body.len() as u64 > MAX_REMOTE_RESPONSE_BYTES {
-        anyhow::bail!("Remote backend response exceeded the 2 MiB limit");
-    }
-
-    if !status.is_success() {
-        anyhow::bail!("Remote backend returned {}", status);
-    }
-
-    if let Ok(json) = serde_json::from_str::<Value>(&body) {
-        if let Some(text) = json.get("text").and_then(|v| v.as_str()) {
-            return Ok(text.to_string());
-        }
-
-        if let Some(text) = json
-            .get("choices")
-            .and_then(|v| v.as_array())
-            .and_then(|arr| arr.first())
-            .and_then(|first| first.get("text"))
-            .and_then(|v| v.as_str())
-        {
-            return Ok(text.to_string());
-        }
-    }
-
-    let plain = body.trim();
-    if !plain.is_empty() {
-        return Ok(plain.to_string());
-    }
-
-    anyhow::bail!("Remote backend response did not include transcribed text")
+/// Route a dictation to the model for its language, then run the fallback chain.
+fn transcribe_local(
+    audio_path: &PathBuf,
+    config: &Config,
+    context_cache: &mut ContextCache,
+    vocabulary_prompt: Option<&str>,
+) -> Result<Transcript> {
+    let config = routing::for_dictation(audio_path, config, context_cache);
+    let text = transcribe_with_fallback(audio_path, &config, context_cache, vocabulary_prompt)?;
+    Ok(Transcript::new(text, &config))
 }
 
 /// Transcribe with fallback chain: Primary GPU -> Fallback GPU -> Primary CPU -> Fallback CPU
@@ -475,16 +714,8 @@ fn transcribe_with_fallback(
     let primary_model = &config.model_path;
     let fallback_model = config.fallback_model_path.as_ref();
 
-    // Check available VRAM
-    let available_vram = get_available_vram();
-    info!(
-        "Available VRAM: {:.2} GB",
-        available_vram as f64 / 1024.0 / 1024.0 / 1024.0
-    );
-
-    // Strategy based on VRAM availability
-    let primary_gpu_safe = context_cache.contains(primary_model, true)
-        || can_run_model_on_gpu(primary_model, available_vram);
+    // Strategy based on the GPU policy and cached VRAM availability
+    let primary_gpu_safe = should_use_gpu(config, primary_model, context_cache);
     if primary_gpu_safe {
         // Enough VRAM - try primary model on GPU
         info!("Sufficient VRAM available, attempting GPU transcription with primary model");
@@ -509,11 +740,13 @@ fn transcribe_with_fallback(
         }
 
         warn!("Primary GPU attempt did not complete cleanly, skipping further GPU fallback");
+    } else if config.gpu.policy == GpuPolicy::Never {
+        debug!("GPU policy is never, transcribing on the CPU");
     } else {
         // Safe GPU headroom unavailable - skip GPU entirely.
         warn!(
             "Safe GPU headroom unavailable ({:.2} GB free), skipping primary GPU",
-            available_vram as f64 / 1024.0 / 1024.0 / 1024.0,
+            get_available_vram(config) as f64 / 1024.0 / 1024.0 / 1024.0,
         );
     }
 
@@ -561,16 +794,17 @@ fn transcribe_with_fallback(
 }
 
 fn prewarm_active_local_context(config: &Config, context_cache: &mut ContextCache) {
-    if !config.model_path.exists() {
+    let model_path = routing::resident_model(config);
+    if !model_path.exists() {
         return;
     }
 
-    let use_gpu = can_run_model_on_gpu(&config.model_path, get_available_vram());
-    match context_cache.get_or_create(&config.model_path, use_gpu) {
+    let use_gpu = should_use_gpu(config, &model_path, context_cache);
+    match context_cache.get_or_create(&model_path, use_gpu) {
         Ok(_) => info!(
             "Prewarmed active local model ({}) for {:?}",
             if use_gpu { "GPU" } else { "CPU" },
-            config.model_path
+            model_path
         ),
         Err(err) => warn!("Failed to prewarm active local model: {}", err),
     }
@@ -585,80 +819,36 @@ fn transcribe_with_model(
     context_cache: &mut ContextCache,
     vocabulary_prompt: Option<&str>,
 ) -> Result<String> {
-    let ctx = context_cache.get_or_create(model_path, use_gpu)?;
-    transcribe_audio(ctx, audio_path, config, vocabulary_prompt)
+    let cached = context_cache.get_or_create(model_path, use_gpu)?;
+    transcribe_audio(cached, audio_path, config, vocabulary_prompt)
 }
 
-/// Get available VRAM in bytes by querying the GPU.
-///
-/// Detection chain: selected ROCm device → AMD sysfs → ROCm inventory → NVIDIA.
-fn get_available_vram() -> u64 {
-    // A configured ROCm device must win over physical sysfs enumeration.
-    let selected_rocm_device = crate::gpu::rocm_visible_device_index();
-    if selected_rocm_device.is_some() {
-        if let Some(vram) = detect_vram_rocm_smi() {
-            return vram;
+/// Whether `model_path` should run on the GPU under the configured policy.
+fn should_use_gpu(config: &Config, model_path: &Path, context_cache: &ContextCache) -> bool {
+    match config.gpu.policy {
+        GpuPolicy::Never => false,
+        GpuPolicy::Always => true,
+        GpuPolicy::Auto => {
+            context_cache.contains(model_path, true)
+                || can_run_model_on_gpu(model_path, get_available_vram(config))
         }
     }
+}
 
-    // 1. AMD sysfs (fastest, no subprocess)
-    if let Some(vram) = detect_vram_amd_sysfs() {
-        return vram;
-    }
-
-    // 2. rocm-smi (AMD fallback)
-    if selected_rocm_device.is_none() {
-        if let Some(vram) = detect_vram_rocm_smi() {
-            return vram;
+/// Free VRAM in bytes on the configured device, from the cached GPU inventory.
+fn get_available_vram(config: &Config) -> u64 {
+    let device = config
+        .gpu
+        .device_index
+        .or_else(crate::gpu::rocm_visible_device_index);
+    match crate::gpu::cached_inventory().available_bytes(device) {
+        Some(bytes) => bytes,
+        None => {
+            // If we can't determine VRAM, stay conservative and avoid GPU use.
+            debug!("Could not determine available VRAM, defaulting to CPU-safe mode");
+            0
         }
     }
-
-    // 3. nvidia-smi (NVIDIA GPUs)
-    if let Some(vram) = detect_vram_nvidia_smi() {
-        return vram;
-    }
-
-    // If we can't determine VRAM, stay conservative and avoid GPU use.
-    warn!("Could not determine available VRAM, defaulting to CPU-safe mode");
-    0
-}
-
-/// Detect available VRAM via AMD sysfs paths.
-fn detect_vram_amd_sysfs() -> Option<u64> {
-    crate::gpu::detect_amd_sysfs_available_bytes()
-}
-
-/// Detect available VRAM via rocm-smi (AMD).
-fn detect_vram_rocm_smi() -> Option<u64> {
-    crate::gpu::detect_rocm_smi_available_bytes(crate::gpu::rocm_visible_device_index())
-}
-
-/// Detect available VRAM via nvidia-smi (NVIDIA).
-///
-/// Uses a single nvidia-smi call to query both total and free memory,
-/// avoiding a TOCTOU race between two separate calls.
-fn detect_vram_nvidia_smi() -> Option<u64> {
-    let output = std::process::Command::new("nvidia-smi")
-        .args([
-            "--query-gpu=memory.total,memory.free",
-            "--format=csv,noheader,nounits",
-        ])
-        .output()
-        .ok()?;
-
-    let stdout = String::from_utf8(output.stdout).ok()?;
-
-    // First line = first GPU. Format: "total_mib, free_mib"
-    let line = stdout.lines().next()?.trim();
-    let mut parts = line.split(',');
-    let total_mib: u64 = parts.next()?.trim().parse().ok()?;
-    let free_mib: u64 = parts.next()?.trim().parse().ok()?;
-
-    let available = free_mib * 1024 * 1024;
-
-    debug!("nvidia-smi: total={}MiB, free={}MiB", total_mib, free_mib);
-
-    Some(available)
 }
 
 /// Check if output is garbage (repeated punctuation from GPU failure)
@@ -698,13 +888,12 @@ fn transcribe_stream_chunk(
         return Ok(String::new());
     }
 
-    let available_vram = get_available_vram();
     if samples.len() < 16_000 {
         return Ok(String::new());
     }
 
-    let use_gpu = context_cache.contains(&config.model_path, true)
-        || can_run_model_on_gpu(&config.model_path, available_vram);
+    let config = &*routing::for_configured_language(config);
+    let use_gpu = should_use_gpu(config, &config.model_path, context_cache);
     let model_path = if use_gpu {
         &config.model_path
     } else {
@@ -714,15 +903,17 @@ fn transcribe_stream_chunk(
             .filter(|path| path.exists())
             .unwrap_or(&config.model_path)
     };
-    let ctx = context_cache.get_or_create(model_path, use_gpu)?;
-    transcribe_samples(
-        ctx,
-        samples,
-        config,
-        (num_cpus::get() / 2).max(1) as i32,
-        true,
-        vocabulary_prompt,
-    )
+    let cached = context_cache.get_or_create(model_path, use_gpu)?;
+    cached.with_state(|state| {
+        transcribe_samples(
+            state,
+            samples,
+            config,
+            (num_cpus::get() / 2).max(1) as i32,
+            true,
+            vocabulary_prompt,
+        )
+    })
 }
 
 fn can_run_model_on_gpu(model_path: &Path, available_vram: u64) -> bool {
@@ -750,7 +941,7 @@ fn can_run_model_on_gpu(model_path: &Path, available_vram: u64) -> bool {
 
 /// Transcribe a single audio file
 fn transcribe_audio(
-    ctx: &WhisperContext,
+    cached: &mut CachedContext,
     audio_path: &PathBuf,
     config: &Config,
     vocabulary_prompt: Option<&str>,
@@ -764,27 +955,101 @@ fn transcribe_audio(
         samples.len() as f32 / 16000.0
     );
 
-    transcribe_samples(
-        ctx,
-        &samples,
-        config,
-        num_cpus::get() as i32,
-        false,
-        vocabulary_prompt,
-    )
+    if let Some(chunks) = chunking::plan_for(&config.long_dictation, &samples) {
+        return transcribe_chunks(cached, &samples, chunks, config, vocabulary_prompt);
+    }
+
+    cached.with_state(|state| {
+        transcribe_samples(
+            state,
+            &samples,
+            config,
+            num_cpus::get() as i32,
+            false,
+            vocabulary_prompt,
+        )
+    })
+}
+
+/// Transcribe a long recording chunk by chunk and stitch the text in order.
+///
+/// CPU contexts spread chunks over several states on one shared model; GPU
+/// contexts run them one after another since the device is already saturated.
+fn transcribe_chunks(
+    cached: &mut CachedContext,
+    samples: &[f32],
+    chunks: Vec<Range<usize>>,
+    config: &Config,
+    vocabulary_prompt: Option<&str>,
+) -> Result<String> {
+    let workers = if cached.use_gpu {
+        1
+    } else {
+        config
+            .long_dictation
+            .workers
+            .unwrap_or_else(chunking::default_workers)
+            .clamp(1, chunks.len())
+    };
+    let n_threads = (num_cpus::get() / workers).max(1) as i32;
+    info!(
+        "Splitting {:.1}s recording into {} chunks across {} worker(s)",
+        samples.len() as f32 / chunking::SAMPLE_RATE as f32,
+        chunks.len(),
+        workers
+    );
+
+    let next_chunk = AtomicUsize::new(0);
+    let texts = cached.with_states(workers, |states| {
+        let mut texts = vec![String::new(); chunks.len()];
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = states
+                .iter_mut()
+                .map(|state| {
+                    let (chunks, next_chunk) = (&chunks, &next_chunk);
+                    scope.spawn(move || -> Result<Vec<(usize, String)>> {
+                        let mut done = Vec::new();
+                        loop {
+                            let index = next_chunk.fetch_add(1, Ordering::Relaxed);
+                            let Some(range) = chunks.get(index) else {
+                                return Ok(done);
+                            };
+                            let text = transcribe_samples(
+                                state,
+                                &samples[range.clone()],
+                                config,
+                                n_threads,
+                                false,
+                                vocabulary_prompt,
+                            )?;
+                            done.push((index, text));
+                        }
+                    })
+                })
+                .collect();
+            for handle in handles {
+                let done = handle
+                    .join()
+                    .map_err(|_| anyhow::anyhow!("Chunk transcription thread panicked"))??;
+                for (index, text) in done {
+                    texts[index] = text;
+                }
+            }
+            Ok(texts)
+        })
+    })?;
+
+    Ok(chunking::stitch(texts.iter().map(String::as_str)))
 }
 
 fn transcribe_samples(
-    ctx: &WhisperContext,
+    state: &mut WhisperState,
     samples: &[f32],
     config: &Config,
     n_threads: i32,
     single_segment: bool,
     vocabulary_prompt: Option<&str>,
 ) -> Result<String> {
-    // Create whisper state
-    let mut state = ctx.create_state()?;
-
     // Configure transcription parameters
     let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
 
@@ -814,7 +1079,7 @@ fn transcribe_samples(
     params.set_print_realtime(false);
     params.set_print_timestamps(false);
 
-    // Quality settings
+    // Quality settings; no_context also keeps the reused state independent of earlier passes
     params.set_no_context(true);
     params.set_single_segment(single_segment);
 
@@ -893,6 +1158,33 @@ mod tests {
         assert!(!is_garbage_output("Hello, world!"));
     }
 
+    #[test]
+    fn lru_evictions_unload_oldest_contexts_until_the_new_one_fits() {
+        let now = Instant::now();
+        let older = now - Duration::from_secs(60);
+        let oldest = now - Duration::from_secs(120);
+        let resident = [(0, 400, now), (1, 300, oldest), (2, 200, older)];
+
+        assert!(lru_evictions(&resident, 100, 1000).is_empty());
+        assert_eq!(lru_evictions(&resident, 300, 1000), vec![1]);
+        assert_eq!(lru_evictions(&resident, 700, 1000), vec![1, 2, 0]);
+        assert_eq!(lru_evictions(&resident, 0, 500), vec![1, 2]);
+    }
+
+    #[test]
+    fn cache_settings_follow_model_cache_and_gpu_config() {
+        let mut config = Config::default();
+        assert_eq!(CacheSettings::from(&config), CacheSettings::default());
+
+        config.model_cache.ram_budget_mb = Some(2048);
+        config.model_cache.vram_budget_mb = Some(4096);
+        config.gpu.device_index = Some(1);
+        let settings = CacheSettings::from(&config);
+        assert_eq!(settings.budget_for(false), Some(2048 * 1024 * 1024));
+        assert_eq!(settings.budget_for(true), Some(4096 * 1024 * 1024));
+        assert_eq!(settings.gpu_device, Some(1));
+    }
+
     #[test]
     fn test_append_stream_text_deduplicates_overlap() {
         let mut text = "hello world".to_string();
@@ -902,22 +1194,8 @@ mod tests {
 
     #[test]
     fn test_vram_detection() {
-        let vram = get_available_vram();
+        let vram = get_available_vram(&Config::default());
         println!("Detected available VRAM: {} bytes", vram);
         // Just ensure it doesn't panic
     }
-
-    #[test]
-    fn test_validate_remote_endpoint_https_required() {
-        assert!(
-            validate_remote_endpoint("https://example.com/v1/audio/transcriptions", false).is_ok()
-        );
-        assert!(
-            validate_remote_endpoint("http://example.com/v1/audio/transcriptions", false).is_err()
-        );
-        assert!(
-            validate_remote_endpoint("http://example.com/v1/audio/transcriptions", true).is_ok()
-        );
-        assert!(validate_remote_endpoint("file:///tmp/transcript", true).is_err());
-    }
 }
@@ -0,0 +1,636 @@
+//! Remote transcription over HTTP for the opt-in VPS/hosted backend.
+//!
+//! Endpoints are tried in their configured order; the caller owns the final
+//! fallback to local Whisper.
+
+use crate::{Config, RemoteDialect, RemoteEndpointConfig};
+use anyhow::{Context, Result};
+use reqwest::blocking::{multipart, Client, RequestBuilder, Response};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Identity};
+use serde_json::Value;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+const MAX_REMOTE_RESPONSE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Text returned by a remote backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct RemoteTranscript {
+    pub text: String,
+    /// Whisper language code from a `verbose_json` response, when the server reported one
+    pub language: Option<String>,
+}
+
+/// Try every configured remote endpoint in order and return the first transcript.
+pub(super) fn transcribe_with_remote_backends(
+    audio_path: &Path,
+    config: &Config,
+    vocabulary_prompt: Option<&str>,
+) -> Result<RemoteTranscript> {
+    let mut attempted = 0;
+    let mut last_error = None;
+
+    for (index, endpoint) in config.remote_backend.endpoints().enumerate() {
+        attempted += 1;
+        let attempt =
+            transcribe_with_remote_endpoint(audio_path, endpoint, config, vocabulary_prompt);
+        if attempt.uploaded {
+            crate::usage::record_remote_request(
+                &endpoint.endpoint,
+                audio_path,
+                attempt.result.is_ok(),
+            );
+        }
+        match attempt.result {
+            Ok(transcript) => {
+                if index > 0 {
+                    info!("Remote failover endpoint #{} succeeded", index + 1);
+                }
+                return Ok(transcript);
+            }
+            Err(err) => {
+                warn!("Remote endpoint #{} failed: {}", index + 1, err);
+                last_error = Some(err);
+            }
+        }
+    }
+
+    match last_error {
+        Some(err) if attempted > 1 => Err(err.context(format!(
+            "All {} remote endpoints failed; last error",
+            attempted
+        ))),
+        Some(err) => Err(err),
+        None => anyhow::bail!("Remote backend endpoint is empty"),
+    }
+}
+
+pub(super) fn resolve_remote_api_key(endpoint: &RemoteEndpointConfig) -> Option<String> {
+    if let Some(env_var) = endpoint
+        .api_key_env
+        .as_ref()
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+    {
+        return std::env::var(env_var)
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+    }
+
+    if let Some(name) = endpoint.api_key_secret.as_deref() {
+        return crate::runtime::read_remote_secret(name)
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+    }
+
+    if !endpoint.use_stored_api_key {
+        return None;
+    }
+
+    crate::get_remote_api_key()
+}
+
+fn validate_remote_endpoint(endpoint: &str, allow_insecure_http: bool) -> Result<()> {
+    let url = reqwest::Url::parse(endpoint)?;
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if allow_insecure_http => Ok(()),
+        "http" => anyhow::bail!(
+            "Remote endpoint must use HTTPS. Enable allow_insecure_http to opt into plain HTTP."
+        ),
+        scheme => anyhow::bail!("Remote endpoint uses unsupported URL scheme: {}", scheme),
+    }
+}
+
+/// Build an HTTP client carrying this endpoint's timeout, trust roots, identity and headers.
+fn build_remote_client(endpoint: &RemoteEndpointConfig) -> Result<Client> {
+    let timeout_ms = endpoint.timeout_ms.max(1_000);
+    let mut builder = Client::builder().timeout(Duration::from_millis(timeout_ms));
+
+    if let Some(path) = &endpoint.ca_bundle_path {
+        let pem =
+            std::fs::read(path).with_context(|| format!("Failed to read CA bundle {:?}", path))?;
+        let certificates = Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("CA bundle {:?} is not valid PEM", path))?;
+        if certificates.is_empty() {
+            anyhow::bail!("CA bundle {:?} contains no certificates", path);
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    endpoint.validate_client_identity()?;
+    if let (Some(cert_path), Some(key_name)) =
+        (&endpoint.client_cert_path, &endpoint.client_key_secret)
+    {
+        let cert = std::fs::read(cert_path)
+            .with_context(|| format!("Failed to read client certificate {:?}", cert_path))?;
+        let key = crate::runtime::read_remote_secret(key_name)
+            .context("Failed to read client key from secrets")?;
+        let identity = Identity::from_pkcs8_pem(&cert, key.as_bytes())
+            .context("Client certificate and key do not form a valid PKCS#8 identity")?;
+        builder = builder.identity(identity);
+    }
+
+    endpoint.validate_extra_headers()?;
+    let mut headers = HeaderMap::new();
+    for (name, value) in &endpoint.extra_headers {
+        headers.insert(
+            HeaderName::from_bytes(name.trim().as_bytes())?,
+            HeaderValue::from_str(value.trim())?,
+        );
+    }
+
+    Ok(builder.default_headers(headers).build()?)
+}
+
+/// Text fields sent alongside the audio part, in request order.
+///
+/// The vocabulary prompt and `verbose_json` are only sent when the endpoint
+/// opts in; by default dictionary contents stay on this device.
+fn remote_form_fields(
+    endpoint: &RemoteEndpointConfig,
+    config: &Config,
+    vocabulary_prompt: Option<&str>,
+) -> Vec<(&'static str, String)> {
+    let mut fields = Vec::new();
+
+    match endpoint.dialect {
+        RemoteDialect::OpenAi => {
+            fields.push(("model", endpoint.model.clone()));
+            if config.language != "auto" {
+                fields.push(("language", config.language.clone()));
+            }
+            if config.translate_to_english {
+                fields.push(("task", "translate".to_string()));
+            }
+            if endpoint.request_verbose_json {
+                fields.push(("response_format", "verbose_json".to_string()));
+            }
+        }
+        RemoteDialect::WhisperCpp => {
+            let response_format = if endpoint.request_verbose_json {
+                "verbose_json"
+            } else {
+                "json"
+            };
+            fields.push(("response_format", response_format.to_string()));
+            fields.push(("language", config.language.clone()));
+            if config.translate_to_english {
+                fields.push(("translate", "true".to_string()));
+            }
+        }
+    }
+
+    if endpoint.forward_vocabulary_prompt {
+        if let Some(prompt) = vocabulary_prompt.filter(|prompt| !prompt.is_empty()) {
+            fields.push(("prompt", prompt.to_string()));
+        }
+    }
+
+    fields
+}
+
+/// Describe one upload to `endpoint` exactly as it would be sent, for the settings preview.
+pub(crate) fn describe_remote_request(
+    endpoint: &RemoteEndpointConfig,
+    config: &Config,
+    vocabulary_prompt: Option<&str>,
+) -> String {
+    let mut lines = vec![format!("POST {}", endpoint.endpoint.trim())];
+    for name in endpoint.extra_headers.keys() {
+        lines.push(format!("header {}: (configured value)", name.trim()));
+    }
+    if resolve_remote_api_key(endpoint).is_some() {
+        lines.push("header Authorization: Bearer (hidden)".to_string());
+    }
+    for (name, value) in remote_form_fields(endpoint, config, vocabulary_prompt) {
+        lines.push(format!("field {} = {:?}", name, value));
+    }
+    let format = super::upload::supported_upload_format(endpoint.upload_format);
+    let (file_name, mime) = super::upload::upload_metadata(format);
+    lines.push(format!("file {} ({}): the recording", file_name, mime));
+    lines.join("\n")
+}
+
+/// Result of one endpoint attempt.
+struct RemoteAttempt {
+    /// Whether the request reached the server, so the audio counts towards usage and caps
+    uploaded: bool,
+    result: Result<RemoteTranscript>,
+}
+
+fn transcribe_with_remote_endpoint(
+    audio_path: &Path,
+    endpoint: &RemoteEndpointConfig,
+    config: &Config,
+    vocabulary_prompt: Option<&str>,
+) -> RemoteAttempt {
+    let request = match build_remote_request(audio_path, endpoint, config, vocabulary_prompt) {
+        Ok(request) => request,
+        Err(err) => {
+            return RemoteAttempt {
+                uploaded: false,
+                result: Err(err),
+            }
+        }
+    };
+
+    let response = match request.send() {
+        Ok(response) => response,
+        Err(err) => {
+            return RemoteAttempt {
+                // Nothing was sent if the connection itself could not be made.
+                uploaded: !err.is_connect(),
+                result: Err(err.into()),
+            };
+        }
+    };
+
+    RemoteAttempt {
+        uploaded: true,
+        result: read_remote_response(response),
+    }
+}
+
+/// Confirm a discovered LAN server still reports the ID it was added with.
+///
+/// Runs before the token or audio is sent, so a replaced server gets neither.
+fn check_pinned_server_id(client: &Client, endpoint: &RemoteEndpointConfig) -> Result<()> {
+    let Some(expected) = endpoint.discovered_server_id.as_deref() else {
+        return Ok(());
+    };
+    let mut health = reqwest::Url::parse(endpoint.endpoint.trim())?;
+    health.set_path("/health");
+    health.set_query(None);
+    crate::discovery::require_server_id(client, health.as_str(), expected)
+        .context("Refusing to upload to a LAN server that does not report its pinned ID")
+}
+
+/// Validate the endpoint, check a pinned server ID and assemble the upload; no
+/// token or audio leaves the device here.
+fn build_remote_request(
+    audio_path: &Path,
+    endpoint: &RemoteEndpointConfig,
+    config: &Config,
+    vocabulary_prompt: Option<&str>,
+) -> Result<RequestBuilder> {
+    let url = endpoint.endpoint.trim();
+    if url.is_empty() {
+        anyhow::bail!("Remote backend endpoint is empty");
+    }
+
+    validate_remote_endpoint(url, endpoint.allow_insecure_http)?;
+
+    let client = build_remote_client(endpoint)?;
+    check_pinned_server_id(&client, endpoint)?;
+
+    let mut form = multipart::Form::new();
+    for (name, value) in remote_form_fields(endpoint, config, vocabulary_prompt) {
+        form = form.text(name, value);
+    }
+
+    let upload = super::upload::prepare_upload(audio_path, endpoint.upload_format)?;
+    let audio_part = multipart::Part::bytes(upload.bytes)
+        .file_name(upload.file_name)
+        .mime_str(upload.mime)?;
+    form = form.part("file", audio_part);
+
+    let mut request = client
+        .post(url)
+        .header("Accept", "application/json")
+        .multipart(form);
+
+    if let Some(token) = resolve_remote_api_key(endpoint) {
+        request = request.bearer_auth(token);
+    }
+
+    Ok(request)
+}
+
+fn read_remote_response(response: Response) -> Result<RemoteTranscript> {
+    let status = response.status();
+    if response
+        .content_length()
+        .map(|length| length > MAX_REMOTE_RESPONSE_BYTES)
+        .unwrap_or(false)
+    {
+        anyhow::bail!("Remote backend response exceeded the 2 MiB limit");
+    }
+
+    let mut body = String::new();
+    response
+        .take(MAX_REMOTE_RESPONSE_BYTES + 1)
+        .read_to_string(&mut body)?;
+    if body.len() as u64 > MAX_REMOTE_RESPONSE_BYTES {
+        anyhow::bail!("Remote backend response exceeded the 2 MiB limit");
+    }
+
+    if !status.is_success() {
+        anyhow::bail!("Remote backend returned {}", status);
+    }
+
+    parse_remote_transcript(&body)
+}
+
+/// Whisper code for a reported language, which OpenAI names in full ("english").
+fn whisper_language_code(reported: &str) -> Option<String> {
+    let reported = reported.trim().to_ascii_lowercase();
+    let id = whisper_rs::get_lang_id(&reported).or_else(|| {
+        (0..=whisper_rs::get_lang_max_id())
+            .find(|&id| whisper_rs::get_lang_str_full(id) == Some(reported.as_str()))
+    })?;
+    whisper_rs::get_lang_str(id).map(str::to_string)
+}
+
+fn parse_remote_transcript(body: &str) -> Result<RemoteTranscript> {
+    let transcript = |text: &str, language: Option<String>| RemoteTranscript {
+        text: text.to_string(),
+        language,
+    };
+
+    if let Ok(json) = serde_json::from_str::<Value>(body) {
+        if let Some(text) = json.get("text").and_then(|v| v.as_str()) {
+            let language = json
+                .get("language")
+                .and_then(|v| v.as_str())
+                .and_then(whisper_language_code);
+            if let Some(language) = &language {
+                debug!("Remote backend detected language {}", language);
+            }
+            return Ok(transcript(text, language));
+        }
+
+        if let Some(text) = json
+            .get("choices")
+            .and_then(|v| v.as_array())
+            .and_then(|arr| arr.first())
+            .and_then(|first| first.get("text"))
+            .and_then(|v| v.as_str())
+        {
+            return Ok(transcript(text, None));
+        }
+    }
+
+    let plain = body.trim();
+    if !plain.is_empty() {
+        return Ok(transcript(plain, None));
+    }
+
+    anyhow::bail!("Remote backend response did not include transcribed text")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_remote_endpoint_https_required() {
+        assert!(
+            validate_remote_endpoint("https://example.com/v1/audio/transcriptions", false).is_ok()
+        );
+        assert!(
+            validate_remote_endpoint("http://example.com/v1/audio/transcriptions", false).is_err()
+        );
+        assert!(
+            validate_remote_endpoint("http://example.com/v1/audio/transcriptions", true).is_ok()
+        );
+        assert!(validate_remote_endpoint("file:///tmp/transcript", true).is_err());
+    }
+
+    #[test]
+    fn form_fields_follow_endpoint_dialect() {
+        let config = Config {
+            language: "de".to_string(),
+            translate_to_english: true,
+            ..Config::default()
+        };
+        let openai = RemoteEndpointConfig::default();
+        assert_eq!(
+            remote_form_fields(&openai, &config, Some("Vocabulary: Kubernetes")),
+            vec![
+                ("model", "whisper-1".to_string()),
+                ("language", "de".to_string()),
+                ("task", "translate".to_string()),
+            ]
+        );
+
+        let whisper_cpp = RemoteEndpointConfig {
+            dialect: RemoteDialect::WhisperCpp,
+            ..RemoteEndpointConfig::default()
+        };
+        assert_eq!(
+            remote_form_fields(&whisper_cpp, &config, None),
+            vec![
+                ("response_format", "json".to_string()),
+                ("language", "de".to_string()),
+                ("translate", "true".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn prompt_and_verbose_json_require_opt_in() {
+        let config = Config {
+            language: "auto".to_string(),
+            ..Config::default()
+        };
+        let endpoint = RemoteEndpointConfig {
+            forward_vocabulary_prompt: true,
+            request_verbose_json: true,
+            use_stored_api_key: false,
+            ..RemoteEndpointConfig::default()
+        };
+        assert_eq!(
+            remote_form_fields(&endpoint, &config, Some("Vocabulary: Kubernetes")),
+            vec![
+                ("model", "whisper-1".to_string()),
+                ("response_format", "verbose_json".to_string()),
+                ("prompt", "Vocabulary: Kubernetes".to_string()),
+            ]
+        );
+
+        let preview = describe_remote_request(&endpoint, &config, Some("Vocabulary: Kubernetes"));
+        assert!(preview.contains("field prompt = \"Vocabulary: Kubernetes\""));
+        assert!(preview.contains("file audio.wav (audio/wav)"));
+        assert!(!preview.contains("Authorization"));
+
+        let opus = RemoteEndpointConfig {
+            upload_format: crate::RemoteUploadFormat::Opus,
+            ..endpoint
+        };
+        let preview = describe_remote_request(&opus, &config, None);
+        if cfg!(feature = "remote-opus") {
+            assert!(preview.contains("file audio.ogg (audio/ogg)"));
+        } else {
+            assert!(preview.contains("file audio.wav (audio/wav)"));
+        }
+    }
+
+    #[test]
+    fn verbose_json_reports_the_detected_language() {
+        let openai = parse_remote_transcript(r#"{"text":"Hallo","language":"german"}"#).unwrap();
+        assert_eq!(openai.text, "Hallo");
+        assert_eq!(openai.language.as_deref(), Some("de"));
+
+        let whisper_cpp = parse_remote_transcript(r#"{"text":"Hi","language":"en"}"#).unwrap();
+        assert_eq!(whisper_cpp.language.as_deref(), Some("en"));
+
+        let unknown = parse_remote_transcript(r#"{"text":"Hi","language":"klingon"}"#).unwrap();
+        assert_eq!(unknown.language, None);
+        assert_eq!(
+            parse_remote_transcript("plain text").unwrap().language,
+            None
+        );
+    }
+
+    #[test]
+    fn endpoints_without_a_key_source_send_no_token() {
+        let endpoint = RemoteEndpointConfig {
+            use_stored_api_key: false,
+            ..RemoteEndpointConfig::default()
+        };
+        assert_eq!(resolve_remote_api_key(&endpoint), None);
+    }
+
+    #[test]
+    fn servers_reporting_another_id_get_no_upload() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let address = server.server_addr().to_ip().unwrap();
+        let handle = std::thread::spawn(move || {
+            let request = server.recv().unwrap();
+            let path = request.url().to_string();
+            let authorized = request
+                .headers()
+                .iter()
+                .any(|h| h.field.equiv("Authorization"));
+            let _ = request.respond(tiny_http::Response::from_string(
+                r#"{"status":"ok","server_id":"FFFF-0000"}"#,
+            ));
+            (path, authorized)
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let audio = dir.path().join("audio.wav");
+        std::fs::write(&audio, b"RIFF").unwrap();
+        let endpoint = RemoteEndpointConfig {
+            endpoint: format!("http://{}/v1/audio/transcriptions", address),
+            allow_insecure_http: true,
+            use_stored_api_key: false,
+            discovered_server_id: Some("ABCD-EF01".to_string()),
+            ..RemoteEndpointConfig::default()
+        };
+        let attempt = transcribe_with_remote_endpoint(&audio, &endpoint, &Config::default(), None);
+
+        let message = format!("{:#}", attempt.result.unwrap_err());
+        assert!(message.contains("pinned ID"));
+        assert!(!attempt.uploaded);
+        assert_eq!(handle.join().unwrap(), ("/health".to_string(), false));
+    }
+
+    #[test]
+    fn unreachable_endpoints_fail_over_in_order() {
+        let mut config = Config::default();
+        config.remote_backend.set_endpoints(vec![
+            RemoteEndpointConfig {
+                endpoint: "http://127.0.0.1:9/primary".to_string(),
+                allow_insecure_http: true,
+                use_stored_api_key: false,
+                ..RemoteEndpointConfig::default()
+            },
+            RemoteEndpointConfig {
+                endpoint: "http://127.0.0.1:9/secondary".to_string(),
+                use_stored_api_key: false,
+                ..RemoteEndpointConfig::default()
+            },
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let audio = dir.path().join("audio.wav");
+        std::fs::write(&audio, b"RIFF").unwrap();
+
+        // The secondary rejects plain HTTP, proving it was reached after the primary.
+        let error = transcribe_with_remote_backends(&audio, &config, None).unwrap_err();
+        let message = format!("{:#}", error);
+        assert!(message.contains("All 2 remote endpoints failed"));
+        assert!(message.contains("must use HTTPS"));
+    }
+
+    #[test]
+    fn attempts_that_never_send_audio_are_not_counted_as_uploads() {
+        let config = Config::default();
+        let dir = tempfile::tempdir().unwrap();
+        let audio = dir.path().join("audio.wav");
+        std::fs::write(&audio, b"RIFF").unwrap();
+
+        let insecure = RemoteEndpointConfig {
+            endpoint: "http://127.0.0.1:9/v1/audio/transcriptions".to_string(),
+            use_stored_api_key: false,
+            ..RemoteEndpointConfig::default()
+        };
+        let attempt = transcribe_with_remote_endpoint(&audio, &insecure, &config, None);
+        assert!(attempt.result.is_err());
+        assert!(!attempt.uploaded);
+
+        let refused = RemoteEndpointConfig {
+            allow_insecure_http: true,
+            ..insecure
+        };
+        let attempt = transcribe_with_remote_endpoint(&audio, &refused, &config, None);
+        assert!(attempt.result.is_err());
+        assert!(!attempt.uploaded);
+    }
+
+    #[test]
+    fn client_rejects_half_configured_identity_and_bad_bundles() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert_only = RemoteEndpointConfig {
+            client_cert_path: Some(dir.path().join("client.pem")),
+            ..RemoteEndpointConfig::default()
+        };
+        assert!(build_remote_client(&cert_only).is_err());
+
+        let bundle = dir.path().join("ca.pem");
+        std::fs::write(&bundle, b"not a certificate").unwrap();
+        let bad_bundle = RemoteEndpointConfig {
+            ca_bundle_path: Some(bundle),
+            ..RemoteEndpointConfig::default()
+        };
+        assert!(build_remote_client(&bad_bundle).is_err());
+    }
+
+    #[test]
+    fn client_applies_validated_extra_headers() {
+        let mut endpoint = RemoteEndpointConfig::default();
+        endpoint
+            .extra_headers
+            .insert("X-Team-Id".to_string(), "speech".to_string());
+        assert!(build_remote_client(&endpoint).is_ok());
+
+        endpoint
+            .extra_headers
+            .insert("Authorization".to_string(), "Basic abc".to_string());
+        assert!(build_remote_client(&endpoint).is_err());
+    }
+
+    #[test]
+    fn transcript_parser_accepts_openai_and_plain_bodies() {
+        assert_eq!(
+            parse_remote_transcript(r#"{"text":"hi"}"#).unwrap().text,
+            "hi"
+        );
+        assert_eq!(
+            parse_remote_transcript(r#"{"choices":[{"text":"there"}]}"#)
+                .unwrap()
+                .text,
+            "there"
+        );
+        assert_eq!(parse_remote_transcript(" plain \n").unwrap().text, "plain");
+        assert!(parse_remote_transcript("  ").is_err());
+    }
+}
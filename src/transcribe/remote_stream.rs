@@ -0,0 +1,460 @@
+//! Realtime WebSocket streaming to a remote transcription backend.
+//!
+//! Wire protocol (one session per recording):
+//! - client → server text: `{"type":"start","sample_rate":16000,"encoding":"pcm_s16le",...}`
+//! - client → server binary: mono 16 kHz little-endian PCM16 audio
+//! - client → server text: `{"type":"finalize"}` once capture stops
+//! - server → client text: `{"type":"partial","text":"..."}` with the running hypothesis,
+//!   then `{"type":"final","text":"..."}`, or `{"type":"error","message":"..."}`
+//!
+//! The socket lives on its own thread so a slow network never stalls the
+//! transcription worker; a failed session falls back to the batch upload.
+
+use crate::{AppEvent, Config, RemoteEndpointConfig};
+use anyhow::{Context, Result};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender, TryRecvError};
+use serde_json::{json, Value};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+use tungstenite::client::IntoClientRequest;
+use tungstenite::http::{HeaderName, HeaderValue};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Connector, Message, WebSocket};
+
+/// How long a blocking socket read may wait before the session checks its inputs again.
+const POLL_INTERVAL: Duration = Duration::from_millis(40);
+const UPLOAD_SAMPLE_RATE: u32 = 16_000;
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+enum SessionInput {
+    Chunk(Vec<f32>),
+    Finalize,
+}
+
+/// Handle to one in-flight streaming session; dropping it cancels the session.
+pub(super) struct RemoteStreamSession {
+    input_tx: Sender<SessionInput>,
+    result_rx: Receiver<Result<String>>,
+    cancelled: Arc<AtomicBool>,
+    timeout: Duration,
+}
+
+impl RemoteStreamSession {
+    /// Start connecting in the background; chunks sent meanwhile are queued.
+    pub(super) fn spawn(
+        endpoint: &RemoteEndpointConfig,
+        config: &Config,
+        event_tx: Sender<AppEvent>,
+    ) -> Result<Self> {
+        validate_stream_endpoint(&endpoint.stream_endpoint, endpoint.allow_insecure_http)?;
+
+        let (input_tx, input_rx) = unbounded();
+        let (result_tx, result_rx) = bounded(1);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let timeout = Duration::from_millis(endpoint.timeout_ms.max(1_000));
+        let start = start_message(endpoint, config);
+        let endpoint = endpoint.clone();
+        let cancelled_for_thread = cancelled.clone();
+
+        std::thread::Builder::new()
+            .name("remote-stream".to_string())
+            .spawn(move || {
+                let result = run_session(
+                    &endpoint,
+                    start,
+                    &input_rx,
+                    &event_tx,
+                    &cancelled_for_thread,
+                    timeout,
+                );
+                if let Err(err) = &result {
+                    if !cancelled_for_thread.load(Ordering::SeqCst) {
+                        warn!("Remote streaming session failed: {:#}", err);
+                    }
+                }
+                let _ = result_tx.send(result);
+            })?;
+
+        Ok(Self {
+            input_tx,
+            result_rx,
+            cancelled,
+            timeout,
+        })
+    }
+
+    pub(super) fn send_chunk(&self, samples: Vec<f32>) {
+        let _ = self.input_tx.send(SessionInput::Chunk(samples));
+    }
+
+    pub(super) fn finalize(&self) {
+        let _ = self.input_tx.send(SessionInput::Finalize);
+    }
+
+    /// Finalize (if capture did not already) and wait for the server's final hypothesis.
+    pub(super) fn wait_final(self) -> Result<String> {
+        self.finalize();
+        // The session enforces `timeout` after finalize; allow the connect on top of that.
+        self.result_rx
+            .recv_timeout(self.timeout * 2)
+            .map_err(|_| anyhow::anyhow!("Remote stream timed out waiting for the final result"))?
+    }
+}
+
+impl Drop for RemoteStreamSession {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+fn validate_stream_endpoint(endpoint: &str, allow_insecure_http: bool) -> Result<()> {
+    let url = reqwest::Url::parse(endpoint.trim())?;
+    match url.scheme() {
+        "wss" => Ok(()),
+        "ws" if allow_insecure_http => Ok(()),
+        "ws" => anyhow::bail!(
+            "Streaming endpoint must use wss://. Enable allow_insecure_http to opt into plain ws://."
+        ),
+        scheme => anyhow::bail!("Streaming endpoint uses unsupported URL scheme: {}", scheme),
+    }
+}
+
+fn start_message(endpoint: &RemoteEndpointConfig, config: &Config) -> String {
+    json!({
+        "type": "start",
+        "sample_rate": UPLOAD_SAMPLE_RATE,
+        "encoding": "pcm_s16le",
+        "model": endpoint.model,
+        "language": config.language,
+        "translate": config.translate_to_english,
+    })
+    .to_string()
+}
+
+fn run_session(
+    endpoint: &RemoteEndpointConfig,
+    start: String,
+    input_rx: &Receiver<SessionInput>,
+    event_tx: &Sender<AppEvent>,
+    cancelled: &AtomicBool,
+    timeout: Duration,
+) -> Result<String> {
+    let mut socket = connect(endpoint, timeout)?;
+    socket.send(Message::Text(start))?;
+
+    let mut finalized_at: Option<Instant> = None;
+    loop {
+        if cancelled.load(Ordering::SeqCst) {
+            let _ = socket.close(None);
+            anyhow::bail!("Remote stream cancelled");
+        }
+
+        loop {
+            match input_rx.try_recv() {
+                Ok(SessionInput::Chunk(samples)) => {
+                    socket.write(Message::Binary(pcm16_bytes(&samples)))?;
+                }
+                Ok(SessionInput::Finalize) => {
+                    if finalized_at.is_none() {
+                        socket.write(Message::Text(json!({"type": "finalize"}).to_string()))?;
+                        finalized_at = Some(Instant::now());
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) if finalized_at.is_some() => break,
+                Err(TryRecvError::Disconnected) => anyhow::bail!("Remote stream abandoned"),
+            }
+        }
+        flush(&mut socket)?;
+
+        if let Some(started) = finalized_at {
+            if started.elapsed() > timeout {
+                let _ = socket.close(None);
+                anyhow::bail!("Remote stream did not deliver a final result in time");
+            }
+        }
+
+        let message = match socket.read() {
+            Ok(message) => message,
+            Err(tungstenite::Error::Io(err)) if is_timeout(&err) => continue,
+            Err(err) => return Err(err).context("Remote stream connection failed"),
+        };
+
+        match message {
+            Message::Text(text) => match parse_server_message(&text)? {
+                ServerMessage::Partial(text) => {
+                    let _ = event_tx.send(AppEvent::StreamingPartial(text));
+                }
+                ServerMessage::Final(text) => {
+                    let _ = socket.close(None);
+                    let _ = flush(&mut socket);
+                    return Ok(text);
+                }
+                ServerMessage::Ignored => {}
+            },
+            Message::Close(_) => anyhow::bail!("Remote stream closed before a final result"),
+            _ => {}
+        }
+    }
+}
+
+fn connect(endpoint: &RemoteEndpointConfig, timeout: Duration) -> Result<Socket> {
+    let mut request = endpoint.stream_endpoint.trim().into_client_request()?;
+    let uri = request.uri().clone();
+    let host = uri
+        .host()
+        .ok_or_else(|| anyhow::anyhow!("Streaming endpoint has no host"))?;
+    let secure = uri.scheme_str() == Some("wss");
+    let port = uri.port_u16().unwrap_or(if secure { 443 } else { 80 });
+
+    endpoint.validate_extra_headers()?;
+    let headers = request.headers_mut();
+    for (name, value) in &endpoint.extra_headers {
+        headers.insert(
+            HeaderName::from_bytes(name.trim().as_bytes())?,
+            HeaderValue::from_str(value.trim())?,
+        );
+    }
+    if let Some(token) = super::remote::resolve_remote_api_key(endpoint) {
+        headers.insert(
+            "Authorization",
+            HeaderValue::from_str(&format!("Bearer {}", token))?,
+        );
+    }
+
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Streaming endpoint host did not resolve"))?;
+    let tcp = TcpStream::connect_timeout(&address, timeout)?;
+    tcp.set_read_timeout(Some(timeout))?;
+    tcp.set_nodelay(true)?;
+
+    let connector = if secure {
+        Some(Connector::NativeTls(tls_connector(endpoint)?))
+    } else {
+        Some(Connector::Plain)
+    };
+    let (socket, response) = tungstenite::client_tls_with_config(request, tcp, None, connector)
+        .map_err(|err| anyhow::anyhow!("Streaming handshake failed: {}", err))?;
+    debug!("Remote stream connected ({})", response.status());
+
+    // Handshake done: switch to short reads so the session stays responsive.
+    match socket.get_ref() {
+        MaybeTlsStream::Plain(stream) => stream.set_read_timeout(Some(POLL_INTERVAL))?,
+        MaybeTlsStream::NativeTls(stream) => {
+            stream.get_ref().set_read_timeout(Some(POLL_INTERVAL))?
+        }
+        _ => {}
+    }
+
+    Ok(socket)
+}
+
+/// TLS settings mirroring the batch client: extra trust roots and an optional client identity.
+fn tls_connector(endpoint: &RemoteEndpointConfig) -> Result<native_tls::TlsConnector> {
+    let mut builder = native_tls::TlsConnector::builder();
+
+    if let Some(path) = &endpoint.ca_bundle_path {
+        let pem = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read CA bundle {:?}", path))?;
+        let blocks = pem_certificate_blocks(&pem);
+        if blocks.is_empty() {
+            anyhow::bail!("CA bundle {:?} contains no certificates", path);
+        }
+        for block in blocks {
+            builder.add_root_certificate(native_tls::Certificate::from_pem(block.as_bytes())?);
+        }
+    }
+
+    endpoint.validate_client_identity()?;
+    if let (Some(cert_path), Some(key_name)) =
+        (&endpoint.client_cert_path, &endpoint.client_key_secret)
+    {
+        let cert = std::fs::read(cert_path)
+            .with_context(|| format!("Failed to read client certificate {:?}", cert_path))?;
+        let key = crate::runtime::read_remote_secret(key_name)
+            .context("Failed to read client key from secrets")?;
+        builder.identity(native_tls::Identity::from_pkcs8(&cert, key.as_bytes())?);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Split a PEM bundle into its individual certificate blocks.
+fn pem_certificate_blocks(pem: &str) -> Vec<&str> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let mut blocks = Vec::new();
+    let mut rest = pem;
+    while let Some(start) = rest.find(BEGIN) {
+        let Some(length) = rest[start..].find(END) else {
+            break;
+        };
+        let end = start + length + END.len();
+        blocks.push(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    blocks
+}
+
+fn flush(socket: &mut Socket) -> Result<()> {
+    match socket.flush() {
+        Ok(()) => Ok(()),
+        Err(tungstenite::Error::Io(err)) if is_timeout(&err) => Ok(()),
+        Err(err) => Err(err).context("Remote stream connection failed"),
+    }
+}
+
+fn is_timeout(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+    )
+}
+
+fn pcm16_bytes(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|sample| ((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes())
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+enum ServerMessage {
+    Partial(String),
+    Final(String),
+    Ignored,
+}
+
+fn parse_server_message(text: &str) -> Result<ServerMessage> {
+    let json: Value = serde_json::from_str(text).context("Remote stream sent invalid JSON")?;
+    let text = || {
+        json.get("text")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    match json.get("type").and_then(Value::as_str) {
+        Some("partial") => Ok(ServerMessage::Partial(text())),
+        Some("final") => Ok(ServerMessage::Final(text())),
+        Some("error") => anyhow::bail!(
+            "Remote stream error: {}",
+            json.get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error")
+        ),
+        _ => Ok(ServerMessage::Ignored),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// Minimal server speaking the protocol: one partial per chunk, then a final.
+    fn spawn_mock_server() -> (String, std::thread::JoinHandle<Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/v1/stream", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut socket = tungstenite::accept(stream).unwrap();
+            let start: Value = match socket.read().unwrap() {
+                Message::Text(text) => serde_json::from_str(&text).unwrap(),
+                other => panic!("expected start message, got {:?}", other),
+            };
+            let mut samples = 0;
+            loop {
+                match socket.read().unwrap() {
+                    Message::Binary(bytes) => {
+                        samples += bytes.len() / 2;
+                        let partial =
+                            json!({"type": "partial", "text": format!("heard {}", samples)});
+                        socket.send(Message::Text(partial.to_string())).unwrap();
+                    }
+                    Message::Text(text) if text.contains("finalize") => {
+                        let done = json!({"type": "final", "text": format!("final {}", samples)});
+                        socket.send(Message::Text(done.to_string())).unwrap();
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            start
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn streams_chunks_and_returns_partials_and_final() {
+        let (url, server) = spawn_mock_server();
+        let endpoint = RemoteEndpointConfig {
+            endpoint: "http://127.0.0.1:9/unused".to_string(),
+            stream_endpoint: url,
+            allow_insecure_http: true,
+            use_stored_api_key: false,
+            ..RemoteEndpointConfig::default()
+        };
+        let config = Config {
+            language: "de".to_string(),
+            ..Config::default()
+        };
+        let (event_tx, event_rx) = unbounded();
+
+        let session = RemoteStreamSession::spawn(&endpoint, &config, event_tx).unwrap();
+        session.send_chunk(vec![0.1; 1600]);
+        session.send_chunk(vec![-0.1; 1600]);
+        session.finalize();
+        assert_eq!(session.wait_final().unwrap(), "final 3200");
+
+        let partials: Vec<String> = event_rx
+            .try_iter()
+            .filter_map(|event| match event {
+                AppEvent::StreamingPartial(text) => Some(text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(partials.last().map(String::as_str), Some("heard 3200"));
+
+        let start = server.join().unwrap();
+        assert_eq!(start["sample_rate"], 16_000);
+        assert_eq!(start["language"], "de");
+    }
+
+    #[test]
+    fn plain_websocket_requires_explicit_opt_in() {
+        assert!(validate_stream_endpoint("wss://example.com/stream", false).is_ok());
+        assert!(validate_stream_endpoint("ws://example.com/stream", false).is_err());
+        assert!(validate_stream_endpoint("ws://example.com/stream", true).is_ok());
+        assert!(validate_stream_endpoint("https://example.com/stream", true).is_err());
+    }
+
+    #[test]
+    fn pem_bundles_split_into_certificates() {
+        let bundle = "junk\n-----BEGIN CERTIFICATE-----\nAAA\n-----END CERTIFICATE-----\n\
+                      -----BEGIN CERTIFICATE-----\nBBB\n-----END CERTIFICATE-----\n";
+        let blocks = pem_certificate_blocks(bundle);
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks[1].contains("BBB"));
+        assert!(pem_certificate_blocks("no certificates").is_empty());
+    }
+
+    #[test]
+    fn server_errors_end_the_session() {
+        assert_eq!(
+            parse_server_message(r#"{"type":"partial","text":"hel"}"#).unwrap(),
+            ServerMessage::Partial("hel".to_string())
+        );
+        assert_eq!(
+            parse_server_message(r#"{"type":"ready"}"#).unwrap(),
+            ServerMessage::Ignored
+        );
+        assert!(parse_server_message(r#"{"type":"error","message":"busy"}"#).is_err());
+    }
+}
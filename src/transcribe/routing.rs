@@ -0,0 +1,281 @@
+//! Per-language model routing.
+//!
+//! `language_models` maps language codes to models and every other language
+//! uses `model_path`. With a fixed language the model is looked up directly.
+//! With "auto" the language is first detected on a multilingual model (the
+//! default one when it is multilingual), restricted to `candidate_languages`
+//! when any are set, and the dictation then goes to that language's model
+//! with the detected language set, so Whisper does not detect it a second
+//! time. A language pinned from the tray, IPC or the engine API replaces the
+//! configured one for the next dictation only.
+
+use super::{load_wav_samples, should_use_gpu, ContextCache};
+use crate::{models, Config};
+use anyhow::Result;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+const SAMPLE_RATE: usize = 16_000;
+/// Whisper detects the language from the first 30 seconds of audio.
+const DETECTION_WINDOW_SAMPLES: usize = 30 * SAMPLE_RATE;
+
+/// Refuse a language pin Whisper does not know; `None` unpins and is always valid.
+pub(crate) fn check_language_pin(language: Option<&str>) -> Result<()> {
+    if let Some(code) = language {
+        if whisper_rs::get_lang_id(code).is_none() {
+            anyhow::bail!("Unknown Whisper language code {:?}", code);
+        }
+    }
+    Ok(())
+}
+
+/// `config` with the language pinned for this dictation, if any.
+pub(super) fn with_pinned_language(mut config: Config, pinned: Option<String>) -> Config {
+    if let Some(language) = pinned {
+        config.language = language;
+    }
+    config
+}
+
+/// `config` with the model and language for dictating in `language`.
+fn route<'a>(config: &'a Config, language: &str) -> Cow<'a, Config> {
+    let model_path = models::model_for_language(config, language);
+    if model_path == config.model_path && language == config.language {
+        return Cow::Borrowed(config);
+    }
+    let mut routed = config.clone();
+    routed.model_path = model_path.to_path_buf();
+    routed.language = language.to_string();
+    Cow::Owned(routed)
+}
+
+/// `config` routed by its configured language; "auto" keeps the default model.
+pub(super) fn for_configured_language(config: &Config) -> Cow<'_, Config> {
+    if config.language == "auto" {
+        Cow::Borrowed(config)
+    } else {
+        route(config, &config.language)
+    }
+}
+
+/// The multilingual model used to detect the language, preferring the default model.
+fn detection_model(config: &Config) -> Option<&Path> {
+    std::iter::once(&config.model_path)
+        .chain(config.language_models.values())
+        .find(|path| path.exists() && models::is_multilingual(path))
+        .map(PathBuf::as_path)
+}
+
+/// The model to keep loaded between dictations.
+pub(super) fn resident_model(config: &Config) -> Cow<'_, Path> {
+    if config.language == "auto" {
+        if let Some(detector) = detection_model(config) {
+            return Cow::Borrowed(detector);
+        }
+    }
+    match for_configured_language(config) {
+        Cow::Borrowed(config) => Cow::Borrowed(config.model_path.as_path()),
+        Cow::Owned(config) => Cow::Owned(config.model_path),
+    }
+}
+
+/// Candidate language codes Whisper does not know; detection ignores them.
+pub(crate) fn unknown_candidate_languages(config: &Config) -> Vec<&str> {
+    config
+        .candidate_languages
+        .iter()
+        .map(String::as_str)
+        .filter(|code| whisper_rs::get_lang_id(code).is_none())
+        .collect()
+}
+
+/// The most probable language, among `candidates` when any are known to Whisper.
+fn pick_language(probabilities: &[f32], candidates: &[String]) -> Option<&'static str> {
+    let allowed: Vec<usize> = candidates
+        .iter()
+        .filter_map(|code| whisper_rs::get_lang_id(code))
+        .map(|id| id as usize)
+        .collect();
+    probabilities
+        .iter()
+        .enumerate()
+        .filter(|(id, _)| allowed.is_empty() || allowed.contains(id))
+        .max_by(|(_, left), (_, right)| left.total_cmp(right))
+        .and_then(|(id, _)| whisper_rs::get_lang_str(id as i32))
+}
+
+fn detect_language(
+    audio_path: &Path,
+    detector: &Path,
+    config: &Config,
+    context_cache: &mut ContextCache,
+) -> Result<Option<String>> {
+    let samples = load_wav_samples(&audio_path.to_path_buf())?;
+    let window = &samples[..samples.len().min(DETECTION_WINDOW_SAMPLES)];
+    let threads = num_cpus::get().max(1);
+    let use_gpu = should_use_gpu(config, detector, context_cache);
+    let cached = context_cache.get_or_create(detector, use_gpu)?;
+    let probabilities = cached.with_state(|state| {
+        state.pcm_to_mel(window, threads)?;
+        Ok(state.lang_detect(0, threads)?.1)
+    })?;
+    Ok(pick_language(&probabilities, &config.candidate_languages).map(str::to_string))
+}
+
+/// `config` with the model and language for the dictation in `audio_path`.
+pub(super) fn for_dictation<'a>(
+    audio_path: &Path,
+    config: &'a Config,
+    context_cache: &mut ContextCache,
+) -> Cow<'a, Config> {
+    if config.language != "auto" {
+        return for_configured_language(config);
+    }
+    // Detecting here costs the same encoder pass Whisper would spend on it.
+    let Some(detector) = detection_model(config) else {
+        debug!("No multilingual model installed to detect the language with");
+        return Cow::Borrowed(config);
+    };
+    let language = match config.candidate_languages.as_slice() {
+        [only] if whisper_rs::get_lang_id(only).is_some() => Ok(Some(only.clone())),
+        _ => detect_language(audio_path, detector, config, context_cache),
+    };
+    let language = match language {
+        Ok(Some(language)) => language,
+        Ok(None) => return Cow::Borrowed(config),
+        Err(err) => {
+            warn!(
+                "Language detection failed, using the default model: {:#}",
+                err
+            );
+            return Cow::Borrowed(config);
+        }
+    };
+
+    let routed = route(config, &language);
+    // Alternating languages must not reload both models every dictation, so a
+    // model that cannot stay loaded beside the detector is skipped.
+    let use_gpu = should_use_gpu(config, &routed.model_path, context_cache);
+    if routed.model_path != detector
+        && !context_cache.fits_alongside(&routed.model_path, detector, use_gpu)
+    {
+        info!(
+            "Transcribing {} with {:?}: {:?} does not fit in the memory budget beside it",
+            language, detector, routed.model_path
+        );
+        let mut fallback = config.clone();
+        fallback.model_path = detector.to_path_buf();
+        fallback.language = language;
+        return Cow::Owned(fallback);
+    }
+    info!(
+        "Detected {}, transcribing with {:?}",
+        language, routed.model_path
+    );
+    routed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn config_with(dir: &Path, language: &str) -> Config {
+        let path = |name: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, b"model").unwrap();
+            path
+        };
+        Config {
+            model_path: path("ggml-small.bin"),
+            language: language.to_string(),
+            language_models: BTreeMap::from([
+                ("en".to_string(), path("ggml-distil-large-v3.en.bin")),
+                ("fr".to_string(), dir.join("ggml-missing.bin")),
+            ]),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn configured_language_picks_its_model_or_the_default() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let english = config_with(dir.path(), "en");
+        let routed = for_configured_language(&english);
+        assert_eq!(
+            routed.model_path,
+            dir.path().join("ggml-distil-large-v3.en.bin")
+        );
+        assert!(matches!(routed, Cow::Owned(_)));
+
+        let german = config_with(dir.path(), "de");
+        assert!(matches!(for_configured_language(&german), Cow::Borrowed(_)));
+
+        let french = config_with(dir.path(), "fr");
+        assert_eq!(
+            for_configured_language(&french).model_path,
+            dir.path().join("ggml-small.bin")
+        );
+    }
+
+    #[test]
+    fn detection_is_restricted_to_candidates() {
+        let mut probabilities = vec![0.0; whisper_rs::get_lang_max_id() as usize + 1];
+        let id = |code: &str| whisper_rs::get_lang_id(code).unwrap() as usize;
+        probabilities[id("nl")] = 0.5;
+        probabilities[id("de")] = 0.3;
+        probabilities[id("en")] = 0.2;
+
+        assert_eq!(pick_language(&probabilities, &[]), Some("nl"));
+        let candidates = ["en".to_string(), "de".to_string()];
+        assert_eq!(pick_language(&probabilities, &candidates), Some("de"));
+    }
+
+    #[test]
+    fn unknown_candidates_are_reported() {
+        let config = Config {
+            candidate_languages: vec!["en".to_string(), "eng-GB".to_string(), "de".to_string()],
+            ..Config::default()
+        };
+        assert_eq!(unknown_candidate_languages(&config), ["eng-GB"]);
+    }
+
+    #[test]
+    fn pinned_language_overrides_the_configured_one() {
+        assert!(check_language_pin(Some("xx-not-a-language")).is_err());
+        assert!(check_language_pin(Some("de")).is_ok());
+        assert!(check_language_pin(None).is_ok());
+
+        let config = with_pinned_language(Config::default(), Some("de".to_string()));
+        assert_eq!(config.language, "de");
+        let unpinned = with_pinned_language(Config::default(), None);
+        assert_eq!(unpinned.language, "en");
+    }
+
+    #[test]
+    fn auto_language_keeps_the_multilingual_detector_loaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config_with(dir.path(), "auto");
+        assert_eq!(detection_model(&config), Some(config.model_path.as_path()));
+        assert_eq!(
+            resident_model(&config).as_ref(),
+            config.model_path.as_path()
+        );
+
+        let detected = route(&config, "en");
+        assert_eq!(detected.language, "en");
+        assert_eq!(
+            detected.model_path,
+            dir.path().join("ggml-distil-large-v3.en.bin")
+        );
+
+        let english_only = Config {
+            model_path: dir.path().join("ggml-distil-large-v3.en.bin"),
+            language_models: BTreeMap::new(),
+            ..config
+        };
+        assert_eq!(detection_model(&english_only), None);
+    }
+}
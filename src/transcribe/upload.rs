@@ -0,0 +1,259 @@
+//! Audio encoding applied before a recording is uploaded to a remote backend.
+//!
+//! Recordings are 16 kHz mono PCM WAV. FLAC halves the upload losslessly;
+//! Ogg Opus at voice bitrates shrinks it roughly twentyfold when the build
+//! includes the `remote-opus` feature.
+
+use crate::RemoteUploadFormat;
+use anyhow::Result;
+use std::path::Path;
+use tracing::{debug, warn};
+
+const UPLOAD_SAMPLE_RATE: u32 = 16_000;
+
+/// Encoded audio body plus the multipart metadata that describes it.
+pub(super) struct UploadAudio {
+    pub bytes: Vec<u8>,
+    pub file_name: &'static str,
+    pub mime: &'static str,
+}
+
+/// The format this build can send; Opus without the `remote-opus` feature is sent as WAV.
+pub(crate) fn supported_upload_format(format: RemoteUploadFormat) -> RemoteUploadFormat {
+    match format {
+        RemoteUploadFormat::Opus if !cfg!(feature = "remote-opus") => RemoteUploadFormat::Wav,
+        format => format,
+    }
+}
+
+/// Encode `audio_path` for upload, falling back to the original WAV on failure.
+pub(super) fn prepare_upload(audio_path: &Path, format: RemoteUploadFormat) -> Result<UploadAudio> {
+    let format = supported_upload_format(format);
+    let encoded = match format {
+        RemoteUploadFormat::Wav => None,
+        RemoteUploadFormat::Flac => {
+            Some(read_pcm16_mono(audio_path).and_then(|pcm| encode_flac(&pcm)))
+        }
+        RemoteUploadFormat::Opus => {
+            Some(read_pcm16_mono(audio_path).and_then(|pcm| encode_opus(&pcm)))
+        }
+    };
+
+    match encoded {
+        Some(Ok(bytes)) => {
+            let (file_name, mime) = upload_metadata(format);
+            debug!("Encoded upload as {} ({} bytes)", mime, bytes.len());
+            Ok(UploadAudio {
+                bytes,
+                file_name,
+                mime,
+            })
+        }
+        Some(Err(err)) => {
+            warn!(
+                "Could not encode upload as {:?}, sending WAV: {}",
+                format, err
+            );
+            read_wav(audio_path)
+        }
+        None => read_wav(audio_path),
+    }
+}
+
+fn read_wav(audio_path: &Path) -> Result<UploadAudio> {
+    let (file_name, mime) = upload_metadata(RemoteUploadFormat::Wav);
+    Ok(UploadAudio {
+        bytes: std::fs::read(audio_path)?,
+        file_name,
+        mime,
+    })
+}
+
+/// Multipart file name and MIME type for each upload format.
+pub(super) fn upload_metadata(format: RemoteUploadFormat) -> (&'static str, &'static str) {
+    match format {
+        RemoteUploadFormat::Wav => ("audio.wav", "audio/wav"),
+        RemoteUploadFormat::Flac => ("audio.flac", "audio/flac"),
+        RemoteUploadFormat::Opus => ("audio.ogg", "audio/ogg"),
+    }
+}
+
+/// Read a recording as 16-bit mono samples at 16 kHz.
+fn read_pcm16_mono(audio_path: &Path) -> Result<Vec<i16>> {
+    let mut reader = hound::WavReader::open(audio_path)?;
+    let spec = reader.spec();
+    if spec.sample_rate != UPLOAD_SAMPLE_RATE {
+        anyhow::bail!("Expected 16kHz audio, got {} Hz", spec.sample_rate);
+    }
+
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Int => {
+            let max_val = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|value| value as f32 / max_val))
+                .collect::<std::result::Result<Vec<_>, _>>()?
+        }
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .collect::<std::result::Result<Vec<_>, _>>()?,
+    };
+
+    let channels = spec.channels.max(1) as usize;
+    Ok(samples
+        .chunks(channels)
+        .map(|frame| {
+            let mixed = frame.iter().sum::<f32>() / frame.len() as f32;
+            (mixed.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+        })
+        .collect())
+}
+
+fn encode_flac(pcm: &[i16]) -> Result<Vec<u8>> {
+    use flacenc::component::BitRepr;
+    use flacenc::error::Verify;
+
+    let config = flacenc::config::Encoder::default()
+        .into_verified()
+        .map_err(|(_, err)| anyhow::anyhow!("Invalid FLAC encoder config: {:?}", err))?;
+    let samples: Vec<i32> = pcm.iter().map(|&sample| i32::from(sample)).collect();
+    let source =
+        flacenc::source::MemSource::from_samples(&samples, 1, 16, UPLOAD_SAMPLE_RATE as usize);
+    let stream = flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
+        .map_err(|err| anyhow::anyhow!("FLAC encoding failed: {:?}", err))?;
+
+    let mut sink = flacenc::bitsink::ByteSink::new();
+    stream
+        .write(&mut sink)
+        .map_err(|err| anyhow::anyhow!("FLAC encoding failed: {:?}", err))?;
+    Ok(sink.as_slice().to_vec())
+}
+
+#[cfg(feature = "remote-opus")]
+fn encode_opus(pcm: &[i16]) -> Result<Vec<u8>> {
+    use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+
+    /// 20 ms frames at 16 kHz.
+    const FRAME_SAMPLES: usize = 320;
+    /// Ogg Opus granule positions always count 48 kHz samples.
+    const GRANULE_PER_SAMPLE: u64 = 48_000 / UPLOAD_SAMPLE_RATE as u64;
+    const SERIAL: u32 = 0x05a1_5a00;
+
+    let mut encoder = opus::Encoder::new(
+        UPLOAD_SAMPLE_RATE,
+        opus::Channels::Mono,
+        opus::Application::Voip,
+    )?;
+    encoder.set_bitrate(opus::Bitrate::Bits(24_000))?;
+    let pre_skip = (encoder.get_lookahead()? as u64 * GRANULE_PER_SAMPLE) as u16;
+
+    // RFC 7845 identification and comment headers.
+    let mut head = Vec::with_capacity(19);
+    head.extend_from_slice(b"OpusHead");
+    head.push(1);
+    head.push(1);
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&UPLOAD_SAMPLE_RATE.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes());
+    head.push(0);
+
+    let vendor = crate::PRODUCT_NAME.as_bytes();
+    let mut tags = Vec::with_capacity(16 + vendor.len());
+    tags.extend_from_slice(b"OpusTags");
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor);
+    tags.extend_from_slice(&0u32.to_le_bytes());
+
+    let mut out = Vec::new();
+    let mut writer = PacketWriter::new(&mut out);
+    writer.write_packet(head, SERIAL, PacketWriteEndInfo::EndPage, 0)?;
+    writer.write_packet(tags, SERIAL, PacketWriteEndInfo::EndPage, 0)?;
+
+    let frame_count = pcm.len().div_ceil(FRAME_SAMPLES).max(1);
+    let mut frame = [0i16; FRAME_SAMPLES];
+    let mut packet = vec![0u8; 4000];
+    for index in 0..frame_count {
+        let start = index * FRAME_SAMPLES;
+        let end = (start + FRAME_SAMPLES).min(pcm.len());
+        frame.fill(0);
+        frame[..end.saturating_sub(start)].copy_from_slice(&pcm[start.min(end)..end]);
+
+        let len = encoder.encode(&frame, &mut packet)?;
+        let is_last = index + 1 == frame_count;
+        // `end` stops at the last real sample, so the final granule trims the padding.
+        let granule = u64::from(pre_skip) + end as u64 * GRANULE_PER_SAMPLE;
+        let info = if is_last {
+            PacketWriteEndInfo::EndStream
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+        writer.write_packet(packet[..len].to_vec(), SERIAL, info, granule)?;
+    }
+    drop(writer);
+
+    Ok(out)
+}
+
+#[cfg(not(feature = "remote-opus"))]
+fn encode_opus(_pcm: &[i16]) -> Result<Vec<u8>> {
+    anyhow::bail!("Opus upload requires a build with the remote-opus feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_test_wav(path: &Path, samples: usize) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: UPLOAD_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for n in 0..samples {
+            let phase = n as f32 * 440.0 * std::f32::consts::TAU / UPLOAD_SAMPLE_RATE as f32;
+            writer.write_sample((phase.sin() * 8_000.0) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    #[test]
+    fn upload_metadata_matches_container() {
+        assert_eq!(
+            upload_metadata(RemoteUploadFormat::Wav),
+            ("audio.wav", "audio/wav")
+        );
+        assert_eq!(
+            upload_metadata(RemoteUploadFormat::Flac),
+            ("audio.flac", "audio/flac")
+        );
+        assert_eq!(
+            upload_metadata(RemoteUploadFormat::Opus),
+            ("audio.ogg", "audio/ogg")
+        );
+    }
+
+    #[test]
+    fn flac_upload_is_smaller_than_wav() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audio.wav");
+        write_test_wav(&path, UPLOAD_SAMPLE_RATE as usize);
+
+        let upload = prepare_upload(&path, RemoteUploadFormat::Flac).unwrap();
+        assert_eq!(upload.mime, "audio/flac");
+        assert!(upload.bytes.starts_with(b"fLaC"));
+        assert!(upload.bytes.len() < std::fs::metadata(&path).unwrap().len() as usize);
+    }
+
+    #[test]
+    fn unreadable_audio_falls_back_to_raw_wav_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audio.wav");
+        std::fs::write(&path, b"RIFF").unwrap();
+
+        let upload = prepare_upload(&path, RemoteUploadFormat::Flac).unwrap();
+        assert_eq!(upload.file_name, "audio.wav");
+        assert_eq!(upload.bytes, b"RIFF");
+    }
+}
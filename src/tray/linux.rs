@@ -3,7 +3,7 @@
 //! Uses ksni for system tray indicator (works with GNOME's AppIndicator extension)
 
 use crate::state::{AppPhase, DeliveryOutcome};
-use crate::{AppEvent, AppState, Config};
+use crate::{AppEvent, AppState, Config, TranscriptionBackend};
 use anyhow::Result;
 use crossbeam_channel::Sender;
 use ksni::{menu::*, ToolTip, Tray, TrayService};
@@ -104,6 +104,11 @@ impl Tray for MorpheosVoiceTray {
         let history_count = state.clipboard_history.len();
         let hotkey = crate::format_hotkey(&self.config.read().unwrap().hotkey);
         let status_label = phase_description(&state.phase, &hotkey);
+        let usage_label = {
+            let config = self.config.read().unwrap();
+            (config.backend == TranscriptionBackend::Remote)
+                .then(|| crate::usage::usage_summary(&config))
+        };
 
         let mut menu = vec![
             StandardItem {
@@ -121,6 +126,17 @@ impl Tray for MorpheosVoiceTray {
             .into(),
         ];
 
+        if let Some(label) = usage_label {
+            menu.push(
+                StandardItem {
+                    label,
+                    enabled: false,
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+
         // Add recent items to menu (last 5)
         if !state.clipboard_history.is_empty() {
             menu.push(MenuItem::Separator);
@@ -150,6 +166,64 @@ impl Tray for MorpheosVoiceTray {
             }
         }
 
+        let profiles: Vec<String> = self
+            .config
+            .read()
+            .unwrap()
+            .profiles
+            .keys()
+            .cloned()
+            .collect();
+        if !profiles.is_empty() {
+            menu.push(MenuItem::Separator);
+            let active = state.active_profile.clone();
+            let choices = std::iter::once(None).chain(profiles.into_iter().map(Some));
+            for profile in choices {
+                let label = match &profile {
+                    Some(name) => format!("Profile: {}", name),
+                    None => "Profile: none".to_string(),
+                };
+                menu.push(
+                    CheckmarkItem {
+                        label,
+                        checked: active == profile,
+                        activate: Box::new(move |tray: &mut Self| {
+                            let _ = tray.event_tx.send(AppEvent::SwitchProfile(profile.clone()));
+                        }),
+                        ..Default::default()
+                    }
+                    .into(),
+                );
+            }
+        }
+
+        // Quick switch between the languages Auto-detect may pick
+        let candidates = self
+            .config
+            .read()
+            .unwrap()
+            .with_active_profile()
+            .candidate_languages;
+        if !candidates.is_empty() {
+            menu.push(MenuItem::Separator);
+            let pinned = state.pinned_language.clone();
+            for code in candidates {
+                let checked = pinned.as_deref() == Some(code.as_str());
+                menu.push(
+                    CheckmarkItem {
+                        label: format!("Next dictation: {}", code),
+                        checked,
+                        activate: Box::new(move |tray: &mut Self| {
+                            let pin = (!checked).then(|| code.clone());
+                            let _ = tray.event_tx.send(AppEvent::PinLanguage(pin));
+                        }),
+                        ..Default::default()
+                    }
+                    .into(),
+                );
+            }
+        }
+
         menu.extend(vec![
             MenuItem::Separator,
             StandardItem {
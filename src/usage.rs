@@ -0,0 +1,320 @@
+//! Local accounting of audio sent to remote endpoints.
+//!
+//! Hosted speech APIs bill per audio minute, so every remote request records
+//! its audio duration, per endpoint and local calendar day, in an owner-only
+//! file. The optional budget caps read the same totals.
+
+use crate::{get_data_dir, persistence, Config, RemoteBudgetConfig};
+use anyhow::{Context, Result};
+use chrono::{Datelike, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+pub const USAGE_SCHEMA_VERSION: u32 = 1;
+/// Days kept on disk; enough to compare the same month across a year.
+const RETAINED_DAYS: i64 = 400;
+
+/// Counters for one endpoint, day or month.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    /// Seconds of audio uploaded, including requests that later failed
+    #[serde(default)]
+    pub audio_seconds: f64,
+    #[serde(default)]
+    pub requests: u64,
+    #[serde(default)]
+    pub failures: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, other: &UsageTotals) {
+        self.audio_seconds += other.audio_seconds;
+        self.requests += other.requests;
+        self.failures += other.failures;
+    }
+
+    pub fn audio_minutes(&self) -> f64 {
+        self.audio_seconds / 60.0
+    }
+}
+
+/// Per-day, per-endpoint remote usage as persisted in `remote_usage.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteUsage {
+    schema_version: u32,
+    #[serde(default)]
+    days: BTreeMap<NaiveDate, BTreeMap<String, UsageTotals>>,
+}
+
+impl Default for RemoteUsage {
+    fn default() -> Self {
+        Self {
+            schema_version: USAGE_SCHEMA_VERSION,
+            days: BTreeMap::new(),
+        }
+    }
+}
+
+impl RemoteUsage {
+    /// Count one request and drop days older than the retention window.
+    pub fn record(&mut self, day: NaiveDate, endpoint: &str, audio_seconds: f64, succeeded: bool) {
+        let totals = self
+            .days
+            .entry(day)
+            .or_default()
+            .entry(endpoint_key(endpoint))
+            .or_default();
+        totals.audio_seconds += audio_seconds.max(0.0);
+        totals.requests += 1;
+        if !succeeded {
+            totals.failures += 1;
+        }
+
+        let oldest = day - chrono::Duration::days(RETAINED_DAYS);
+        self.days.retain(|date, _| *date > oldest);
+    }
+
+    /// Totals for each endpoint on `day`.
+    pub fn endpoints_on(&self, day: NaiveDate) -> BTreeMap<String, UsageTotals> {
+        self.days.get(&day).cloned().unwrap_or_default()
+    }
+
+    pub fn day_total(&self, day: NaiveDate) -> UsageTotals {
+        self.total_where(|date| date == day)
+    }
+
+    pub fn month_total(&self, day: NaiveDate) -> UsageTotals {
+        self.total_where(|date| date.year() == day.year() && date.month() == day.month())
+    }
+
+    fn total_where(&self, include: impl Fn(NaiveDate) -> bool) -> UsageTotals {
+        let mut total = UsageTotals::default();
+        for (date, endpoints) in &self.days {
+            if include(*date) {
+                for totals in endpoints.values() {
+                    total.add(totals);
+                }
+            }
+        }
+        total
+    }
+
+    /// Describe the first cap that `day` has reached, if any.
+    pub fn budget_exceeded(&self, budget: &RemoteBudgetConfig, day: NaiveDate) -> Option<String> {
+        if let Some(limit) = budget.daily_minutes {
+            let used = self.day_total(day).audio_minutes();
+            if used >= limit as f64 {
+                return Some(format!(
+                    "daily cap of {} min reached ({:.1} min used)",
+                    limit, used
+                ));
+            }
+        }
+        if let Some(limit) = budget.monthly_minutes {
+            let used = self.month_total(day).audio_minutes();
+            if used >= limit as f64 {
+                return Some(format!(
+                    "monthly cap of {} min reached ({:.1} min used)",
+                    limit, used
+                ));
+            }
+        }
+        None
+    }
+
+    /// One-line summary of today and this month against the configured caps.
+    pub fn summary(&self, budget: &RemoteBudgetConfig, day: NaiveDate) -> String {
+        let today = self.day_total(day);
+        let month = self.month_total(day);
+        format!(
+            "Remote today: {} · this month: {}",
+            describe_totals(&today, budget.daily_minutes),
+            describe_totals(&month, budget.monthly_minutes)
+        )
+    }
+}
+
+fn describe_totals(totals: &UsageTotals, cap: Option<u32>) -> String {
+    let minutes = match cap {
+        Some(cap) => format!("{:.1}/{} min", totals.audio_minutes(), cap),
+        None => format!("{:.1} min", totals.audio_minutes()),
+    };
+    if totals.failures > 0 {
+        format!(
+            "{}, {} requests ({} failed)",
+            minutes, totals.requests, totals.failures
+        )
+    } else {
+        format!("{}, {} requests", minutes, totals.requests)
+    }
+}
+
+/// Identify an endpoint without persisting credentials or query parameters from its URL.
+fn endpoint_key(endpoint: &str) -> String {
+    match reqwest::Url::parse(endpoint.trim()) {
+        Ok(mut url) => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            url.set_query(None);
+            url.set_fragment(None);
+            url.to_string()
+        }
+        Err(_) => endpoint.trim().to_string(),
+    }
+}
+
+pub fn usage_path() -> PathBuf {
+    get_data_dir().join("remote_usage.json")
+}
+
+pub fn load_usage() -> Result<RemoteUsage> {
+    let path = usage_path();
+    if path.exists() {
+        persistence::read_json_private(&path)
+            .with_context(|| format!("Remote usage file is invalid: {:?}", path))
+    } else {
+        Ok(RemoteUsage::default())
+    }
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// Record one remote request for the recording at `audio_path`; failures to persist are logged.
+pub(crate) fn record_remote_request(endpoint: &str, audio_path: &Path, succeeded: bool) {
+    let seconds = recording_seconds(audio_path);
+    let result = load_usage().and_then(|mut usage| {
+        usage.record(today(), endpoint, seconds, succeeded);
+        persistence::write_json_private(&usage_path(), &usage)
+    });
+    if let Err(err) = result {
+        warn!("Failed to record remote usage: {}", err);
+    }
+}
+
+/// Reason the remote backend should be skipped for this recording, if a cap is reached.
+pub(crate) fn remote_budget_exceeded(config: &Config) -> Option<String> {
+    let budget = &config.remote_backend.budget;
+    if budget.daily_minutes.is_none() && budget.monthly_minutes.is_none() {
+        return None;
+    }
+    match load_usage() {
+        Ok(usage) => usage.budget_exceeded(budget, today()),
+        Err(err) => {
+            warn!("Failed to read remote usage: {}", err);
+            None
+        }
+    }
+}
+
+/// Summary line for the tray and Backend tab.
+pub(crate) fn usage_summary(config: &Config) -> String {
+    match load_usage() {
+        Ok(usage) => usage.summary(&config.remote_backend.budget, today()),
+        Err(err) => format!("Remote usage unavailable: {}", err),
+    }
+}
+
+/// One line per endpoint used today.
+pub(crate) fn usage_by_endpoint_today() -> Vec<String> {
+    let Ok(usage) = load_usage() else {
+        return Vec::new();
+    };
+    usage
+        .endpoints_on(today())
+        .iter()
+        .map(|(endpoint, totals)| format!("{}: {}", endpoint, describe_totals(totals, None)))
+        .collect()
+}
+
+fn recording_seconds(audio_path: &Path) -> f64 {
+    match hound::WavReader::open(audio_path) {
+        Ok(reader) => reader.duration() as f64 / reader.spec().sample_rate.max(1) as f64,
+        Err(err) => {
+            warn!("Could not measure recording length for usage: {}", err);
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn totals_are_grouped_by_day_month_and_endpoint() {
+        let mut usage = RemoteUsage::default();
+        usage.record(date("2026-03-30"), "https://a.example/v1", 60.0, true);
+        usage.record(date("2026-03-31"), "https://a.example/v1", 30.0, false);
+        usage.record(date("2026-03-31"), "https://b.example/v1", 90.0, true);
+        usage.record(date("2026-04-01"), "https://a.example/v1", 120.0, true);
+
+        let day = usage.day_total(date("2026-03-31"));
+        assert_eq!(day.audio_seconds, 120.0);
+        assert_eq!(day.requests, 2);
+        assert_eq!(day.failures, 1);
+        assert_eq!(usage.endpoints_on(date("2026-03-31")).len(), 2);
+
+        let march = usage.month_total(date("2026-03-01"));
+        assert_eq!(march.audio_seconds, 180.0);
+        assert_eq!(march.requests, 3);
+    }
+
+    #[test]
+    fn caps_trip_once_reached() {
+        let mut usage = RemoteUsage::default();
+        let today = date("2026-05-10");
+        usage.record(date("2026-05-02"), "https://a.example/v1", 240.0, true);
+        usage.record(today, "https://a.example/v1", 90.0, true);
+
+        let daily = RemoteBudgetConfig {
+            daily_minutes: Some(1),
+            monthly_minutes: None,
+        };
+        assert!(usage
+            .budget_exceeded(&daily, today)
+            .unwrap()
+            .contains("daily"));
+        assert!(usage.budget_exceeded(&daily, date("2026-05-11")).is_none());
+
+        let monthly = RemoteBudgetConfig {
+            daily_minutes: Some(10),
+            monthly_minutes: Some(5),
+        };
+        assert!(usage
+            .budget_exceeded(&monthly, today)
+            .unwrap()
+            .contains("monthly"));
+        assert!(usage
+            .budget_exceeded(&RemoteBudgetConfig::default(), today)
+            .is_none());
+    }
+
+    #[test]
+    fn endpoint_keys_drop_credentials_and_old_days_expire() {
+        let mut usage = RemoteUsage::default();
+        usage.record(
+            date("2025-01-01"),
+            "https://user:pw@a.example/v1?key=secret",
+            1.0,
+            true,
+        );
+        let json = serde_json::to_string(&usage).unwrap();
+        assert!(json.contains("https://a.example/v1"));
+        assert!(!json.contains("secret"));
+        assert!(!json.contains("pw"));
+
+        usage.record(date("2026-06-01"), "https://a.example/v1", 1.0, true);
+        assert_eq!(usage.day_total(date("2025-01-01")).requests, 0);
+        let round_trip: RemoteUsage =
+            serde_json::from_str(&serde_json::to_string(&usage).unwrap()).unwrap();
+        assert_eq!(round_trip, usage);
+    }
+}